@@ -25,6 +25,12 @@ use crate::FlameError;
 
 pub const DEFAULT_MAX_INSTANCES: i32 = i32::MAX;
 pub const DEFAULT_DELAY_RELEASE: Duration = Duration::seconds(60);
+pub const DEFAULT_CONCURRENCY: i32 = 1;
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::seconds(300);
+
+const PACKAGE_URL_SCHEMES: [&str; 3] = ["file://", "http://", "https://"];
+const SHA256_PREFIX: &str = "sha256:";
+const SHA256_HEX_LEN: usize = 64;
 
 pub type SessionID = i64;
 pub type TaskID = i64;
@@ -55,6 +61,7 @@ pub enum Shim {
     Wasm = 2,
     Shell = 3,
     Grpc = 4,
+    Http = 5,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -64,6 +71,16 @@ pub struct ApplicationSchema {
     pub common_data: Option<String>,
 }
 
+/// The sandbox of the application's service: it runs as the user/group, in private
+/// mount/PID/network namespaces with the read-only system directories and a writable
+/// work directory.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ApplicationSandbox {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub allow_network: bool,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Application {
     pub name: String,
@@ -80,6 +97,11 @@ pub struct Application {
     pub max_instances: i32,
     pub delay_release: Duration,
     pub schema: Option<ApplicationSchema>,
+    pub sandbox: Option<ApplicationSandbox>,
+    pub url: Option<String>,
+    pub checksum: Option<String>,
+    /// The max number of tasks run concurrently in one executor.
+    pub concurrency: i32,
 }
 
 #[derive(Clone, Debug)]
@@ -95,6 +117,10 @@ pub struct ApplicationAttributes {
     pub max_instances: i32,
     pub delay_release: Duration,
     pub schema: Option<ApplicationSchema>,
+    pub sandbox: Option<ApplicationSandbox>,
+    pub url: Option<String>,
+    pub checksum: Option<String>,
+    pub concurrency: i32,
 }
 
 impl Default for ApplicationAttributes {
@@ -111,10 +137,35 @@ impl Default for ApplicationAttributes {
             max_instances: DEFAULT_MAX_INSTANCES,
             delay_release: DEFAULT_DELAY_RELEASE,
             schema: Some(ApplicationSchema::default()),
+            sandbox: None,
+            url: None,
+            checksum: None,
+            concurrency: DEFAULT_CONCURRENCY,
         }
     }
 }
 
+/// Validate the package of the application, and return its SHA-256 in lower-case hex;
+/// the url must be `file://`, `http://` or `https://`, and the checksum may have a
+/// `sha256:` prefix.
+pub fn parse_package_checksum(url: &str, checksum: Option<&str>) -> Result<String, FlameError> {
+    if !PACKAGE_URL_SCHEMES.iter().any(|s| url.starts_with(s)) {
+        return Err(FlameError::InvalidConfig(format!(
+            "unsupported package url <{url}>"
+        )));
+    }
+
+    let checksum = checksum.unwrap_or_default().trim();
+    let checksum = checksum.strip_prefix(SHA256_PREFIX).unwrap_or(checksum);
+    if checksum.len() != SHA256_HEX_LEN || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(FlameError::InvalidConfig(format!(
+            "the checksum of package <{url}> must be a SHA-256 in hex"
+        )));
+    }
+
+    Ok(checksum.to_lowercase())
+}
+
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, strum_macros::Display)]
 pub enum SessionState {
     #[default]
@@ -150,7 +201,7 @@ pub enum TaskState {
     Failed = 3,
 }
 
-#[derive(Clone, Debug, Default, Copy)]
+#[derive(Clone, Debug, Default, Copy, PartialEq, Eq)]
 pub struct TaskGID {
     pub ssn_id: SessionID,
     pub task_id: TaskID,
@@ -166,7 +217,17 @@ pub struct Task {
     pub creation_time: DateTime<Utc>,
     pub completion_time: Option<DateTime<Utc>>,
 
+    pub retry_count: i32,
     pub state: TaskState,
+    /// The latest progress of the running task, which is not persisted.
+    pub progress: Option<TaskProgress>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaskProgress {
+    /// The percentage of the task which was done, in [0, 100].
+    pub percentage: f32,
+    pub message: Option<String>,
 }
 
 impl Task {
@@ -218,6 +279,14 @@ pub struct ApplicationContext {
     pub command: Option<String>,
     pub arguments: Vec<String>,
     pub environments: HashMap<String, String>,
+    pub working_directory: Option<String>,
+    pub sandbox: Option<ApplicationSandbox>,
+    pub url: Option<String>,
+    pub checksum: Option<String>,
+    pub concurrency: i32,
+    /// The local directory of the unpacked package, which is resolved by the executor
+    /// manager rather than provided by the application.
+    pub package_dir: Option<String>,
 
     pub shim: Shim,
 }
@@ -249,6 +318,11 @@ pub struct Node {
     pub allocatable: ResourceRequirement,
     pub info: NodeInfo,
     pub state: NodeState,
+    /// The endpoint of the executor manager in the node, e.g. to read the logs.
+    pub endpoint: Option<String>,
+
+    pub unschedulable: bool,
+    pub draining: bool,
 }
 
 impl Node {
@@ -342,7 +416,7 @@ impl ResourceRequirement {
         (self.cpu / unit.cpu).min(self.memory / unit.memory)
     }
 
-    fn parse_memory(s: &str) -> u64 {
+    pub fn parse_memory(s: &str) -> u64 {
         let s = s.to_lowercase();
         let v = s[..s.len() - 1].parse::<u64>().unwrap_or(0);
         let unit = s[s.len() - 1..].to_string();
@@ -422,6 +496,7 @@ impl From<Node> for rpc::Node {
             capacity: Some(node.capacity.into()),
             allocatable: Some(node.allocatable.into()),
             info: Some(node.info.into()),
+            endpoint: node.endpoint,
         });
 
         Self {
@@ -430,7 +505,10 @@ impl From<Node> for rpc::Node {
                 name: node.name.clone(),
                 owner: None,
             }),
-            spec: Some(rpc::NodeSpec {}),
+            spec: Some(rpc::NodeSpec {
+                unschedulable: node.unschedulable,
+                draining: node.draining,
+            }),
             status,
         }
     }
@@ -440,12 +518,16 @@ impl From<rpc::Node> for Node {
     fn from(node: rpc::Node) -> Self {
         let status = node.status.unwrap_or_default();
         let metadata = node.metadata.unwrap_or_default();
+        let spec = node.spec.unwrap_or_default();
         Self {
             name: metadata.name,
             capacity: status.capacity.unwrap_or_default().into(),
             allocatable: status.allocatable.unwrap_or_default().into(),
             info: status.info.unwrap_or_default().into(),
             state: status.state.into(),
+            endpoint: status.endpoint,
+            unschedulable: spec.unschedulable,
+            draining: spec.draining,
         }
     }
 }
@@ -459,6 +541,11 @@ impl Session {
         let task_ptr = TaskPtr::new(task.clone().into());
 
         self.tasks.insert(task.id, task_ptr.clone());
+        for (state, tasks) in self.tasks_index.iter_mut() {
+            if *state != task.state {
+                tasks.remove(&task.id);
+            }
+        }
         self.tasks_index.entry(task.state).or_default();
         self.tasks_index
             .get_mut(&task.state)
@@ -549,6 +636,12 @@ impl TryFrom<rpc::Application> for ApplicationContext {
                 .into_iter()
                 .map(|e| (e.name, e.value))
                 .collect(),
+            working_directory: spec.working_directory.clone(),
+            sandbox: spec.sandbox.map(ApplicationSandbox::from),
+            url: spec.url.clone(),
+            checksum: spec.checksum.clone(),
+            concurrency: spec.concurrency.unwrap_or(DEFAULT_CONCURRENCY),
+            package_dir: None,
             shim: Shim::try_from(spec.shim)
                 .map_err(|_| FlameError::InvalidConfig("shim".to_string()))?,
         })
@@ -639,11 +732,31 @@ impl From<&Task> for rpc::Task {
             state: task.state as i32,
             creation_time: task.creation_time.timestamp(),
             completion_time: task.completion_time.map(|s| s.timestamp()),
+            progress: task.progress.clone().map(rpc::TaskProgress::from),
         });
         rpc::Task {
             metadata,
             spec,
             status,
+            partial_output: None,
+        }
+    }
+}
+
+impl From<TaskProgress> for rpc::TaskProgress {
+    fn from(progress: TaskProgress) -> Self {
+        Self {
+            percentage: progress.percentage,
+            message: progress.message,
+        }
+    }
+}
+
+impl From<rpc::TaskProgress> for TaskProgress {
+    fn from(progress: rpc::TaskProgress) -> Self {
+        Self {
+            percentage: progress.percentage,
+            message: progress.message,
         }
     }
 }
@@ -710,6 +823,26 @@ impl From<rpc::ApplicationSchema> for ApplicationSchema {
     }
 }
 
+impl From<ApplicationSandbox> for rpc::ApplicationSandbox {
+    fn from(sandbox: ApplicationSandbox) -> Self {
+        Self {
+            uid: sandbox.uid,
+            gid: sandbox.gid,
+            allow_network: sandbox.allow_network,
+        }
+    }
+}
+
+impl From<rpc::ApplicationSandbox> for ApplicationSandbox {
+    fn from(sandbox: rpc::ApplicationSandbox) -> Self {
+        Self {
+            uid: sandbox.uid,
+            gid: sandbox.gid,
+            allow_network: sandbox.allow_network,
+        }
+    }
+}
+
 impl TryFrom<rpc::Application> for Application {
     type Error = FlameError;
     fn try_from(app: rpc::Application) -> Result<Self, Self::Error> {
@@ -757,6 +890,10 @@ impl TryFrom<&rpc::Application> for Application {
                 .map(Duration::seconds)
                 .unwrap_or(DEFAULT_DELAY_RELEASE),
             schema: spec.schema.map(ApplicationSchema::from),
+            sandbox: spec.sandbox.map(ApplicationSandbox::from),
+            url: spec.url.clone(),
+            checksum: spec.checksum.clone(),
+            concurrency: spec.concurrency.unwrap_or(DEFAULT_CONCURRENCY),
         })
     }
 }
@@ -786,6 +923,10 @@ impl From<&Application> for rpc::Application {
             max_instances: Some(app.max_instances),
             delay_release: Some(app.delay_release.num_seconds()),
             schema: app.schema.clone().map(rpc::ApplicationSchema::from),
+            sandbox: app.sandbox.clone().map(rpc::ApplicationSandbox::from),
+            url: app.url.clone(),
+            checksum: app.checksum.clone(),
+            concurrency: Some(app.concurrency),
         });
         let metadata = Some(rpc::Metadata {
             id: app.name.clone(),
@@ -827,6 +968,10 @@ impl From<rpc::ApplicationSpec> for ApplicationAttributes {
                 .map(Duration::seconds)
                 .unwrap_or(DEFAULT_DELAY_RELEASE),
             schema: spec.schema.map(ApplicationSchema::from),
+            sandbox: spec.sandbox.map(ApplicationSandbox::from),
+            url: spec.url.clone(),
+            checksum: spec.checksum.clone(),
+            concurrency: spec.concurrency.unwrap_or(DEFAULT_CONCURRENCY),
         }
     }
 }
@@ -942,6 +1087,7 @@ impl From<rpc::Shim> for Shim {
             rpc::Shim::Wasm => Self::Wasm,
             rpc::Shim::Shell => Self::Shell,
             rpc::Shim::Grpc => Self::Grpc,
+            rpc::Shim::Http => Self::Http,
         }
     }
 }
@@ -954,6 +1100,7 @@ impl From<Shim> for rpc::Shim {
             Shim::Wasm => Self::Wasm,
             Shim::Shell => Self::Shell,
             Shim::Grpc => Self::Grpc,
+            Shim::Http => Self::Http,
         }
     }
 }
@@ -1029,4 +1176,30 @@ mod tests {
             assert_eq!(resreq.memory, expected.1);
         }
     }
+
+    #[test]
+    fn test_parse_package_checksum() {
+        let sha256 = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let cases = vec![
+            ("file:///opt/app.tar.gz", Some(sha256), true),
+            (
+                "https://example.com/app.tgz",
+                Some("sha256:9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08"),
+                true,
+            ),
+            ("ftp://example.com/app.tgz", Some(sha256), false),
+            ("http://example.com/app.tgz", None, false),
+            ("http://example.com/app.tgz", Some("9f86d0"), false),
+        ];
+
+        for (url, checksum, valid) in cases {
+            match parse_package_checksum(url, checksum) {
+                Ok(checksum) => {
+                    assert!(valid, "<{url}> should be invalid");
+                    assert_eq!(checksum, sha256);
+                }
+                Err(_) => assert!(!valid, "<{url}> should be valid"),
+            }
+        }
+    }
 }
@@ -15,6 +15,7 @@ use std::fmt::{Display, Formatter};
 use std::fs;
 use std::path::Path;
 
+use chrono::Duration;
 use serde_derive::{Deserialize, Serialize};
 
 use crate::apis::ResourceRequirement;
@@ -26,6 +27,10 @@ const DEFAULT_FLAME_ENDPOINT: &str = "http://127.0.0.1:8080";
 const DEFAULT_SLOT: &str = "cpu=1,mem=2g";
 const DEFAULT_POLICY: &str = "proportion";
 const DEFAULT_STORAGE: &str = "sqlite://flame.db";
+const DEFAULT_NODE_GRACE_PERIOD: i64 = 30;
+const DEFAULT_MAX_TASK_RETRIES: i32 = 3;
+const DEFAULT_IDLE_TIMEOUT: i64 = 60;
+const DEFAULT_WORK_ROOT: &str = "/tmp/flame/work";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct FlameContextYaml {
@@ -34,6 +39,12 @@ struct FlameContextYaml {
     pub slot: String,
     pub policy: String,
     pub storage: String,
+    pub node_grace_period: Option<i64>,
+    pub max_task_retries: Option<i32>,
+    pub idle_timeout: Option<i64>,
+    pub work_root: Option<String>,
+    pub work_quota: Option<String>,
+    pub keep_work_dir_on_failure: Option<bool>,
 }
 
 #[derive(Debug, Clone)]
@@ -43,6 +54,19 @@ pub struct FlameContext {
     pub slot: ResourceRequirement,
     pub policy: String,
     pub storage: String,
+    /// The node is marked as NotReady if it did not sync within this period.
+    pub node_grace_period: Duration,
+    /// The maximum times to re-queue a task whose executor was lost.
+    pub max_task_retries: i32,
+    /// The idle executors are released after this timeout, even if they're still
+    /// needed by the open sessions.
+    pub idle_timeout: Duration,
+    /// The root of the executors' work directories in the node.
+    pub work_root: String,
+    /// The disk quota of each executor's work directory in bytes, 0 means unlimited.
+    pub work_quota: u64,
+    /// Keep the work directory of the executor whose task failed, for debugging.
+    pub keep_work_dir_on_failure: bool,
 }
 
 impl Display for FlameContext {
@@ -59,6 +83,12 @@ impl Default for FlameContext {
             slot: ResourceRequirement::from(&DEFAULT_SLOT.to_string()),
             policy: DEFAULT_POLICY.to_string(),
             storage: DEFAULT_STORAGE.to_string(),
+            node_grace_period: Duration::seconds(DEFAULT_NODE_GRACE_PERIOD),
+            max_task_retries: DEFAULT_MAX_TASK_RETRIES,
+            idle_timeout: Duration::seconds(DEFAULT_IDLE_TIMEOUT),
+            work_root: DEFAULT_WORK_ROOT.to_string(),
+            work_quota: 0,
+            keep_work_dir_on_failure: false,
         }
     }
 }
@@ -87,6 +117,18 @@ impl FlameContext {
             slot: ResourceRequirement::from(&ctx.slot),
             policy: ctx.policy,
             storage: ctx.storage,
+            node_grace_period: Duration::seconds(
+                ctx.node_grace_period.unwrap_or(DEFAULT_NODE_GRACE_PERIOD),
+            ),
+            max_task_retries: ctx.max_task_retries.unwrap_or(DEFAULT_MAX_TASK_RETRIES),
+            idle_timeout: Duration::seconds(ctx.idle_timeout.unwrap_or(DEFAULT_IDLE_TIMEOUT)),
+            work_root: ctx.work_root.unwrap_or(DEFAULT_WORK_ROOT.to_string()),
+            work_quota: ctx
+                .work_quota
+                .filter(|q| !q.is_empty())
+                .map(|q| ResourceRequirement::parse_memory(&q))
+                .unwrap_or_default(),
+            keep_work_dir_on_failure: ctx.keep_work_dir_on_failure.unwrap_or(false),
         };
 
         log::debug!("Load FrameContext from <{fp}>: {ctx}");
@@ -0,0 +1,350 @@
+/*
+Copyright 2025 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tokio::process::Command;
+use tokio::runtime::Handle;
+
+use crate::executor::Executor;
+use common::apis::ResourceRequirement;
+use common::FlameError;
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+const FLAME_CGROUP: &str = "flame";
+// The leaf cgroup of the executor manager, as the controllers can't be enabled for the
+// children of a (non-root) cgroup with processes in it.
+const MANAGER_CGROUP: &str = "manager";
+const CGROUP_CONTROLLERS: [&str; 2] = ["cpu", "memory"];
+const CPU_PERIOD: u64 = 100_000;
+const UNLIMITED: &str = "max";
+
+const CGROUP_REMOVE_RETRIES: u32 = 10;
+const CGROUP_REMOVE_INTERVAL: Duration = Duration::from_millis(10);
+// Writing 0 into `cgroup.procs` moves the writing process itself.
+const SELF_PID: &[u8] = b"0";
+
+/// The cgroup (v2) of an executor at `<cgroup of executor manager>/flame/<executor id>`,
+/// which limits the CPU and memory of the processes started by its shim according to the
+/// resource requirement of the executor; the processes are killed and the cgroup is
+/// removed when it's dropped.
+///
+/// If the executor requires resources but they can't be limited, e.g. cgroup v2 is not
+/// mounted or not writable in a container, the executor fails to start.
+pub struct Cgroup {
+    executor_id: String,
+    // The path of the cgroup, None if the resources of the executor are not limited.
+    path: Option<PathBuf>,
+    memory_limit: u64,
+    // The number of OOM kills in the cgroup when it was checked last time.
+    oom_kills: AtomicU64,
+}
+
+impl Cgroup {
+    pub fn new(executor: &Executor) -> Result<Self, FlameError> {
+        let resreq = &executor.resreq;
+        let path = match (resreq.cpu, resreq.memory) {
+            (0, 0) => None,
+            _ => Some(create(&executor.id, resreq).map_err(|e| {
+                FlameError::InvalidState(format!(
+                    "failed to limit the resources of executor <{}>: {e}",
+                    executor.id
+                ))
+            })?),
+        };
+
+        let cgroup = Self {
+            executor_id: executor.id.clone(),
+            path,
+            memory_limit: executor.resreq.memory,
+            oom_kills: AtomicU64::new(0),
+        };
+        cgroup
+            .oom_kills
+            .store(cgroup.read_oom_kills(), Ordering::Relaxed);
+
+        Ok(cgroup)
+    }
+
+    /// Start the process of the command in the cgroup: it moves itself into the cgroup
+    /// before exec, so neither it nor its children escape the limits.
+    ///
+    /// The `cgroup.procs` is opened by the executor manager, as the kernel checks the
+    /// permission of migration by the credentials of the opener; so the process can
+    /// still move itself after it switched to the user of the sandbox.
+    #[allow(unsafe_code)]
+    pub fn attach(&self, cmd: &mut Command) -> Result<(), FlameError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let procs = path.join("cgroup.procs");
+        let file = OpenOptions::new()
+            .write(true)
+            .open(&procs)
+            .map_err(|e| io_error(&procs, e))?;
+
+        // SAFETY: the closure only writes the opened file, which is async-signal-safe
+        // and does not allocate.
+        unsafe {
+            cmd.pre_exec(move || (&file).write_all(SELF_PID));
+        }
+
+        Ok(())
+    }
+
+    /// If any process of the executor was killed by OOM since the last check, it's
+    /// the root cause of the error, e.g. the service exited; attach it to the error.
+    pub fn check_oom(&self, e: FlameError) -> FlameError {
+        let oom_kills = self.read_oom_kills();
+        if oom_kills <= self.oom_kills.fetch_max(oom_kills, Ordering::Relaxed) {
+            return e;
+        }
+
+        FlameError::Internal(format!(
+            "killed by OOM, the memory limit is {} bytes: {e}",
+            self.memory_limit
+        ))
+    }
+
+    fn read_oom_kills(&self) -> u64 {
+        let Some(path) = &self.path else {
+            return 0;
+        };
+
+        fs::read_to_string(path.join("memory.events"))
+            .unwrap_or_default()
+            .lines()
+            .find_map(|line| line.strip_prefix("oom_kill "))
+            .and_then(|n| n.trim().parse::<u64>().ok())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        // Kill the remaining processes, e.g. the children of the service; the cgroup can
+        // be removed only after all of them exited, which is retried in background.
+        let _ = write_file(&path.join("cgroup.kill"), "1");
+        if remove(&self.executor_id, path) {
+            return;
+        }
+
+        let (executor_id, path) = (self.executor_id.clone(), path.clone());
+        let Ok(handle) = Handle::try_current() else {
+            log::warn!(
+                "Failed to remove the cgroup <{}> of executor <{executor_id}>",
+                path.display()
+            );
+            return;
+        };
+        handle.spawn(async move {
+            for _ in 0..CGROUP_REMOVE_RETRIES {
+                tokio::time::sleep(CGROUP_REMOVE_INTERVAL).await;
+                if remove(&executor_id, &path) {
+                    return;
+                }
+            }
+
+            log::warn!(
+                "Failed to remove the cgroup <{}> of executor <{executor_id}>",
+                path.display()
+            );
+        });
+    }
+}
+
+/// Remove the cgroup, return whether it's removed or not found.
+fn remove(executor_id: &str, path: &Path) -> bool {
+    match fs::remove_dir(path) {
+        Ok(_) => {
+            log::debug!("The cgroup of executor <{executor_id}> was removed");
+            true
+        }
+        Err(e) => e.kind() == ErrorKind::NotFound,
+    }
+}
+
+/// Set up the cgroup of the executors, so the resources of the executors can be limited;
+/// it's done once when the first executor is created, but can be done in advance to
+/// report the error early.
+pub fn init() -> Result<(), FlameError> {
+    flame_cgroup().map(|_| ())
+}
+
+fn flame_cgroup() -> Result<&'static Path, FlameError> {
+    static FLAME_CGROUP_PATH: OnceLock<Result<PathBuf, String>> = OnceLock::new();
+
+    FLAME_CGROUP_PATH
+        .get_or_init(|| setup().map_err(|e| e.to_string()))
+        .as_deref()
+        .map_err(|e| FlameError::InvalidConfig(e.to_string()))
+}
+
+/// Create the cgroup of the executors under the cgroup of the executor manager, and
+/// enable the controllers for it.
+fn setup() -> Result<PathBuf, FlameError> {
+    let root = Path::new(CGROUP_ROOT);
+    if !root.join("cgroup.controllers").exists() {
+        return Err(FlameError::InvalidConfig(format!(
+            "cgroup v2 is not mounted at <{CGROUP_ROOT}>"
+        )));
+    }
+
+    let content = fs::read_to_string("/proc/self/cgroup")
+        .map_err(|e| FlameError::Internal(format!("failed to read cgroup: {e}")))?;
+    let current = current_cgroup(&content).ok_or(FlameError::InvalidConfig(
+        "cgroup v2 is not used by executor manager".to_string(),
+    ))?;
+    let current = root.join(current.trim_start_matches('/'));
+
+    // The controllers can't be enabled if there're processes in the cgroup, e.g. the
+    // executor manager is in the root cgroup of a container; move them into a leaf
+    // cgroup. The root cgroup of the host has no such restriction.
+    if enable_controllers(&current).is_err() && current.join("cgroup.type").exists() {
+        let leaf = current.join(MANAGER_CGROUP);
+        fs::create_dir_all(&leaf).map_err(|e| io_error(&leaf, e))?;
+        move_processes(&current, &leaf)?;
+        enable_controllers(&current)?;
+    }
+
+    let parent = current.join(FLAME_CGROUP);
+    fs::create_dir_all(&parent).map_err(|e| io_error(&parent, e))?;
+    enable_controllers(&parent)?;
+
+    Ok(parent)
+}
+
+/// The path of the cgroup (v2) in `/proc/self/cgroup`, e.g. `0::/system.slice/flame.service`.
+fn current_cgroup(content: &str) -> Option<&str> {
+    content.lines().find_map(|line| line.strip_prefix("0::"))
+}
+
+fn move_processes(from: &Path, to: &Path) -> Result<(), FlameError> {
+    let procs = from.join("cgroup.procs");
+    let pids = fs::read_to_string(&procs).map_err(|e| io_error(&procs, e))?;
+    for pid in pids.split_whitespace() {
+        match fs::write(to.join("cgroup.procs"), pid) {
+            Ok(_) => {}
+            // The process exited.
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => {}
+            Err(e) => return Err(io_error(&to.join("cgroup.procs"), e)),
+        }
+    }
+
+    Ok(())
+}
+
+fn create(executor_id: &str, resreq: &ResourceRequirement) -> Result<PathBuf, FlameError> {
+    let path = flame_cgroup()?.join(executor_id);
+    fs::create_dir_all(&path).map_err(|e| io_error(&path, e))?;
+
+    write_file(&path.join("cpu.max"), &cpu_max(resreq))?;
+    write_file(&path.join("memory.max"), &memory_max(resreq))?;
+    // Disable swap, so the memory limit is enforced by OOM kill; it's not
+    // available if swap accounting is disabled.
+    let _ = write_file(&path.join("memory.swap.max"), "0");
+
+    Ok(path)
+}
+
+fn enable_controllers(path: &Path) -> Result<(), FlameError> {
+    let subtree_control = path.join("cgroup.subtree_control");
+    let enabled =
+        fs::read_to_string(&subtree_control).map_err(|e| io_error(&subtree_control, e))?;
+    let enabled: Vec<&str> = enabled.split_whitespace().collect();
+
+    for controller in CGROUP_CONTROLLERS {
+        if !enabled.contains(&controller) {
+            write_file(&subtree_control, &format!("+{controller}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The CPU quota in the period, e.g. `200000 100000` for 2 CPUs.
+fn cpu_max(resreq: &ResourceRequirement) -> String {
+    match resreq.cpu {
+        0 => format!("{UNLIMITED} {CPU_PERIOD}"),
+        cpu => format!("{} {CPU_PERIOD}", cpu * CPU_PERIOD),
+    }
+}
+
+fn memory_max(resreq: &ResourceRequirement) -> String {
+    match resreq.memory {
+        0 => UNLIMITED.to_string(),
+        memory => memory.to_string(),
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> Result<(), FlameError> {
+    fs::write(path, content).map_err(|e| io_error(path, e))
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> FlameError {
+    FlameError::Internal(format!("failed to update <{}>: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_resreq(cpu: u64, memory: u64) -> ResourceRequirement {
+        ResourceRequirement { cpu, memory }
+    }
+
+    #[test]
+    fn test_cpu_max() {
+        assert_eq!(cpu_max(&new_resreq(0, 0)), "max 100000");
+        assert_eq!(cpu_max(&new_resreq(1, 0)), "100000 100000");
+        assert_eq!(cpu_max(&new_resreq(2, 1024)), "200000 100000");
+    }
+
+    #[test]
+    fn test_memory_max() {
+        assert_eq!(memory_max(&new_resreq(0, 0)), "max");
+        assert_eq!(memory_max(&new_resreq(2, 1024)), "1024");
+    }
+
+    #[test]
+    fn test_current_cgroup() {
+        assert_eq!(
+            current_cgroup("0::/system.slice/flame.service\n"),
+            Some("/system.slice/flame.service")
+        );
+        assert_eq!(current_cgroup("1:cpu:/\n0::/\n"), Some("/"));
+        assert_eq!(current_cgroup("4:memory:/flame\n"), None);
+    }
+
+    #[test]
+    fn test_no_limits() -> Result<(), FlameError> {
+        // No cgroup is required if the executor does not require any resources.
+        let cgroup = Cgroup::new(&Executor::for_test("exe-cgroup"))?;
+        assert!(cgroup.path.is_none());
+
+        let mut cmd = Command::new("true");
+        cgroup.attach(&mut cmd)?;
+
+        Ok(())
+    }
+}
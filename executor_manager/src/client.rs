@@ -12,38 +12,79 @@ limitations under the License.
 */
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use lazy_static::lazy_static;
-use tonic::transport::Channel;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::{Channel, Endpoint};
+use tonic::Streaming;
 
 use self::rpc::backend_client::BackendClient as FlameBackendClient;
+use self::rpc::node_request::Request as NodeRequestKind;
+use self::rpc::node_response::Response as NodeResponseKind;
 use self::rpc::{
-    BindExecutorCompletedRequest, BindExecutorRequest, CompleteTaskRequest, LaunchTaskRequest,
-    RegisterExecutorRequest, RegisterNodeRequest, ReleaseNodeRequest, SyncNodeRequest,
-    UnbindExecutorCompletedRequest, UnbindExecutorRequest,
+    BindExecutorCompletedRequest, CompleteTaskRequest, DrainNodeRequest, NodeRequest, NodeResponse,
+    RegisterExecutorRequest, RegisterNodeRequest, ReleaseExecutorCompletedRequest,
+    ReleaseNodeRequest, ReportTaskRequest, SyncNodeRequest, SyncNodeResponse,
+    UnbindExecutorCompletedRequest, UnregisterExecutorRequest,
 };
 use ::rpc::flame as rpc;
 
-use crate::executor::Executor;
+use crate::executor::{Executor, TaskResult};
+use crate::shims::TaskUpdate;
 use common::apis::{self, Node, ResourceRequirement, SessionContext, TaskContext};
 use common::ctx::FlameContext;
 use common::{lock_ptr, FlameError};
 
 pub type FlameClient = FlameBackendClient<Channel>;
 
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+const KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(20);
+// The requests buffered in the node stream, e.g. by the executors of the node.
+const NODE_STREAM_BUFFER_SIZE: usize = 128;
+
+/// The work assigned to the executor, which is pushed by the server.
+#[derive(Clone, Debug)]
+pub enum Assignment {
+    /// Bind the executor to the session.
+    Bind(Box<SessionContext>),
+    /// Unbind the executor from its session, after its tasks completed.
+    Unbind,
+    /// Release the idle executor.
+    Release,
+    /// Launch the tasks to the bound executor.
+    Launch(Vec<TaskContext>),
+}
+
+/// The client of the server: the node and its executors share one stream to the server,
+/// which carries the requests of the node and the work pushed to its executors.
 #[derive(Clone, Debug)]
 pub struct BackendClient {
-    client: FlameClient,
+    stream: Arc<NodeStream>,
 }
 
 impl BackendClient {
-    pub async fn new(ctx: &FlameContext) -> Result<Self, FlameError> {
-        let client = FlameBackendClient::connect(ctx.endpoint.clone())
-            .await
-            .map_err(|_e| FlameError::Network("tonic connection".to_string()))?;
-
-        Ok(Self { client })
+    /// Create the client of the server, which is connected lazily and reconnected
+    /// after the connection was lost, e.g. the server restarted.
+    pub fn new(ctx: &FlameContext) -> Result<Self, FlameError> {
+        let channel = Endpoint::from_shared(ctx.endpoint.clone())
+            .map_err(|e| {
+                FlameError::InvalidConfig(format!("invalid endpoint <{}>: {e}", ctx.endpoint))
+            })?
+            .connect_timeout(CONNECT_TIMEOUT)
+            // Detect the lost connection of the stream, which waits for the pushed work.
+            .http2_keep_alive_interval(KEEP_ALIVE_INTERVAL)
+            .keep_alive_timeout(KEEP_ALIVE_TIMEOUT)
+            .keep_alive_while_idle(true)
+            .connect_lazy();
+
+        Ok(Self {
+            stream: Arc::new(NodeStream::new(FlameBackendClient::new(channel))),
+        })
     }
 
     pub async fn register_node(&mut self, node: &Node) -> Result<(), FlameError> {
@@ -51,10 +92,7 @@ impl BackendClient {
             node: Some(node.clone().into()),
         };
 
-        self.client
-            .register_node(req)
-            .await
-            .map_err(FlameError::from)?;
+        self.stream.call(NodeRequestKind::RegisterNode(req)).await?;
 
         Ok(())
     }
@@ -63,22 +101,31 @@ impl BackendClient {
         &mut self,
         node: &Node,
         executors: Vec<Executor>,
-    ) -> Result<Vec<Executor>, FlameError> {
+    ) -> Result<(Node, Vec<Executor>), FlameError> {
         let req = SyncNodeRequest {
             node: Some(node.clone().into()),
             executors: executors.into_iter().map(rpc::Executor::from).collect(),
         };
 
-        let resp = self.client.sync_node(req).await.map_err(FlameError::from)?;
+        let resp = match self.stream.call(NodeRequestKind::SyncNode(req)).await? {
+            NodeResponseKind::SyncNode(resp) => resp,
+            resp => return Err(unexpected_response(resp)),
+        };
 
-        let executors = resp
-            .into_inner()
-            .executors
-            .into_iter()
-            .map(rpc::Executor::into)
-            .collect();
+        Ok(node_executors(node, resp))
+    }
 
-        Ok(executors)
+    /// Wait for the executors of the node pushed by the server, e.g. after they were
+    /// created or released; it fails after the stream was closed, so the node is synced
+    /// again by a new stream.
+    pub async fn watch_node(&self, node: &Node) -> Result<(Node, Vec<Executor>), FlameError> {
+        let mut events = self.stream.events.lock().await;
+        match events.recv().await {
+            Some(NodeEvent::Synced(resp)) => Ok(node_executors(node, *resp)),
+            Some(NodeEvent::Closed) | None => {
+                Err(FlameError::Network("node stream closed".to_string()))
+            }
+        }
     }
 
     pub async fn release_node(&mut self, node: &Node) -> Result<(), FlameError> {
@@ -86,10 +133,20 @@ impl BackendClient {
             node_name: node.name.clone(),
         };
 
-        self.client
-            .release_node(req)
-            .await
-            .map_err(FlameError::from)?;
+        self.stream.call(NodeRequestKind::ReleaseNode(req)).await?;
+
+        Ok(())
+    }
+
+    /// Drain the node: its executors are unbound after the running tasks completed, and
+    /// released forcibly after the timeout.
+    pub async fn drain_node(&mut self, node: &Node, timeout: Duration) -> Result<(), FlameError> {
+        let req = DrainNodeRequest {
+            node_name: node.name.clone(),
+            timeout: Some(timeout.as_secs() as i64),
+        };
+
+        self.stream.call(NodeRequestKind::DrainNode(req)).await?;
 
         Ok(())
     }
@@ -103,26 +160,36 @@ impl BackendClient {
             }),
         };
 
-        self.client
-            .register_executor(req)
-            .await
-            .map_err(FlameError::from)?;
+        self.stream
+            .call(NodeRequestKind::RegisterExecutor(req))
+            .await?;
 
         Ok(())
     }
 
-    pub async fn bind_executor(&mut self, exe: &Executor) -> Result<SessionContext, FlameError> {
-        let req = BindExecutorRequest {
-            executor_id: exe.id.clone(),
-        };
+    /// Wait for the work pushed to the executor by the server, e.g. the session to bind;
+    /// the work is kept for the executor while the stream is reconnected.
+    pub async fn receive(&self, exe: &Executor) -> Result<Assignment, FlameError> {
+        let mailbox = self.stream.mailbox(&exe.id)?;
+        let mut receiver = mailbox.receiver.lock().await;
 
-        let ssn = self
-            .client
-            .bind_executor(req)
+        receiver
+            .recv()
             .await
-            .map_err(FlameError::from)?;
+            .ok_or(FlameError::InvalidState(format!(
+                "executor <{}> was closed",
+                exe.id
+            )))
+    }
 
-        SessionContext::try_from(ssn.into_inner())
+    /// Drop the work of the stopped executor, which is not pushed anymore.
+    pub fn close(&self, executor_id: &str) {
+        match lock_ptr!(self.stream.mailboxes) {
+            Ok(mut mailboxes) => {
+                mailboxes.remove(executor_id);
+            }
+            Err(e) => log::error!("Failed to close executor <{executor_id}>: {e}"),
+        }
     }
 
     pub async fn bind_executor_completed(&mut self, exe: &Executor) -> Result<(), FlameError> {
@@ -130,27 +197,22 @@ impl BackendClient {
             executor_id: exe.id.clone(),
         };
 
-        self.client
-            .bind_executor_completed(req)
-            .await
-            .map_err(FlameError::from)?;
+        self.stream
+            .call(NodeRequestKind::BindExecutorCompleted(req))
+            .await?;
 
         Ok(())
     }
 
-    //
-    // rpc UnregisterExecutor (UnregisterExecutorRequest) returns (Result) {}
-    //
-
-    pub async fn unbind_executor(&mut self, exe: &Executor) -> Result<(), FlameError> {
-        let req = UnbindExecutorRequest {
+    pub async fn unregister_executor(&mut self, exe: &Executor) -> Result<(), FlameError> {
+        let req = UnregisterExecutorRequest {
             executor_id: exe.id.clone(),
         };
 
-        self.client
-            .unbind_executor(req)
-            .await
-            .map_err(FlameError::from)?;
+        self.stream
+            .call(NodeRequestKind::UnregisterExecutor(req))
+            .await?;
+
         Ok(())
     }
 
@@ -159,52 +221,515 @@ impl BackendClient {
             executor_id: exe.id.clone(),
         };
 
-        self.client
-            .unbind_executor_completed(req)
-            .await
-            .map_err(FlameError::from)?;
+        self.stream
+            .call(NodeRequestKind::UnbindExecutorCompleted(req))
+            .await?;
 
         Ok(())
     }
 
-    pub async fn launch_task(&mut self, exe: &Executor) -> Result<Option<TaskContext>, FlameError> {
-        let req = LaunchTaskRequest {
+    pub async fn release_executor_completed(&mut self, exe: &Executor) -> Result<(), FlameError> {
+        let req = ReleaseExecutorCompletedRequest {
             executor_id: exe.id.clone(),
         };
 
-        let resp = self
+        self.stream
+            .call(NodeRequestKind::ReleaseExecutorCompleted(req))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Report the results of the completed tasks of the executor in one call.
+    pub async fn complete_task(
+        &mut self,
+        exe: &Executor,
+        results: &[TaskResult],
+    ) -> Result<(), FlameError> {
+        let req = CompleteTaskRequest {
+            executor_id: exe.id.clone(),
+            results: results
+                .iter()
+                .map(|res| rpc::TaskResult {
+                    task_id: res.task_id.clone(),
+                    task_output: res.output.clone().map(apis::TaskOutput::into),
+                    error: res.error.clone(),
+                })
+                .collect(),
+        };
+
+        self.stream.call(NodeRequestKind::CompleteTask(req)).await?;
+
+        Ok(())
+    }
+
+    /// Report the progress and partial output of the running task to the watchers.
+    pub async fn report_task(
+        &mut self,
+        executor_id: &str,
+        task: &TaskContext,
+        update: TaskUpdate,
+    ) -> Result<(), FlameError> {
+        let req = ReportTaskRequest {
+            executor_id: executor_id.to_string(),
+            session_id: task.session_id.clone(),
+            task_id: task.task_id.clone(),
+            progress: update.progress.map(rpc::TaskProgress::from),
+            partial_output: update.partial_output.map(apis::TaskOutput::into),
+        };
+
+        self.stream.call(NodeRequestKind::ReportTask(req)).await?;
+
+        Ok(())
+    }
+}
+
+/// The stream of the node to the server; it's connected on demand, and connected again
+/// after it was closed, e.g. the server restarted.
+#[derive(Debug)]
+struct NodeStream {
+    client: FlameClient,
+    connection: tokio::sync::Mutex<Option<NodeConnection>>,
+    next_id: AtomicU64,
+    /// The work pushed to the executors, which is kept across the connections.
+    mailboxes: Arc<Mutex<HashMap<String, Arc<Mailbox>>>>,
+    /// The events of the node, e.g. its executors were pushed.
+    events: tokio::sync::Mutex<mpsc::UnboundedReceiver<NodeEvent>>,
+    event_sender: mpsc::UnboundedSender<NodeEvent>,
+}
+
+#[derive(Clone, Debug)]
+struct NodeConnection {
+    sender: mpsc::Sender<NodeRequest>,
+    calls: Arc<Mutex<PendingCalls>>,
+}
+
+/// The requests waiting for their responses in the stream.
+#[derive(Debug, Default)]
+struct PendingCalls {
+    closed: bool,
+    calls: HashMap<u64, oneshot::Sender<NodeResponseKind>>,
+}
+
+#[derive(Debug)]
+struct Mailbox {
+    sender: mpsc::UnboundedSender<Assignment>,
+    receiver: tokio::sync::Mutex<mpsc::UnboundedReceiver<Assignment>>,
+}
+
+#[derive(Debug)]
+enum NodeEvent {
+    Synced(Box<SyncNodeResponse>),
+    Closed,
+}
+
+impl NodeStream {
+    fn new(client: FlameClient) -> Self {
+        let (event_sender, events) = mpsc::unbounded_channel();
+        Self {
+            client,
+            connection: tokio::sync::Mutex::new(None),
+            next_id: AtomicU64::new(1),
+            mailboxes: Arc::new(Mutex::new(HashMap::new())),
+            events: tokio::sync::Mutex::new(events),
+            event_sender,
+        }
+    }
+
+    /// Send the request by the stream and wait for its response; the error of the
+    /// request is returned as a network error.
+    async fn call(&self, request: NodeRequestKind) -> Result<NodeResponseKind, FlameError> {
+        let conn = self.connect().await?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut pending = lock_ptr!(conn.calls)?;
+            if pending.closed {
+                return Err(FlameError::Network("node stream closed".to_string()));
+            }
+            pending.calls.insert(id, tx);
+        }
+
+        let req = NodeRequest {
+            id,
+            request: Some(request),
+        };
+        if conn.sender.send(req).await.is_err() {
+            lock_ptr!(conn.calls)?.calls.remove(&id);
+            return Err(FlameError::Network("node stream closed".to_string()));
+        }
+
+        match rx.await {
+            Ok(NodeResponseKind::Result(res)) if res.return_code != 0 => {
+                Err(FlameError::Network(res.message.unwrap_or_default()))
+            }
+            Ok(resp) => Ok(resp),
+            Err(_) => Err(FlameError::Network("node stream closed".to_string())),
+        }
+    }
+
+    async fn connect(&self) -> Result<NodeConnection, FlameError> {
+        let mut connection = self.connection.lock().await;
+        if let Some(conn) = connection.as_ref() {
+            if !lock_ptr!(conn.calls)?.closed {
+                return Ok(conn.clone());
+            }
+        }
+
+        let (sender, rx) = mpsc::channel(NODE_STREAM_BUFFER_SIZE);
+        let inbound = self
             .client
-            .launch_task(req)
+            .clone()
+            .connect_node(ReceiverStream::new(rx))
             .await
-            .map_err(FlameError::from)?;
+            .map_err(FlameError::from)?
+            .into_inner();
+
+        let conn = NodeConnection {
+            sender,
+            calls: Arc::new(Mutex::new(PendingCalls::default())),
+        };
+        let receiver = Receiver {
+            calls: conn.calls.clone(),
+            mailboxes: self.mailboxes.clone(),
+            events: self.event_sender.clone(),
+        };
+        tokio::spawn(receiver.run(inbound));
+        log::info!("The node stream is connected.");
 
-        if let Some(t) = resp.into_inner().task {
-            return Ok(Some(TaskContext::try_from(t)?));
+        *connection = Some(conn.clone());
+
+        Ok(conn)
+    }
+
+    fn mailbox(&self, executor_id: &str) -> Result<Arc<Mailbox>, FlameError> {
+        mailbox(&self.mailboxes, executor_id)
+    }
+}
+
+/// Get the mailbox of the executor, which is created by the first work pushed to it or
+/// its first receiving; so the work pushed before the executor waits for it is kept.
+fn mailbox(
+    mailboxes: &Mutex<HashMap<String, Arc<Mailbox>>>,
+    executor_id: &str,
+) -> Result<Arc<Mailbox>, FlameError> {
+    let mut mailboxes = lock_ptr!(mailboxes)?;
+    let mailbox = mailboxes.entry(executor_id.to_string()).or_insert_with(|| {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Arc::new(Mailbox {
+            sender,
+            receiver: tokio::sync::Mutex::new(receiver),
+        })
+    });
+
+    Ok(mailbox.clone())
+}
+
+/// Dispatch the messages of the stream: the responses to their requests, and the work
+/// pushed by the server to the node or its executors.
+struct Receiver {
+    calls: Arc<Mutex<PendingCalls>>,
+    mailboxes: Arc<Mutex<HashMap<String, Arc<Mailbox>>>>,
+    events: mpsc::UnboundedSender<NodeEvent>,
+}
+
+impl Receiver {
+    /// Receive the messages until the stream was closed; the pending requests are
+    /// failed after that, and the node is notified to sync again.
+    async fn run(self, mut inbound: Streaming<NodeResponse>) {
+        loop {
+            match inbound.message().await {
+                Ok(Some(resp)) => {
+                    if let Err(e) = self.dispatch(resp) {
+                        log::error!("Failed to dispatch node response: {e}");
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    log::warn!("The node stream is broken: {e}");
+                    break;
+                }
+            }
         }
 
-        Ok(None)
+        if let Ok(mut pending) = lock_ptr!(self.calls) {
+            pending.closed = true;
+            pending.calls.clear();
+        }
+        let _ = self.events.send(NodeEvent::Closed);
     }
 
-    pub async fn complete_task(&mut self, exe: &Executor) -> Result<(), FlameError> {
-        let task = exe
-            .task
-            .clone()
-            .ok_or(FlameError::InvalidState("no task in executor".to_string()))?;
+    fn dispatch(&self, resp: NodeResponse) -> Result<(), FlameError> {
+        let Some(response) = resp.response else {
+            return Ok(());
+        };
 
-        let req = CompleteTaskRequest {
-            executor_id: exe.id.clone(),
-            task_output: task.output.map(apis::TaskOutput::into),
+        // The response of the request.
+        if resp.id != 0 {
+            let call = lock_ptr!(self.calls)?.calls.remove(&resp.id);
+            if let Some(call) = call {
+                // The request was cancelled, e.g. the executor was stopped.
+                let _ = call.send(response);
+            }
+            return Ok(());
+        }
+
+        let (executor_id, assignment) = match response {
+            NodeResponseKind::SyncNode(resp) => {
+                let _ = self.events.send(NodeEvent::Synced(Box::new(resp)));
+                return Ok(());
+            }
+            NodeResponseKind::BindExecutor(resp) => {
+                let executor_id = resp.executor_id.clone();
+                (
+                    executor_id,
+                    Assignment::Bind(Box::new(SessionContext::try_from(resp)?)),
+                )
+            }
+            NodeResponseKind::UnbindExecutor(resp) => (resp.executor_id, Assignment::Unbind),
+            NodeResponseKind::ReleaseExecutor(resp) => (resp.executor_id, Assignment::Release),
+            NodeResponseKind::LaunchTask(resp) => {
+                let tasks = resp
+                    .tasks
+                    .into_iter()
+                    .map(TaskContext::try_from)
+                    .collect::<Result<_, _>>()?;
+                (resp.executor_id, Assignment::Launch(tasks))
+            }
+            resp => return Err(unexpected_response(resp)),
         };
 
-        self.client
-            .complete_task(req)
-            .await
-            .map_err(FlameError::from)?;
+        let mailbox = mailbox(&self.mailboxes, &executor_id)?;
+        // The mailbox is kept until the executor is closed.
+        let _ = mailbox.sender.send(assignment);
 
         Ok(())
     }
 }
+
+fn node_executors(node: &Node, resp: SyncNodeResponse) -> (Node, Vec<Executor>) {
+    let node = resp.node.map(Node::from).unwrap_or(node.clone());
+    let executors = resp
+        .executors
+        .into_iter()
+        .map(rpc::Executor::into)
+        .collect();
+
+    (node, executors)
+}
+
+fn unexpected_response(resp: NodeResponseKind) -> FlameError {
+    FlameError::Internal(format!("unexpected response of node stream: {resp:?}"))
+}
 // rpc UnbindExecutor (UnbindExecutorRequest) returns (Result) {}
 //
 // rpc LaunchTask (LaunchTaskRequest) returns (Task) {}
 // rpc CompleteTask(CompleteTaskRequest) returns (Result) {}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use self::rpc::backend_server::{Backend, BackendServer};
+    use self::rpc::{LaunchTaskResponse, ReleaseExecutorResponse, UnbindExecutorResponse};
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tokio_stream::StreamExt;
+    use tonic::codec::{Codec, ProstCodec};
+    use tonic::{Request, Response, Status};
+
+    type Pusher = mpsc::Sender<Result<NodeResponse, Status>>;
+
+    /// A fake server of the node stream, which accepts all requests of the node and
+    /// records them; the work is pushed to the node by the test.
+    #[derive(Clone, Default)]
+    pub(crate) struct FakeBackend {
+        requests: Arc<Mutex<Vec<NodeRequestKind>>>,
+        pusher: Arc<Mutex<Option<Pusher>>>,
+    }
+
+    impl FakeBackend {
+        /// Serve the fake server on a local port, and return the client connected to it.
+        pub(crate) async fn serve() -> (Self, BackendClient) {
+            let backend = Self::default();
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let ctx = FlameContext {
+                endpoint: format!("http://{}", listener.local_addr().unwrap()),
+                ..FlameContext::default()
+            };
+            tokio::spawn(
+                tonic::transport::Server::builder()
+                    .add_service(BackendServer::new(backend.clone()))
+                    .serve_with_incoming(TcpListenerStream::new(listener)),
+            );
+
+            (backend, BackendClient::new(&ctx).unwrap())
+        }
+
+        pub(crate) fn requests(&self) -> Vec<NodeRequestKind> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        pub(crate) async fn push(&self, response: NodeResponseKind) {
+            let pusher = self.pusher.lock().unwrap().clone().unwrap();
+            pusher.send(Ok(pushed(response))).await.unwrap();
+        }
+    }
+
+    #[tonic::async_trait]
+    impl Backend for FakeBackend {
+        type ConnectNodeStream = ReceiverStream<Result<NodeResponse, Status>>;
+
+        async fn connect_node(
+            &self,
+            req: Request<Streaming<NodeRequest>>,
+        ) -> Result<Response<Self::ConnectNodeStream>, Status> {
+            let mut inbound = req.into_inner();
+            let (tx, rx) = mpsc::channel(NODE_STREAM_BUFFER_SIZE);
+            *self.pusher.lock().unwrap() = Some(tx.clone());
+
+            let requests = self.requests.clone();
+            tokio::spawn(async move {
+                while let Some(Ok(req)) = inbound.next().await {
+                    requests.lock().unwrap().extend(req.request);
+                    let resp = NodeResponse {
+                        id: req.id,
+                        response: Some(NodeResponseKind::Result(rpc::Result {
+                            return_code: 0,
+                            message: None,
+                        })),
+                    };
+                    if tx.send(Ok(resp)).await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            Ok(Response::new(ReceiverStream::new(rx)))
+        }
+    }
+
+    fn new_receiver() -> (Receiver, mpsc::UnboundedReceiver<NodeEvent>) {
+        let (events, rx) = mpsc::unbounded_channel();
+        let receiver = Receiver {
+            calls: Arc::new(Mutex::new(PendingCalls::default())),
+            mailboxes: Arc::new(Mutex::new(HashMap::new())),
+            events,
+        };
+
+        (receiver, rx)
+    }
+
+    fn pushed(response: NodeResponseKind) -> NodeResponse {
+        NodeResponse {
+            id: 0,
+            response: Some(response),
+        }
+    }
+
+    fn received(receiver: &Receiver, executor_id: &str) -> Vec<Assignment> {
+        let mailbox = mailbox(&receiver.mailboxes, executor_id).unwrap();
+        let mut mailbox = mailbox.receiver.try_lock().unwrap();
+        let mut res = vec![];
+        while let Ok(assignment) = mailbox.try_recv() {
+            res.push(assignment);
+        }
+
+        res
+    }
+
+    #[test]
+    fn test_dispatch_responses() -> Result<(), FlameError> {
+        let (receiver, _events) = new_receiver();
+        let (tx, mut rx) = oneshot::channel();
+        lock_ptr!(receiver.calls)?.calls.insert(1, tx);
+
+        // The response goes to its request, instead of the mailboxes.
+        receiver.dispatch(NodeResponse {
+            id: 1,
+            response: Some(NodeResponseKind::UnbindExecutor(UnbindExecutorResponse {
+                executor_id: "exe-1".to_string(),
+            })),
+        })?;
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(NodeResponseKind::UnbindExecutor(_))
+        ));
+        assert!(lock_ptr!(receiver.calls)?.calls.is_empty());
+        assert!(received(&receiver, "exe-1").is_empty());
+
+        // The response of a cancelled request is dropped.
+        receiver.dispatch(NodeResponse {
+            id: 2,
+            response: Some(NodeResponseKind::UnbindExecutor(UnbindExecutorResponse {
+                executor_id: "exe-1".to_string(),
+            })),
+        })?;
+        assert!(received(&receiver, "exe-1").is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_pushed_work() -> Result<(), FlameError> {
+        let (receiver, mut events) = new_receiver();
+
+        receiver.dispatch(pushed(NodeResponseKind::SyncNode(SyncNodeResponse {
+            node: None,
+            executors: vec![],
+        })))?;
+        assert!(matches!(events.try_recv(), Ok(NodeEvent::Synced(_))));
+
+        // The work is kept in the mailbox of its executor until it's received.
+        receiver.dispatch(pushed(NodeResponseKind::LaunchTask(LaunchTaskResponse {
+            tasks: vec![],
+            executor_id: "exe-1".to_string(),
+        })))?;
+        receiver.dispatch(pushed(NodeResponseKind::UnbindExecutor(
+            UnbindExecutorResponse {
+                executor_id: "exe-1".to_string(),
+            },
+        )))?;
+        receiver.dispatch(pushed(NodeResponseKind::ReleaseExecutor(
+            ReleaseExecutorResponse {
+                executor_id: "exe-2".to_string(),
+            },
+        )))?;
+
+        let work = received(&receiver, "exe-1");
+        assert_eq!(work.len(), 2);
+        assert!(matches!(&work[0], Assignment::Launch(tasks) if tasks.is_empty()));
+        assert!(matches!(work[1], Assignment::Unbind));
+        let work = received(&receiver, "exe-2");
+        assert!(matches!(work[..], [Assignment::Release]));
+
+        // The results are not pushed by the server.
+        let res = receiver.dispatch(pushed(NodeResponseKind::Result(rpc::Result {
+            return_code: 0,
+            message: None,
+        })));
+        assert!(res.is_err());
+        assert!(events.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_closed() -> Result<(), FlameError> {
+        let (receiver, mut events) = new_receiver();
+        let calls = receiver.calls.clone();
+        let (tx, rx) = oneshot::channel();
+        lock_ptr!(calls)?.calls.insert(1, tx);
+
+        // The stream ends without any message.
+        let mut codec = ProstCodec::<NodeRequest, NodeResponse>::default();
+        let inbound = Streaming::new_empty(codec.decoder(), tonic::body::empty_body());
+        receiver.run(inbound).await;
+
+        assert!(lock_ptr!(calls)?.closed);
+        assert!(rx.await.is_err());
+        assert!(matches!(events.recv().await, Some(NodeEvent::Closed)));
+
+        Ok(())
+    }
+}
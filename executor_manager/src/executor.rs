@@ -11,14 +11,17 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use std::collections::{HashSet, VecDeque};
 use std::sync::{Arc, Mutex};
 
+use crate::backoff::Backoff;
 use crate::client::BackendClient;
 use crate::shims::ShimPtr;
 use ::rpc::flame::{self as rpc, ExecutorSpec, ExecutorStatus, Metadata};
 
 use crate::states;
-use common::apis::{ExecutorState, ResourceRequirement, SessionContext, TaskContext};
+use common::apis::{ExecutorState, ResourceRequirement, SessionContext, TaskContext, TaskOutput};
+use common::ptr::{self, MutexPtr};
 use common::FlameError;
 use common::{lock_ptr, trace::TraceFn, trace_fn};
 
@@ -28,7 +31,13 @@ pub struct Executor {
     pub resreq: ResourceRequirement,
     pub node: String,
     pub session: Option<SessionContext>,
-    pub task: Option<TaskContext>,
+    /// The tasks prefetched from the server, which are not invoked yet.
+    pub tasks: VecDeque<TaskContext>,
+    /// The results of the completed tasks, which are not reported to the server yet.
+    pub results: Vec<TaskResult>,
+    /// The tasks held by the running state, e.g. invoked in the shim; it's shared by the
+    /// copies of the executor, so the manager reports them while the state is running.
+    pub held: MutexPtr<HashSet<String>>,
 
     pub shim: Option<ShimPtr>,
 
@@ -37,6 +46,14 @@ pub struct Executor {
 
 pub type ExecutorPtr = Arc<Mutex<Executor>>;
 
+#[derive(Clone, Debug)]
+pub struct TaskResult {
+    pub task_id: String,
+    pub output: Option<TaskOutput>,
+    /// If the task failed, the error message which will be recorded as the task output.
+    pub error: Option<String>,
+}
+
 impl From<rpc::Executor> for Executor {
     fn from(e: rpc::Executor) -> Self {
         Executor::from(&e)
@@ -46,7 +63,7 @@ impl From<rpc::Executor> for Executor {
 impl From<&rpc::Executor> for Executor {
     fn from(e: &rpc::Executor) -> Self {
         let spec = e.spec.clone().unwrap();
-        let status = e.status.unwrap();
+        let status = e.status.clone().unwrap();
         let metadata = e.metadata.clone().unwrap();
 
         let state = rpc::ExecutorState::try_from(status.state).unwrap().into();
@@ -56,7 +73,9 @@ impl From<&rpc::Executor> for Executor {
             resreq: spec.resreq.unwrap().into(),
             node: spec.node.clone(),
             session: None,
-            task: None,
+            tasks: VecDeque::new(),
+            results: vec![],
+            held: ptr::new_ptr(HashSet::new()),
             shim: None,
             state,
         }
@@ -84,6 +103,8 @@ impl From<&Executor> for rpc::Executor {
 
         let status = Some(ExecutorStatus {
             state: rpc::ExecutorState::from(e.state).into(),
+            session_id: e.session.as_ref().map(|ssn| ssn.session_id.clone()),
+            task_ids: e.task_ids(),
         });
 
         rpc::Executor {
@@ -95,6 +116,19 @@ impl From<&Executor> for rpc::Executor {
 }
 
 impl Executor {
+    /// The tasks held by the executor: the prefetched, running and completed but not
+    /// reported ones; the server reclaims them if it was restarted.
+    pub fn task_ids(&self) -> Vec<String> {
+        let mut ids: HashSet<String> = self.tasks.iter().map(|t| t.task_id.clone()).collect();
+        ids.extend(self.results.iter().map(|r| r.task_id.clone()));
+        match self.held.lock() {
+            Ok(held) => ids.extend(held.iter().cloned()),
+            Err(_) => log::error!("Failed to lock the tasks of executor <{}>.", self.id),
+        }
+
+        ids.into_iter().collect()
+    }
+
     pub fn update(&mut self, next: &Executor) {
         log::info!(
             "Update executor <{}> from <{}> to <{}>",
@@ -105,41 +139,81 @@ impl Executor {
         self.state = next.state;
         self.shim = next.shim.clone();
         self.session = next.session.clone();
-        self.task = next.task.clone();
+        self.tasks = next.tasks.clone();
+        self.results = next.results.clone();
     }
 }
 
-pub fn start(client: BackendClient, executor: ExecutorPtr) {
-    tokio::task::spawn(async move {
-        loop {
-            let exec = {
-                let exec = lock_ptr!(executor);
+/// Run the executor until it's released or failed.
+pub async fn run(client: BackendClient, executor: ExecutorPtr) {
+    // Retry the state with backoff if the server is unavailable, e.g. restarting; the
+    // executor is adopted by the server with its tasks when the node is synced again.
+    let mut backoff = Backoff::default();
+    loop {
+        let exec = {
+            let exec = lock_ptr!(executor);
+            match exec {
+                Ok(exec) => exec.clone(),
+                Err(e) => {
+                    log::error!("Failed to lock executor: {e}");
+                    return;
+                }
+            }
+        };
+
+        let mut state = states::from(client.clone(), exec.clone());
+        match state.execute().await {
+            Ok(next_state) => {
+                backoff.reset();
+                let mut exec = lock_ptr!(executor);
                 match exec {
-                    Ok(exec) => exec.clone(),
+                    Ok(mut exec) => {
+                        exec.update(&next_state);
+                        // The executor was released, exit the loop.
+                        if exec.state == ExecutorState::Released {
+                            log::info!("Executor <{}> was released.", exec.id);
+                            return;
+                        }
+                    }
                     Err(e) => {
                         log::error!("Failed to lock executor: {e}");
-                        return;
-                    }
-                }
-            };
-
-            let mut state = states::from(client.clone(), exec.clone());
-            match state.execute().await {
-                Ok(next_state) => {
-                    let mut exec = lock_ptr!(executor);
-                    match exec {
-                        Ok(mut exec) => {
-                            exec.update(&next_state);
-                        }
-                        Err(e) => {
-                            log::error!("Failed to lock executor: {e}");
-                        }
                     }
                 }
-                Err(e) => {
-                    log::error!("Failed to execute: {e}");
+            }
+            Err(e) if state.is_healthy() => {
+                let delay = backoff.next_delay();
+                log::error!(
+                    "Executor <{}> failed to execute, retry in {delay:?}: {e}",
+                    exec.id
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => {
+                // The shim of the executor failed; unregister it from the server.
+                log::error!("Executor <{}> failed: {e}", exec.id);
+                if let Err(e) = client.clone().unregister_executor(&exec).await {
+                    log::error!("Failed to unregister executor <{}>: {e}", exec.id);
                 }
+                return;
             }
         }
-    });
+    }
+}
+
+#[cfg(test)]
+impl Executor {
+    /// A bound executor of the tests, without session, tasks or shim.
+    pub(crate) fn for_test(id: &str) -> Self {
+        Executor {
+            id: id.to_string(),
+            resreq: ResourceRequirement::default(),
+            node: "node-1".to_string(),
+            session: None,
+            tasks: VecDeque::new(),
+            results: vec![],
+            held: ptr::new_ptr(HashSet::new()),
+            shim: None,
+            state: ExecutorState::Bound,
+        }
+    }
 }
@@ -0,0 +1,608 @@
+/*
+Copyright 2025 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use lazy_static::lazy_static;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader as AsyncBufReader};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use ::rpc::flame as rpc;
+use rpc::executor_manager_server::{ExecutorManager, ExecutorManagerServer};
+use rpc::{LogEntry, ReadLogsRequest};
+
+use common::{lock_ptr, trace::TraceFn, trace_fn, FlameError};
+
+pub const LOG_DIRECTORY: &str = "/tmp/flame/logs";
+const LOG_FILE_EXTENSION: &str = "log";
+// The log file of an executor is rotated when it exceeds this size.
+const MAX_LOG_FILE_SIZE: u64 = 10 * 1024 * 1024;
+// The number of rotated log files kept for each executor.
+const MAX_ROTATED_LOG_FILES: usize = 3;
+const LOG_BROADCAST_CAPACITY: usize = 1024;
+// The log writer flushes the files at least once per this number of logs.
+const MAX_LOG_BATCH: usize = 1024;
+
+// The placeholder of the session or task in the log file if there is none.
+const NO_ID: &str = "-";
+
+lazy_static! {
+    // The new logs of all executors in the node with their sequence, for the requests
+    // following the logs.
+    static ref LOG_BROADCAST: broadcast::Sender<(u64, LogEntry)> =
+        broadcast::channel(LOG_BROADCAST_CAPACITY).0;
+
+    // The log files are written by a dedicated thread, so the runtime is not blocked by
+    // the disk; the sequence is assigned under the lock, so the logs are written in order.
+    static ref LOG_WRITER: Mutex<Sender<LogCommand>> = Mutex::new(LogWriter::start());
+}
+
+// The sequence of the logs in the node, which tells the followed logs apart from the
+// ones read from the files; the executors are not kept across the restarts of the
+// executor manager, so it's unique with the executor.
+static LOG_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+pub type ExecutorLogPtr = Arc<ExecutorLog>;
+
+/// The log of an executor: the output of its service is written into a rotating
+/// file, and each line is tagged with the session and task the executor is running.
+pub struct ExecutorLog {
+    executor_id: String,
+    path: PathBuf,
+    context: Mutex<LogContext>,
+}
+
+#[derive(Clone, Default)]
+struct LogContext {
+    session_id: Option<String>,
+    task_id: Option<String>,
+}
+
+impl ExecutorLog {
+    pub fn new_ptr(executor_id: &str) -> ExecutorLogPtr {
+        let path = Path::new(LOG_DIRECTORY).join(format!("{executor_id}.{LOG_FILE_EXTENSION}"));
+
+        Arc::new(Self {
+            executor_id: executor_id.to_string(),
+            path,
+            context: Mutex::new(LogContext::default()),
+        })
+    }
+
+    /// Tag the following logs with the session and task.
+    pub fn set_context(
+        &self,
+        session_id: Option<String>,
+        task_id: Option<String>,
+    ) -> Result<(), FlameError> {
+        let mut context = lock_ptr!(self.context)?;
+        *context = LogContext {
+            session_id,
+            task_id,
+        };
+
+        Ok(())
+    }
+
+    /// Capture the lines of the stream into the log until the stream is closed.
+    pub fn capture<R>(self: &Arc<Self>, stream: &'static str, reader: R)
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let log = self.clone();
+        tokio::spawn(async move {
+            let mut lines = AsyncBufReader::new(reader).split(b'\n');
+            loop {
+                match lines.next_segment().await {
+                    Ok(Some(line)) => {
+                        let line = String::from_utf8_lossy(&line);
+                        if let Err(e) = log.append(stream, line.trim_end_matches('\r')) {
+                            log::warn!(
+                                "Failed to write log of executor <{}>: {e}",
+                                log.executor_id
+                            );
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::debug!(
+                            "Failed to read {stream} of executor <{}>: {e}",
+                            log.executor_id
+                        );
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Append the message of the stream into the log.
+    pub fn append(&self, stream: &str, message: &str) -> Result<(), FlameError> {
+        let context = lock_ptr!(self.context)?.clone();
+        let entry = LogEntry {
+            executor_id: self.executor_id.clone(),
+            session_id: context.session_id.unwrap_or_default(),
+            task_id: context.task_id,
+            stream: stream.to_string(),
+            timestamp: Utc::now().timestamp_millis(),
+            message: message.to_string(),
+        };
+
+        let seq = {
+            let writer = lock_ptr!(LOG_WRITER)?;
+            let seq = LOG_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+            writer
+                .send(LogCommand::Append(self.path.clone(), encode(seq, &entry)))
+                .map_err(|_| FlameError::Internal("log writer was stopped".to_string()))?;
+
+            seq
+        };
+
+        // It's ok that no one is following the logs.
+        let _ = LOG_BROADCAST.send((seq, entry));
+
+        Ok(())
+    }
+}
+
+impl Drop for ExecutorLog {
+    fn drop(&mut self) {
+        // Close the log file after the pending logs were written.
+        if let Ok(writer) = lock_ptr!(LOG_WRITER) {
+            let _ = writer.send(LogCommand::Close(self.path.clone()));
+        }
+    }
+}
+
+enum LogCommand {
+    Append(PathBuf, String),
+    Close(PathBuf),
+    Remove(PathBuf),
+}
+
+struct LogFile {
+    writer: BufWriter<File>,
+    size: u64,
+}
+
+/// The writer of the log files, which runs in its own thread; the logs are buffered and
+/// flushed when there's no pending log.
+#[derive(Default)]
+struct LogWriter {
+    files: HashMap<PathBuf, LogFile>,
+}
+
+impl LogWriter {
+    fn start() -> Sender<LogCommand> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let res = thread::Builder::new()
+            .name("log-writer".to_string())
+            .spawn(move || LogWriter::default().run(rx));
+        if let Err(e) = res {
+            log::error!("Failed to start log writer: {e}");
+        }
+
+        tx
+    }
+
+    fn run(mut self, rx: Receiver<LogCommand>) {
+        while let Ok(cmd) = rx.recv() {
+            self.handle(cmd);
+            for cmd in rx.try_iter().take(MAX_LOG_BATCH) {
+                self.handle(cmd);
+            }
+            self.flush();
+        }
+    }
+
+    fn handle(&mut self, cmd: LogCommand) {
+        match cmd {
+            LogCommand::Append(path, line) => {
+                if let Err(e) = self.append(&path, &line) {
+                    log::warn!("Failed to write log <{}>: {e}", path.display());
+                }
+            }
+            LogCommand::Close(path) => {
+                if let Some(mut file) = self.files.remove(&path) {
+                    let _ = file.writer.flush();
+                }
+            }
+            LogCommand::Remove(path) => {
+                self.files.remove(&path);
+                remove_files(&path);
+            }
+        }
+    }
+
+    fn append(&mut self, path: &Path, line: &str) -> Result<(), FlameError> {
+        let rotated = self
+            .files
+            .get(path)
+            .is_some_and(|f| f.size > 0 && f.size + line.len() as u64 > MAX_LOG_FILE_SIZE);
+        if rotated {
+            if let Some(mut file) = self.files.remove(path) {
+                let _ = file.writer.flush();
+            }
+            rotate(path)?;
+        }
+
+        let file = match self.files.entry(path.to_path_buf()) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| {
+                        FlameError::Internal(format!(
+                            "failed to open log <{}>: {e}",
+                            path.display()
+                        ))
+                    })?;
+                let size = file.metadata().map(|m| m.len()).unwrap_or_default();
+                e.insert(LogFile {
+                    writer: BufWriter::new(file),
+                    size,
+                })
+            }
+        };
+
+        file.writer
+            .write_all(line.as_bytes())
+            .map_err(|e| FlameError::Internal(e.to_string()))?;
+        file.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        for (path, file) in self.files.iter_mut() {
+            if let Err(e) = file.writer.flush() {
+                log::warn!("Failed to flush log <{}>: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// Rotate the log files: <id>.log -> <id>.log.1 -> ... -> <id>.log.N, the oldest one is removed.
+fn rotate(path: &Path) -> Result<(), FlameError> {
+    for i in (1..MAX_ROTATED_LOG_FILES).rev() {
+        let from = rotated_path(path, i);
+        if from.exists() {
+            let _ = fs::rename(&from, rotated_path(path, i + 1));
+        }
+    }
+
+    fs::rename(path, rotated_path(path, 1)).map_err(|e| {
+        FlameError::Internal(format!("failed to rotate log <{}>: {e}", path.display()))
+    })
+}
+
+/// Remove the log files of the executor, e.g. it was released; they're removed after
+/// the pending logs were written.
+pub fn remove(executor_id: &str) {
+    let path = Path::new(LOG_DIRECTORY).join(format!("{executor_id}.{LOG_FILE_EXTENSION}"));
+    let sent = match lock_ptr!(LOG_WRITER) {
+        Ok(writer) => writer.send(LogCommand::Remove(path.clone())).is_ok(),
+        Err(_) => false,
+    };
+    if !sent {
+        remove_files(&path);
+    }
+}
+
+fn remove_files(path: &Path) {
+    let paths = (1..=MAX_ROTATED_LOG_FILES).map(|i| rotated_path(path, i));
+    for path in paths.chain([path.to_path_buf()]) {
+        if let Err(e) = fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to remove log <{}>: {e}", path.display());
+            }
+        }
+    }
+}
+
+fn rotated_path(path: &Path, i: usize) -> PathBuf {
+    PathBuf::from(format!("{}.{i}", path.display()))
+}
+
+/// The log is stored as one line per entry: <seq> <timestamp> <session> <task> <stream> <message>, split by tab.
+fn encode(seq: u64, entry: &LogEntry) -> String {
+    let session_id = match entry.session_id.as_str() {
+        "" => NO_ID,
+        id => id,
+    };
+    format!(
+        "{seq}\t{}\t{}\t{}\t{}\t{}\n",
+        entry.timestamp,
+        session_id,
+        entry.task_id.as_deref().unwrap_or(NO_ID),
+        entry.stream,
+        entry.message
+    )
+}
+
+fn decode(executor_id: &str, line: &str) -> Option<(u64, LogEntry)> {
+    let mut parts = line.splitn(6, '\t');
+    let seq = parts.next()?.parse::<u64>().ok()?;
+    let timestamp = parts.next()?.parse::<i64>().ok()?;
+    let session_id = parts.next()?;
+    let task_id = parts.next()?;
+    let stream = parts.next()?;
+    let message = parts.next()?;
+
+    let entry = LogEntry {
+        executor_id: executor_id.to_string(),
+        session_id: match session_id {
+            NO_ID => String::new(),
+            id => id.to_string(),
+        },
+        task_id: match task_id {
+            NO_ID => None,
+            id => Some(id.to_string()),
+        },
+        stream: stream.to_string(),
+        timestamp,
+        message: message.to_string(),
+    };
+
+    Some((seq, entry))
+}
+
+fn is_matched(req: &ReadLogsRequest, entry: &LogEntry) -> bool {
+    entry.session_id == req.session_id
+        && req
+            .task_id
+            .as_ref()
+            .is_none_or(|id| entry.task_id.as_ref() == Some(id))
+}
+
+/// Read the logs of all executors in the node which match the request, in time order,
+/// with their sequence.
+fn read_logs(req: &ReadLogsRequest) -> Result<Vec<(u64, LogEntry)>, FlameError> {
+    let mut entries = vec![];
+
+    let dir = fs::read_dir(LOG_DIRECTORY).map_err(|e| {
+        FlameError::Internal(format!("failed to read directory <{LOG_DIRECTORY}>: {e}"))
+    })?;
+    for file in dir.flatten() {
+        let path = file.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(LOG_FILE_EXTENSION) {
+            continue;
+        }
+        let Some(executor_id) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+
+        // The rotated files are older, read them first.
+        let mut paths: Vec<PathBuf> = (1..=MAX_ROTATED_LOG_FILES)
+            .rev()
+            .map(|i| rotated_path(&path, i))
+            .collect();
+        paths.push(path.clone());
+
+        for path in paths {
+            let Ok(file) = File::open(&path) else {
+                continue;
+            };
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                match decode(executor_id, &line) {
+                    Some((seq, entry)) if is_matched(req, &entry) => entries.push((seq, entry)),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    entries.sort_by_key(|(_, e)| e.timestamp);
+
+    Ok(entries)
+}
+
+/// The service of the executor manager, which serves the logs of the executors in the node.
+pub struct LogService {}
+
+#[async_trait]
+impl ExecutorManager for LogService {
+    type ReadLogsStream = Pin<Box<dyn Stream<Item = Result<LogEntry, Status>> + Send>>;
+
+    async fn read_logs(
+        &self,
+        req: Request<ReadLogsRequest>,
+    ) -> Result<Response<Self::ReadLogsStream>, Status> {
+        trace_fn!("LogService::read_logs");
+
+        let req = req.into_inner();
+        // Subscribe the new logs before reading the files, so no log is missed in between.
+        let follower = req.follow.then(|| LOG_BROADCAST.subscribe());
+        let entries = {
+            let req = req.clone();
+            tokio::task::spawn_blocking(move || read_logs(&req))
+                .await
+                .map_err(|e| Status::internal(format!("failed to read logs: {e}")))??
+        };
+
+        let (tx, rx) = mpsc::channel(128);
+        tokio::spawn(async move {
+            // The logs read from the files, which may be followed again.
+            let mut read: HashSet<(String, u64)> = HashSet::new();
+            for (seq, entry) in entries {
+                if follower.is_some() {
+                    read.insert((entry.executor_id.clone(), seq));
+                }
+                if tx.send(Ok(entry)).await.is_err() {
+                    return;
+                }
+            }
+
+            let Some(mut follower) = follower else {
+                return;
+            };
+            loop {
+                let entry = tokio::select! {
+                    entry = follower.recv() => entry,
+                    _ = tx.closed() => break,
+                };
+                match entry {
+                    Ok((seq, entry)) => {
+                        // The log was read from the files already.
+                        if !is_matched(&req, &entry)
+                            || read.remove(&(entry.executor_id.clone(), seq))
+                        {
+                            continue;
+                        }
+                        if tx.send(Ok(entry)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        log::warn!(
+                            "The logs of session <{}> lagged, {n} logs were skipped.",
+                            req.session_id
+                        );
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let output_stream = ReceiverStream::new(rx);
+        Ok(Response::new(
+            Box::pin(output_stream) as Self::ReadLogsStream
+        ))
+    }
+}
+
+/// Serve the logs of the executors in the node at the address of the host, which is
+/// the endpoint registered for the node.
+pub async fn serve(host: &str, port: u16) -> Result<(), FlameError> {
+    let address = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| FlameError::Network(format!("failed to resolve <{host}>: {e}")))?
+        .next()
+        .ok_or(FlameError::InvalidConfig(format!("no address of <{host}>")))?;
+    log::info!("Listening executor manager at {address}");
+
+    Server::builder()
+        .add_service(ExecutorManagerServer::new(LogService {}))
+        .serve(address)
+        .await
+        .map_err(|e| FlameError::Network(e.to_string()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_entry(task_id: Option<&str>, message: &str) -> LogEntry {
+        LogEntry {
+            executor_id: "exe-1".to_string(),
+            session_id: "ssn-1".to_string(),
+            task_id: task_id.map(String::from),
+            stream: "stdout".to_string(),
+            timestamp: 1_700_000_000_000,
+            message: message.to_string(),
+        }
+    }
+
+    fn wait_for(f: impl Fn() -> bool) -> bool {
+        for _ in 0..50 {
+            if f() {
+                return true;
+            }
+            thread::sleep(std::time::Duration::from_millis(100));
+        }
+
+        false
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        // The message is kept as is, even with tabs.
+        let entry = new_entry(Some("1"), "hello\tworld");
+        let line = encode(42, &entry);
+        assert_eq!(
+            decode("exe-1", line.trim_end_matches('\n')),
+            Some((42, entry))
+        );
+
+        // The empty session and task are encoded by the placeholder.
+        let entry = LogEntry {
+            session_id: String::new(),
+            ..new_entry(None, "")
+        };
+        let line = encode(7, &entry);
+        assert!(line.starts_with("7\t1700000000000\t-\t-\t"));
+        assert_eq!(
+            decode("exe-1", line.trim_end_matches('\n')),
+            Some((7, entry))
+        );
+
+        assert_eq!(decode("exe-1", "not a log"), None);
+    }
+
+    #[test]
+    fn test_is_matched() {
+        let mut req = ReadLogsRequest {
+            session_id: "ssn-1".to_string(),
+            ..ReadLogsRequest::default()
+        };
+        assert!(is_matched(&req, &new_entry(None, "")));
+        assert!(is_matched(&req, &new_entry(Some("1"), "")));
+
+        req.task_id = Some("1".to_string());
+        assert!(is_matched(&req, &new_entry(Some("1"), "")));
+        assert!(!is_matched(&req, &new_entry(Some("2"), "")));
+        assert!(!is_matched(&req, &new_entry(None, "")));
+    }
+
+    #[test]
+    fn test_remove() -> Result<(), FlameError> {
+        fs::create_dir_all(LOG_DIRECTORY).map_err(|e| FlameError::Internal(e.to_string()))?;
+        let executor_id = format!(
+            "test-remove-{}",
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        );
+        let log = ExecutorLog::new_ptr(&executor_id);
+        log.append("stdout", "hello")?;
+        // The log is written by the writer thread.
+        assert!(wait_for(|| log.path.exists()));
+        fs::write(rotated_path(&log.path, 1), "")
+            .map_err(|e| FlameError::Internal(e.to_string()))?;
+
+        // The files are removed after the pending logs were written.
+        remove(&executor_id);
+        assert!(wait_for(|| !log.path.exists()));
+        assert!(!rotated_path(&log.path, 1).exists());
+
+        Ok(())
+    }
+}
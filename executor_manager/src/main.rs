@@ -11,16 +11,24 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use std::time::Duration;
+
 use crate::manager::ExecutorManager;
 use clap::Parser;
 use common::ctx::FlameContext;
 use common::FlameError;
 
+mod backoff;
+mod cgroup;
 mod client;
 mod executor;
+mod logs;
 mod manager;
+mod package;
+mod sandbox;
 mod shims;
 mod states;
+mod workdir;
 
 #[derive(Parser)]
 #[command(name = "flame-executor-manager")]
@@ -32,6 +40,12 @@ struct Cli {
     flame_conf: Option<String>,
     #[arg(long)]
     slots: Option<i32>,
+    /// The port of the executor manager service, e.g. to read the logs of executors
+    #[arg(long)]
+    port: Option<u16>,
+    /// The seconds to wait for the running tasks when shutting down
+    #[arg(long)]
+    grace_period: Option<u64>,
 }
 
 #[tokio::main]
@@ -42,7 +56,8 @@ async fn main() -> Result<(), FlameError> {
     let ctx = FlameContext::from_file(cli.flame_conf)?;
 
     // Create the executor manager by the context.
-    let mut manager = ExecutorManager::new(&ctx).await?;
+    let grace_period = cli.grace_period.map(Duration::from_secs);
+    let mut manager = ExecutorManager::new(&ctx, cli.port, grace_period).await?;
 
     // Run the executor manager.
     manager.run().await?;
@@ -10,71 +10,312 @@ You may obtain a copy of the License at
 Unless required by applicable law or agreed to in writing, software
  */
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::sync::{Arc, Mutex};
-use std::{thread, time};
+use std::time::Duration;
 
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::task::{AbortHandle, JoinSet};
+use tokio::time::Instant;
+
+use crate::backoff::Backoff;
+use crate::cgroup;
 use crate::client::BackendClient;
 use crate::executor::{self, Executor, ExecutorPtr};
-use common::apis::Node;
+use crate::logs;
+use crate::package;
+use crate::workdir;
+use common::apis::{ExecutorState, Node};
 use common::lock_ptr;
 use common::{ctx::FlameContext, FlameError};
 
+const DEFAULT_PORT: u16 = 8090;
+// The running tasks are waited for this grace period when shutting down, which is
+// shorter than the default termination grace period of Kubernetes.
+const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(25);
+// The executors are stopped forcibly if the node is not drained after the grace period.
+const SHUTDOWN_MARGIN: Duration = Duration::from_secs(3);
+// The time to leave the session for the executors stopped forcibly.
+const SESSION_LEAVE_TIMEOUT: Duration = Duration::from_secs(1);
+
 pub struct ExecutorManager {
     ctx: FlameContext,
+    port: u16,
+    grace_period: Duration,
     executors: HashMap<String, ExecutorPtr>,
+    handlers: HashMap<String, AbortHandle>,
+    /// The running executors, which return their ids after exited.
+    running: JoinSet<String>,
     client: BackendClient,
 }
 
 impl ExecutorManager {
-    pub async fn new(ctx: &FlameContext) -> Result<Self, FlameError> {
+    pub async fn new(
+        ctx: &FlameContext,
+        port: Option<u16>,
+        grace_period: Option<Duration>,
+    ) -> Result<Self, FlameError> {
         // Create the Flame directory.
         fs::create_dir_all("/tmp/flame/shim")
             .map_err(|e| FlameError::Internal(format!("failed to create shim directory: {e}")))?;
+        fs::create_dir_all(logs::LOG_DIRECTORY)
+            .map_err(|e| FlameError::Internal(format!("failed to create log directory: {e}")))?;
+        fs::create_dir_all(package::PACKAGE_DIRECTORY).map_err(|e| {
+            FlameError::Internal(format!("failed to create package directory: {e}"))
+        })?;
+        workdir::init(ctx)?;
+        // The executors without resource requirement can still run without cgroup.
+        if let Err(e) = cgroup::init() {
+            log::warn!("The resources of executors can not be limited: {e}");
+        }
 
-        let client = BackendClient::new(ctx).await?;
+        let client = BackendClient::new(ctx)?;
 
         Ok(Self {
             ctx: ctx.clone(),
+            port: port.unwrap_or(DEFAULT_PORT),
+            grace_period: grace_period.unwrap_or(DEFAULT_GRACE_PERIOD),
             executors: HashMap::new(),
+            handlers: HashMap::new(),
+            running: JoinSet::new(),
             client,
         })
     }
 
     pub async fn run(&mut self) -> Result<(), FlameError> {
+        let port = self.port;
         let mut node = Node::new();
-        self.client.register_node(&node).await?;
-        let one_second = time::Duration::from_secs(1);
+        node.endpoint = Some(format!("http://{}:{port}", node.name));
 
-        loop {
-            node.refresh();
+        let host = node.name.clone();
+        tokio::spawn(async move {
+            if let Err(e) = logs::serve(&host, port).await {
+                log::error!("Failed to serve executor manager: {e}");
+            }
+        });
+
+        let mut sigterm = signal(SignalKind::terminate())
+            .map_err(|e| FlameError::Internal(format!("failed to handle SIGTERM: {e}")))?;
+        let mut sigint = signal(SignalKind::interrupt())
+            .map_err(|e| FlameError::Internal(format!("failed to handle SIGINT: {e}")))?;
+        // The deadline to stop the executors forcibly after shutting down.
+        let mut deadline: Option<Instant> = None;
 
-            // TODO(k82cn): also sync the executors in that node.
-            let mut executors = self.client.sync_node(&node, vec![]).await?;
+        // The node is registered and synced again after the stream was reconnected, e.g.
+        // the server restarted; the executors keep running while the server is unavailable.
+        // After synced, the server pushes the changes of the executors by the stream.
+        let mut registered = false;
+        let mut synced = false;
+        let mut backoff = Backoff::default();
 
-            for executor in &executors {
-                if self.executors.contains_key(&executor.id) {
-                    // log::debug!("Executor <{}> is already running.", executor.id);
-                    continue;
+        loop {
+            let mut delay = None;
+            if !synced {
+                node.refresh();
+                match self.sync(&node, &mut registered).await {
+                    Ok(drained) => {
+                        backoff.reset();
+                        synced = true;
+                        if drained {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        registered = false;
+                        let next = backoff.next_delay();
+                        log::warn!(
+                            "Failed to sync node <{}>, retry in {next:?}: {e}",
+                            node.name
+                        );
+                        delay = Some(next);
+                    }
                 }
+            }
 
-                log::debug!("Executor <{}> is starting.", executor.id);
-                let executor_ptr = Arc::new(Mutex::new(executor.clone()));
-                self.executors
-                    .insert(executor.id.clone(), executor_ptr.clone());
-                executor::start(self.client.clone(), executor_ptr.clone());
+            let signal = tokio::select! {
+                _ = tokio::time::sleep(delay.unwrap_or_default()), if delay.is_some() => None,
+                res = self.client.watch_node(&node), if synced => {
+                    match res {
+                        Ok((remote, executors)) => {
+                            if self.apply(&remote, &executors) {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            log::warn!("Node <{}> will sync again: {e}", node.name);
+                            synced = false;
+                        }
+                    }
+                    None
+                }
+                Some(res) = self.running.join_next(), if !self.running.is_empty() => {
+                    // The executor exited, e.g. failed; report it to the server.
+                    if let Ok(id) = res {
+                        log::debug!("Executor <{id}> exited.");
+                        synced = false;
+                    }
+                    None
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    log::warn!(
+                        "Node <{}> was not drained in {:?}, stop its {} executors.",
+                        node.name,
+                        self.grace_period,
+                        self.executors.len()
+                    );
+                    self.stop_executors().await;
+                    if let Err(e) = self.client.release_node(&node).await {
+                        log::error!("Failed to release node <{}>: {e}", node.name);
+                    }
+                    return Ok(());
+                }
+                _ = sigterm.recv() => Some("SIGTERM"),
+                _ = sigint.recv() => Some("SIGINT"),
+            };
+            if let Some(signal) = signal {
+                if deadline.is_none() {
+                    deadline = Some(self.shutdown(&node, signal).await);
+                } else {
+                    log::info!("Node <{}> is shutting down, ignore {signal}.", node.name);
+                }
             }
+        }
 
-            log::debug!(
-                "There are {} executors in node {}",
-                executors.len(),
-                node.name
-            );
+        // All executors were released after draining, exit.
+        log::info!("Node <{}> was drained, release it.", node.name);
+        self.client.release_node(&node).await?;
 
-            thread::sleep(one_second);
-        }
+        log::info!("Executor manager of node <{}> exited.", node.name);
 
         Ok(())
     }
+
+    /// Sync the node with the server by a new stream, and apply the executors of the
+    /// node. Return whether the node was drained.
+    async fn sync(&mut self, node: &Node, registered: &mut bool) -> Result<bool, FlameError> {
+        if !*registered {
+            self.client.register_node(node).await?;
+            *registered = true;
+            log::info!("Node <{}> was registered.", node.name);
+        }
+
+        let (remote, executors) = self.client.sync_node(node, self.local_executors()?).await?;
+
+        Ok(self.apply(&remote, &executors))
+    }
+
+    /// Start the executors of the node, and stop the executors which were released or
+    /// unknown by the server. Return whether the node was drained, e.g. all its executors
+    /// were released after draining.
+    fn apply(&mut self, remote: &Node, executors: &[Executor]) -> bool {
+        let desired: HashSet<String> = executors
+            .iter()
+            .filter(|e| e.state != ExecutorState::Released)
+            .map(|e| e.id.clone())
+            .collect();
+        let stale: Vec<String> = self
+            .executors
+            .keys()
+            .filter(|id| !desired.contains(*id))
+            .cloned()
+            .collect();
+        for id in stale {
+            self.stop_executor(&id);
+        }
+
+        for executor in executors {
+            if self.executors.contains_key(&executor.id)
+                || executor.state == ExecutorState::Released
+            {
+                continue;
+            }
+
+            log::debug!("Executor <{}> is starting.", executor.id);
+            let executor_ptr = Arc::new(Mutex::new(executor.clone()));
+            self.executors
+                .insert(executor.id.clone(), executor_ptr.clone());
+            let (client, id) = (self.client.clone(), executor.id.clone());
+            let handler = self.running.spawn(async move {
+                executor::run(client, executor_ptr).await;
+                id
+            });
+            self.handlers.insert(executor.id.clone(), handler);
+        }
+
+        log::debug!(
+            "There are {} executors in node {}",
+            executors.len(),
+            remote.name
+        );
+
+        remote.draining && desired.is_empty() && self.executors.is_empty()
+    }
+
+    /// Shutdown the node by draining it: no more executors are bound to the node, and
+    /// the bound executors leave their sessions after the running tasks completed.
+    /// Return the deadline to stop the executors forcibly.
+    async fn shutdown(&mut self, node: &Node, signal: &str) -> Instant {
+        log::info!(
+            "Received {signal}, drain node <{}> in {:?}.",
+            node.name,
+            self.grace_period
+        );
+
+        match self.client.drain_node(node, self.grace_period).await {
+            Ok(()) => Instant::now() + self.grace_period + SHUTDOWN_MARGIN,
+            Err(e) => {
+                log::error!("Failed to drain node <{}>: {e}", node.name);
+                Instant::now()
+            }
+        }
+    }
+
+    /// Stop all executors forcibly, and leave their sessions if any; their tasks
+    /// are re-queued by the server after the node is released.
+    async fn stop_executors(&mut self) {
+        let ids: Vec<String> = self.executors.keys().cloned().collect();
+        for id in ids {
+            let shim = self
+                .executors
+                .get(&id)
+                .and_then(|exe| lock_ptr!(exe).ok().and_then(|exe| exe.shim.clone()));
+            self.stop_executor(&id);
+
+            // The shim is unlocked after the executor is stopped.
+            if let Some(shim) = shim {
+                let leave = async { shim.write().await.on_session_leave().await };
+                match tokio::time::timeout(SESSION_LEAVE_TIMEOUT, leave).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => log::warn!("Executor <{id}> failed to leave session: {e}"),
+                    Err(_) => log::warn!("Executor <{id}> did not leave session in time."),
+                }
+            }
+        }
+    }
+
+    /// Collect the actual state of the executors in this node; the executors which
+    /// exited, e.g. failed, are reported as Released so the server releases them.
+    fn local_executors(&self) -> Result<Vec<Executor>, FlameError> {
+        let mut res = vec![];
+        for (id, exe) in &self.executors {
+            let mut exe = lock_ptr!(exe)?.clone();
+            if self.handlers.get(id).is_some_and(|h| h.is_finished()) {
+                exe.state = ExecutorState::Released;
+            }
+            res.push(exe);
+        }
+
+        Ok(res)
+    }
+
+    fn stop_executor(&mut self, id: &str) {
+        log::info!("Executor <{id}> is stopping.");
+        if let Some(handler) = self.handlers.remove(id) {
+            handler.abort();
+        }
+        self.executors.remove(id);
+        self.client.close(id);
+        logs::remove(id);
+    }
 }
@@ -0,0 +1,367 @@
+/*
+Copyright 2025 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use flate2::read::GzDecoder;
+use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use uuid::Uuid;
+
+use common::apis::{self, ApplicationContext};
+use common::{lock_ptr, FlameError};
+
+pub const PACKAGE_DIRECTORY: &str = "/tmp/flame/packages";
+
+const FILE_SCHEME: &str = "file://";
+const DOWNLOAD_BUFFER_SIZE: usize = 64 * 1024;
+const DOWNLOAD_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// The download fails if no data is received in this time; the whole download is not
+// bounded, as the package may be large.
+const DOWNLOAD_READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    // The packages being fetched, so each package is fetched once by the executors of the node.
+    static ref FETCHING: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Prepare the package of the application, if any: it's downloaded and verified by the
+/// checksum into the node-local cache, `/tmp/flame/packages/<sha256>`, and unpacked
+/// there; the relative command of the application is resolved in the package, and the
+/// directory of the package is returned in `package_dir`.
+///
+/// The package is unpacked if it's a `.tar`, `.tar.gz` or `.tgz`; otherwise, it's an
+/// executable file, e.g. a binary or a `.wasm`, which is the command by default.
+pub async fn prepare(app: &ApplicationContext) -> Result<ApplicationContext, FlameError> {
+    let mut app = app.clone();
+    // The directory of the package is only set by the executor manager.
+    app.package_dir = None;
+
+    let Some(url) = app.url.clone() else {
+        return Ok(app);
+    };
+    let checksum = apis::parse_package_checksum(&url, app.checksum.as_deref())?;
+
+    let dir = fetch(&url, &checksum).await?;
+
+    app.command = resolve_command(&dir, &url, app.command)?;
+    app.package_dir = Some(dir.display().to_string());
+
+    Ok(app)
+}
+
+/// Fetch the package into the cache, and return the directory of the unpacked package.
+async fn fetch(url: &str, checksum: &str) -> Result<PathBuf, FlameError> {
+    let dir = Path::new(PACKAGE_DIRECTORY).join(checksum);
+
+    let lock = {
+        let mut fetching = lock_ptr!(FETCHING)?;
+        fetching.entry(checksum.to_string()).or_default().clone()
+    };
+    let _guard = lock.lock().await;
+
+    if dir.is_dir() {
+        log::debug!("The package <{url}> is cached at <{}>.", dir.display());
+        return Ok(dir);
+    }
+
+    log::info!("Try to download package <{url}> into <{}>.", dir.display());
+
+    // Download and unpack the package into temporary paths, so the cache is never partial.
+    let id = Uuid::new_v4();
+    let tmp = Path::new(PACKAGE_DIRECTORY).join(format!(".{checksum}.{id}"));
+    let archive = Path::new(PACKAGE_DIRECTORY).join(format!(".{checksum}.{id}.download"));
+    let res = async {
+        let actual = download(url, &archive).await?;
+        if actual != checksum {
+            return Err(FlameError::InvalidConfig(format!(
+                "the checksum of package <{url}> is <{actual}>, but <{checksum}> is expected"
+            )));
+        }
+
+        let (src_url, src, target) = (url.to_string(), archive.clone(), tmp.clone());
+        tokio::task::spawn_blocking(move || unpack(&src_url, &src, &target))
+            .await
+            .map_err(|e| FlameError::Internal(format!("failed to unpack package: {e}")))??;
+
+        fs::rename(&tmp, &dir).map_err(|e| io_error(&dir, e))
+    }
+    .await;
+
+    let _ = fs::remove_file(&archive);
+    if res.is_err() {
+        let _ = fs::remove_dir_all(&tmp);
+    }
+    res?;
+
+    log::info!("The package <{url}> was unpacked into <{}>.", dir.display());
+
+    Ok(dir)
+}
+
+/// Download the package into the file, and return its SHA-256 in hex.
+async fn download(url: &str, path: &Path) -> Result<String, FlameError> {
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| io_error(path, e))?;
+    let mut hasher = Sha256::new();
+
+    match url.strip_prefix(FILE_SCHEME) {
+        Some(src) => {
+            let mut src_file = tokio::fs::File::open(src)
+                .await
+                .map_err(|e| FlameError::InvalidConfig(format!("failed to open <{url}>: {e}")))?;
+            let mut buf = vec![0u8; DOWNLOAD_BUFFER_SIZE];
+            loop {
+                let n = src_file
+                    .read(&mut buf)
+                    .await
+                    .map_err(|e| FlameError::Internal(format!("failed to read <{url}>: {e}")))?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buf[..n]);
+                file.write_all(&buf[..n])
+                    .await
+                    .map_err(|e| io_error(path, e))?;
+            }
+        }
+        None => {
+            let client = reqwest::Client::builder()
+                .connect_timeout(DOWNLOAD_CONNECT_TIMEOUT)
+                .read_timeout(DOWNLOAD_READ_TIMEOUT)
+                .build()
+                .map_err(|e| FlameError::Internal(format!("failed to build HTTP client: {e}")))?;
+            let mut resp = client
+                .get(url)
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status())
+                .map_err(|e| FlameError::Network(format!("failed to download <{url}>: {e}")))?;
+            while let Some(chunk) = resp
+                .chunk()
+                .await
+                .map_err(|e| FlameError::Network(format!("failed to download <{url}>: {e}")))?
+            {
+                hasher.update(&chunk);
+                file.write_all(&chunk)
+                    .await
+                    .map_err(|e| io_error(path, e))?;
+            }
+        }
+    }
+
+    file.flush().await.map_err(|e| io_error(path, e))?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Unpack the archive into the directory; or copy the file into it, if not an archive.
+fn unpack(url: &str, src: &Path, dir: &Path) -> Result<(), FlameError> {
+    fs::create_dir_all(dir).map_err(|e| io_error(dir, e))?;
+    let file = File::open(src).map_err(|e| io_error(src, e))?;
+    let reader = BufReader::new(file);
+
+    let name = file_name(url)?;
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        tar::Archive::new(GzDecoder::new(reader))
+            .unpack(dir)
+            .map_err(|e| io_error(dir, e))?;
+    } else if name.ends_with(".tar") {
+        tar::Archive::new(reader)
+            .unpack(dir)
+            .map_err(|e| io_error(dir, e))?;
+    } else {
+        let target = dir.join(name);
+        fs::copy(src, &target).map_err(|e| io_error(&target, e))?;
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o755))
+            .map_err(|e| io_error(&target, e))?;
+    }
+
+    Ok(())
+}
+
+/// Resolve the relative command in the package; the command is the package itself by
+/// default, if it's not an archive. The relative command can not escape the package.
+fn resolve_command(
+    dir: &Path,
+    url: &str,
+    command: Option<String>,
+) -> Result<Option<String>, FlameError> {
+    let command = match command {
+        Some(command) => command,
+        None if dir.join(file_name(url)?).is_file() => file_name(url)?,
+        None => return Ok(None),
+    };
+
+    let path = Path::new(&command);
+    if path.is_absolute() {
+        return Ok(Some(command));
+    }
+    if path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(FlameError::InvalidConfig(format!(
+            "invalid command <{command}> in package <{url}>"
+        )));
+    }
+
+    Ok(Some(dir.join(path).display().to_string()))
+}
+
+fn file_name(url: &str) -> Result<String, FlameError> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    match path.rsplit('/').next() {
+        Some(name) if !name.is_empty() => Ok(name.to_string()),
+        _ => Err(FlameError::InvalidConfig(format!(
+            "no file name in package url <{url}>"
+        ))),
+    }
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> FlameError {
+    FlameError::Internal(format!("failed to write <{}>: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use common::apis::Shim;
+
+    fn new_app(url: &Path, checksum: &str) -> ApplicationContext {
+        ApplicationContext {
+            name: "test".to_string(),
+            image: None,
+            command: None,
+            arguments: vec![],
+            environments: HashMap::new(),
+            working_directory: None,
+            sandbox: None,
+            url: Some(format!("{FILE_SCHEME}{}", url.display())),
+            checksum: Some(checksum.to_string()),
+            concurrency: 1,
+            package_dir: None,
+            shim: Shim::Shell,
+        }
+    }
+
+    /// Write a unique package, and return its path and checksum.
+    fn new_package(name: &str) -> Result<(PathBuf, String), FlameError> {
+        let path = Path::new("/tmp").join(format!(
+            "flame_test_{}_{name}",
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let content = format!("#!/bin/sh\necho {}\n", path.display());
+        fs::write(&path, &content).map_err(|e| io_error(&path, e))?;
+
+        Ok((path, format!("{:x}", Sha256::digest(content.as_bytes()))))
+    }
+
+    #[test]
+    fn test_prepare_package() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            fs::create_dir_all(PACKAGE_DIRECTORY)
+                .map_err(|e| io_error(Path::new(PACKAGE_DIRECTORY), e))?;
+            let (path, checksum) = new_package("run.sh")?;
+
+            let app = prepare(&new_app(&path, &checksum)).await?;
+            let dir = Path::new(PACKAGE_DIRECTORY).join(&checksum);
+            let file_name = path.file_name().unwrap_or_default();
+            assert_eq!(app.package_dir, Some(dir.display().to_string()));
+            assert_eq!(app.command, Some(dir.join(file_name).display().to_string()));
+
+            // The package is prepared from the cache, even if the source is gone.
+            fs::remove_file(&path).map_err(|e| io_error(&path, e))?;
+            let cached = prepare(&new_app(&path, &checksum)).await?;
+            assert_eq!(cached.package_dir, app.package_dir);
+
+            fs::remove_dir_all(&dir).map_err(|e| io_error(&dir, e))?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_prepare_checksum_mismatch() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            fs::create_dir_all(PACKAGE_DIRECTORY)
+                .map_err(|e| io_error(Path::new(PACKAGE_DIRECTORY), e))?;
+            let (path, _) = new_package("mismatch.sh")?;
+            let (other, checksum) = new_package("other.sh")?;
+
+            let res = prepare(&new_app(&path, &checksum)).await;
+            assert!(matches!(res, Err(FlameError::InvalidConfig(_))));
+            // Nothing is cached for the mismatched package.
+            assert!(!Path::new(PACKAGE_DIRECTORY).join(&checksum).exists());
+
+            let _ = fs::remove_file(&path);
+            let _ = fs::remove_file(&other);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_prepare_without_package() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            let mut app = new_app(Path::new("/tmp/none"), "");
+            app.url = None;
+            // The directory of the package can not be planted by the application.
+            app.package_dir = Some("/etc".to_string());
+
+            let app = prepare(&app).await?;
+            assert_eq!(app.package_dir, None);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_resolve_command() -> Result<(), FlameError> {
+        let dir = Path::new("/tmp/flame/packages/abc");
+        let url = "http://example.com/app.tar.gz";
+
+        assert_eq!(
+            resolve_command(dir, url, Some("bin/run".to_string()))?,
+            Some("/tmp/flame/packages/abc/bin/run".to_string())
+        );
+        assert_eq!(
+            resolve_command(dir, url, Some("./run".to_string()))?,
+            Some("/tmp/flame/packages/abc/./run".to_string())
+        );
+        assert_eq!(
+            resolve_command(dir, url, Some("/usr/bin/python3".to_string()))?,
+            Some("/usr/bin/python3".to_string())
+        );
+        assert_eq!(resolve_command(dir, url, None)?, None);
+
+        // The command can not escape the package.
+        for command in ["../run", "bin/../../run"] {
+            let res = resolve_command(dir, url, Some(command.to_string()));
+            assert!(matches!(res, Err(FlameError::InvalidConfig(_))));
+        }
+
+        Ok(())
+    }
+}
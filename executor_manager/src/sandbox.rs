@@ -0,0 +1,254 @@
+/*
+Copyright 2025 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::{chown, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use tokio::process::Command;
+
+use crate::workdir::WorkDir;
+use common::apis::{ApplicationContext, ApplicationSandbox};
+use common::FlameError;
+
+// The sandbox is built by bubblewrap, see https://github.com/containers/bubblewrap.
+const BWRAP: &str = "bwrap";
+// The user/group `nobody`, if the sandbox of the application does not specify one.
+const DEFAULT_SANDBOX_ID: u32 = 65534;
+// The directories of the host which are read-only in the sandbox, for the programs
+// and their libraries; they're skipped if not found in the host.
+const SYSTEM_DIRS: [&str; 6] = ["/usr", "/bin", "/lib", "/lib32", "/lib64", "/libx32"];
+// The files of the host which are read-only in the sandbox, for the name resolution,
+// the certificates and the dynamic linker.
+const SYSTEM_FILES: [&str; 4] = [
+    "/etc/resolv.conf",
+    "/etc/hosts",
+    "/etc/ssl",
+    "/etc/ld.so.cache",
+];
+
+/// The sandbox of an executor, which runs the commands of its application as an
+/// unprivileged user in private mount/PID/IPC/UTS (and network, unless allowed)
+/// namespaces. Only the system directories (e.g. `/usr`), a few files of `/etc` and the
+/// package of the application are visible and read-only; the work directory of the
+/// executor is the only writable one, and nothing else of the host is exposed.
+///
+/// If the application has no sandbox, the commands are run as is in the work directory.
+pub struct Sandbox {
+    config: Option<ApplicationSandbox>,
+    work_dir: PathBuf,
+    // The package of the application, which is read-only in the sandbox.
+    package_dir: Option<String>,
+}
+
+impl Sandbox {
+    pub fn new(app: &ApplicationContext, work_dir: &WorkDir) -> Result<Self, FlameError> {
+        let Some(config) = app.sandbox.clone() else {
+            return Ok(Self {
+                config: None,
+                work_dir: work_dir.path().to_path_buf(),
+                package_dir: None,
+            });
+        };
+
+        if !is_installed(BWRAP) {
+            return Err(FlameError::InvalidConfig(format!(
+                "<{BWRAP}> is not found in PATH, which is required by the sandbox of application <{}>",
+                app.name
+            )));
+        }
+
+        // Only the user of the sandbox can access its work directory.
+        let path = work_dir.path();
+        chown(path, Some(uid(&config)), Some(gid(&config))).map_err(|e| io_error(path, e))?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o700))
+            .map_err(|e| io_error(path, e))?;
+
+        Ok(Self {
+            config: Some(config),
+            work_dir: path.to_path_buf(),
+            package_dir: app.package_dir.clone(),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// Whether the commands in the sandbox can access the network of the host.
+    pub fn allow_network(&self) -> bool {
+        self.config.as_ref().is_none_or(|c| c.allow_network)
+    }
+
+    /// The work directory of the executor, which is writable in the sandbox.
+    pub fn work_dir(&self) -> &Path {
+        &self.work_dir
+    }
+
+    /// Build the command to run the program with the args in the sandbox.
+    pub fn command(&self, program: &str, args: &[String]) -> Command {
+        let Some(config) = &self.config else {
+            let mut cmd = Command::new(program);
+            cmd.args(args).current_dir(&self.work_dir);
+            return cmd;
+        };
+
+        let mut cmd = Command::new(BWRAP);
+        cmd.args(self.bwrap_args(config, program, args))
+            .current_dir(&self.work_dir)
+            .uid(uid(config))
+            .gid(gid(config));
+
+        cmd
+    }
+
+    fn bwrap_args(
+        &self,
+        config: &ApplicationSandbox,
+        program: &str,
+        args: &[String],
+    ) -> Vec<String> {
+        let work_dir = self.work_dir.to_string_lossy().to_string();
+        let mut bwrap_args = vec![];
+        let mut add = |args: &[&str]| bwrap_args.extend(args.iter().map(|a| a.to_string()));
+
+        for path in SYSTEM_DIRS.iter().chain(SYSTEM_FILES.iter()) {
+            add(&["--ro-bind-try", path, path]);
+        }
+        add(&["--dev", "/dev"]);
+        add(&["--proc", "/proc"]);
+        add(&["--tmpfs", "/tmp"]);
+        if let Some(package_dir) = &self.package_dir {
+            add(&["--ro-bind", package_dir, package_dir]);
+        }
+        add(&["--bind", &work_dir, &work_dir]);
+        add(&["--setenv", "HOME", &work_dir]);
+        add(&["--chdir", &work_dir]);
+        add(&["--unshare-all"]);
+        if config.allow_network {
+            add(&["--share-net"]);
+        }
+        add(&["--die-with-parent", "--new-session", "--", program]);
+        bwrap_args.extend(args.iter().cloned());
+
+        bwrap_args
+    }
+}
+
+fn uid(config: &ApplicationSandbox) -> u32 {
+    config.uid.unwrap_or(DEFAULT_SANDBOX_ID)
+}
+
+fn gid(config: &ApplicationSandbox) -> u32 {
+    config.gid.unwrap_or(DEFAULT_SANDBOX_ID)
+}
+
+fn is_installed(program: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> FlameError {
+    FlameError::Internal(format!("failed to prepare <{}>: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_sandbox(config: Option<ApplicationSandbox>) -> Sandbox {
+        Sandbox {
+            config,
+            work_dir: PathBuf::from("/tmp/flame/work/exe-1"),
+            package_dir: Some("/tmp/flame/packages/app-1".to_string()),
+        }
+    }
+
+    fn has_args(args: &[String], expected: &[&str]) -> bool {
+        args.windows(expected.len()).any(|w| w == expected)
+    }
+
+    #[test]
+    fn test_bwrap_args() {
+        let config = ApplicationSandbox::default();
+        let sandbox = new_sandbox(Some(config.clone()));
+        let args = sandbox.bwrap_args(&config, "python3", &["main.py".to_string()]);
+
+        // Only the system directories, the package and the work directory are visible.
+        assert!(!has_args(&args, &["--ro-bind", "/", "/"]));
+        assert!(has_args(&args, &["--ro-bind-try", "/usr", "/usr"]));
+        assert!(has_args(&args, &["--ro-bind-try", "/lib64", "/lib64"]));
+        assert!(has_args(
+            &args,
+            &["--ro-bind-try", "/etc/resolv.conf", "/etc/resolv.conf"]
+        ));
+        assert!(!args
+            .iter()
+            .any(|a| a == "/etc" || a == "/home" || a == "/root"));
+        assert!(has_args(
+            &args,
+            &[
+                "--ro-bind",
+                "/tmp/flame/packages/app-1",
+                "/tmp/flame/packages/app-1"
+            ]
+        ));
+        assert!(has_args(
+            &args,
+            &["--bind", "/tmp/flame/work/exe-1", "/tmp/flame/work/exe-1"]
+        ));
+        assert!(has_args(&args, &["--chdir", "/tmp/flame/work/exe-1"]));
+
+        // The network is not shared by default, and the program is run at last.
+        assert!(args.contains(&"--unshare-all".to_string()));
+        assert!(!args.contains(&"--share-net".to_string()));
+        assert!(args.ends_with(&[
+            "--".to_string(),
+            "python3".to_string(),
+            "main.py".to_string()
+        ]));
+    }
+
+    #[test]
+    fn test_bwrap_args_network() {
+        let config = ApplicationSandbox {
+            allow_network: true,
+            ..ApplicationSandbox::default()
+        };
+        let sandbox = Sandbox {
+            package_dir: None,
+            ..new_sandbox(Some(config.clone()))
+        };
+        let args = sandbox.bwrap_args(&config, "sh", &[]);
+
+        assert!(has_args(&args, &["--unshare-all", "--share-net"]));
+        assert!(!args.contains(&"--ro-bind".to_string()));
+        assert!(args.ends_with(&["--".to_string(), "sh".to_string()]));
+    }
+
+    #[test]
+    fn test_no_sandbox() {
+        let sandbox = new_sandbox(None);
+        let cmd = sandbox.command("sh", &["-c".to_string(), "true".to_string()]);
+
+        let cmd = cmd.as_std();
+        assert_eq!(cmd.get_program(), "sh");
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), vec!["-c", "true"]);
+        assert_eq!(
+            cmd.get_current_dir(),
+            Some(Path::new("/tmp/flame/work/exe-1"))
+        );
+    }
+}
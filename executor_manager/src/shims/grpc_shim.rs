@@ -12,63 +12,372 @@ limitations under the License.
 */
 
 use std::env;
-use std::fs::{self, File};
-use std::future::Future;
-use std::pin::Pin;
-use std::process::{self, Stdio};
-use std::sync::Arc;
-use std::task::{Context, Poll};
+use std::fs;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::Duration;
-use std::{thread, time};
 
 use async_trait::async_trait;
 use hyper_util::rt::TokioIo;
 use tokio::net::UnixStream;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, MutexGuard, RwLock};
+use tokio::time::Instant;
 use tonic::transport::Channel;
 use tonic::transport::{Endpoint, Uri};
-use tonic::Request;
+use tonic::{Code, Request, Status};
 use tower::service_fn;
 
 use ::rpc::flame as rpc;
+use ::rpc::health::health_check_response::ServingStatus;
+use ::rpc::health::health_client::HealthClient;
+use ::rpc::health::HealthCheckRequest;
 use rpc::grpc_shim_client::GrpcShimClient;
 use rpc::EmptyRequest;
-use uuid::Uuid;
 
-use crate::shims::{Shim, ShimPtr};
-use common::apis::{ApplicationContext, SessionContext, TaskContext, TaskOutput};
-use common::{trace::TraceFn, trace_fn, FlameError};
+use crate::cgroup::Cgroup;
+use crate::executor::Executor;
+use crate::logs::{ExecutorLog, ExecutorLogPtr};
+use crate::sandbox::Sandbox;
+use crate::shims::{Shim, ShimPtr, TaskUpdate, TaskUpdater};
+use crate::workdir::WorkDir;
+use common::apis::{ApplicationContext, SessionContext, TaskContext, TaskOutput, TaskProgress};
+use common::{lock_ptr, trace::TraceFn, trace_fn, FlameError};
 
+const RUST_LOG: &str = "RUST_LOG";
+const DEFAULT_SVC_LOG_LEVEL: &str = "info";
+
+// The socket of the service, one per executor.
+const FLAME_SERVICE_SOCKET: &str = "FLAME_SERVICE_SOCKET";
+const SHIM_SOCKET_DIRECTORY: &str = "/tmp/flame/shim";
+// The socket of the sandboxed service, in its work directory.
+const SANDBOX_SOCKET_NAME: &str = "fsi.sock";
+
+const SERVICE_STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+const SERVICE_PROBE_INTERVAL: Duration = Duration::from_millis(100);
+// The serving service is probed at most once in this interval, or after a failed call.
+const SERVICE_HEALTH_INTERVAL: Duration = Duration::from_secs(10);
+const SERVICE_RESTART_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_SERVICE_RESTART_BACKOFF: Duration = Duration::from_secs(30);
+// The executor is unhealthy after the service crashed this many times in a row.
+const MAX_SERVICE_CRASHES: u32 = 3;
+
+/// The GrpcShim supervises the service of the executor: the service is probed by gRPC
+/// health checking periodically and after a failed call, and restarted with backoff if
+/// it crashed. The tasks
+/// are invoked concurrently on the service, up to the concurrency of the application.
 pub struct GrpcShim {
+    executor_id: String,
+    app: ApplicationContext,
     session_context: Option<SessionContext>,
-    client: GrpcShimClient<Channel>,
-    child: tokio::process::Child,
-    service_socket: String,
+    // The service is shared by the concurrent tasks, and restarted by one of them if it crashed.
+    service: Mutex<Option<GrpcService>>,
+    // The output of the service is captured into the log of the executor.
+    log: ExecutorLogPtr,
+    // The resources of the service are limited by the cgroup of the executor.
+    cgroup: Cgroup,
+    // The service is run in the sandbox of the application, if any.
+    sandbox: Sandbox,
+    // The service is run in the work directory of the executor.
+    work_dir: WorkDir,
+    // The number of continuous crashes of the service.
+    crashes: AtomicU32,
+    // The time to restart the crashed service, after the backoff of its crashes.
+    restart_at: StdMutex<Option<Instant>>,
 }
 
-const RUST_LOG: &str = "RUST_LOG";
-const DEFAULT_SVC_LOG_LEVEL: &str = "info";
-
 impl GrpcShim {
-    pub async fn new_ptr(app: &ApplicationContext) -> Result<ShimPtr, FlameError> {
+    pub async fn new_ptr(
+        executor: &Executor,
+        app: &ApplicationContext,
+    ) -> Result<ShimPtr, FlameError> {
         trace_fn!("GrpcShim::new_ptr");
 
+        Ok(Arc::new(RwLock::new(Self::new(executor, app).await?)))
+    }
+
+    async fn new(executor: &Executor, app: &ApplicationContext) -> Result<Self, FlameError> {
+        let log = ExecutorLog::new_ptr(&executor.id);
+        let cgroup = Cgroup::new(executor)?;
+        let work_dir = WorkDir::new(&executor.id, app)?;
+        let sandbox = Sandbox::new(app, &work_dir)?;
+        let service = GrpcService::start(&executor.id, app, &log, &cgroup, &sandbox).await?;
+
+        Ok(Self {
+            executor_id: executor.id.clone(),
+            app: app.clone(),
+            session_context: None,
+            service: Mutex::new(Some(service)),
+            log,
+            cgroup,
+            sandbox,
+            work_dir,
+            crashes: AtomicU32::new(0),
+            restart_at: StdMutex::new(None),
+        })
+    }
+
+    /// Lock the service, and check whether it crashed; the backoff of restarting the
+    /// crashed service is waited without the lock, so it does not block the others.
+    async fn lock_service(&self) -> MutexGuard<'_, Option<GrpcService>> {
+        loop {
+            let mut service = self.service.lock().await;
+            self.probe_service(&mut service).await;
+
+            let now = Instant::now();
+            let restart_at = lock_ptr!(self.restart_at).ok().and_then(|t| *t);
+            match restart_at {
+                Some(t) if service.is_none() && self.is_healthy() && t > now => {
+                    drop(service);
+                    tokio::time::sleep_until(t).await;
+                }
+                _ => return service,
+            }
+        }
+    }
+
+    /// Check whether the service exited, or is not serving if it should be probed.
+    async fn probe_service(&self, service: &mut Option<GrpcService>) {
+        if let Some(current) = service.as_mut() {
+            if let Some(status) = current.exit_status() {
+                self.on_service_crashed(service, &format!("service exited with {status}"));
+            } else if current.should_probe() && !current.is_serving().await {
+                self.on_service_crashed(service, "service is not serving");
+            }
+        }
+    }
+
+    /// Make sure the service is serving, restart it if it crashed.
+    async fn ensure_service<'a>(
+        &self,
+        service: &'a mut Option<GrpcService>,
+    ) -> Result<&'a mut GrpcService, FlameError> {
+        if service.is_none() {
+            if !self.is_healthy() {
+                return Err(FlameError::Internal(format!(
+                    "the service of executor <{}> crashed {} times",
+                    self.executor_id,
+                    self.crashes.load(Ordering::Relaxed)
+                )));
+            }
+
+            log::info!("Restart the service of executor <{}>.", self.executor_id);
+            let mut new_service = match GrpcService::start(
+                &self.executor_id,
+                &self.app,
+                &self.log,
+                &self.cgroup,
+                &self.sandbox,
+            )
+            .await
+            {
+                Ok(service) => service,
+                Err(e) => {
+                    self.on_service_crashed(service, &e.to_string());
+                    return Err(e);
+                }
+            };
+
+            // Re-enter the session for the new service.
+            if let Some(ctx) = &self.session_context {
+                let req = Request::new(rpc::SessionContext::from(ctx.clone()));
+                if let Err(status) = new_service.client.on_session_enter(req).await {
+                    let reason = new_service.error_reason(&status);
+                    self.on_service_crashed(service, &reason);
+                    return Err(FlameError::Internal(reason));
+                }
+            }
+
+            *service = Some(new_service);
+        }
+
+        service
+            .as_mut()
+            .ok_or(FlameError::Internal("no service".to_string()))
+    }
+
+    fn on_service_crashed(&self, service: &mut Option<GrpcService>, reason: &str) {
+        let crashes = self.crashes.fetch_add(1, Ordering::Relaxed) + 1;
+        self.work_dir.set_failed();
+        *service = None;
+
+        let backoff = SERVICE_RESTART_BACKOFF
+            .saturating_mul(1 << crashes.saturating_sub(1).min(16))
+            .min(MAX_SERVICE_RESTART_BACKOFF);
+        if let Ok(mut restart_at) = lock_ptr!(self.restart_at) {
+            *restart_at = Some(Instant::now() + backoff);
+        }
+        log::error!(
+            "The service of executor <{}> crashed <{crashes}> times, restart it in {backoff:?}: {reason}",
+            self.executor_id
+        );
+    }
+
+    /// Handle the error of calling the service: if the service exited, it crashed.
+    fn on_service_error(&self, service: &mut Option<GrpcService>, status: Status) -> FlameError {
+        let Some(current) = service.as_mut() else {
+            return FlameError::Internal(status.message().to_string());
+        };
+
+        let reason = current.error_reason(&status);
+        if current.exit_status().is_some() {
+            self.on_service_crashed(service, &reason);
+        } else {
+            // Probe the service before the next call, as it may be hung.
+            current.probed_at = None;
+        }
+
+        self.cgroup.check_oom(FlameError::Internal(reason))
+    }
+}
+
+#[async_trait]
+impl Shim for GrpcShim {
+    async fn on_session_enter(&mut self, ctx: &SessionContext) -> Result<(), FlameError> {
+        trace_fn!("GrpcShim::on_session_enter");
+
+        self.log.set_context(Some(ctx.session_id.clone()), None)?;
+
+        {
+            let mut service = self.lock_service().await;
+            let current = self.ensure_service(&mut service).await?;
+            let req = Request::new(rpc::SessionContext::from(ctx.clone()));
+            if let Err(status) = current.client.on_session_enter(req).await {
+                return Err(self.on_service_error(&mut service, status));
+            }
+        }
+
+        self.session_context = Some(ctx.clone());
+
+        Ok(())
+    }
+
+    async fn on_task_invoke(
+        &self,
+        ctx: &TaskContext,
+        updater: &TaskUpdater,
+    ) -> Result<Option<TaskOutput>, FlameError> {
+        trace_fn!("GrpcShim::on_task_invoke");
+
+        // The output of the service can not be told apart by the concurrent tasks.
+        let concurrent = self.app.concurrency > 1;
+        if !concurrent {
+            self.log
+                .set_context(Some(ctx.session_id.clone()), Some(ctx.task_id.clone()))?;
+        }
+
+        // Only the supervision of the service is exclusive, the calls run concurrently.
+        let mut invoker = {
+            let mut service = self.lock_service().await;
+            self.ensure_service(&mut service).await?.invoker()
+        };
+        let res = self.work_dir.guard(invoker.invoke(ctx, updater)).await;
+
+        let res = {
+            let mut service = self.service.lock().await;
+            match res {
+                Ok(Ok(output)) => {
+                    self.crashes.store(0, Ordering::Relaxed);
+                    if let Some(current) = service.as_mut() {
+                        current.streaming &= invoker.streaming;
+                    }
+                    Ok(output)
+                }
+                Ok(Err(status)) => Err(self.on_service_error(&mut service, status)),
+                Err(e) => Err(e),
+            }
+        };
+        let res = self.work_dir.check(res).await;
+
+        if !concurrent {
+            self.log.set_context(Some(ctx.session_id.clone()), None)?;
+        }
+
+        res
+    }
+
+    async fn on_session_leave(&mut self) -> Result<(), FlameError> {
+        trace_fn!("GrpcShim::on_session_leave");
+
+        {
+            let mut service = self.service.lock().await;
+            // No need to restart the crashed service for leaving the session.
+            if service.as_mut().is_some_and(|s| s.exit_status().is_some()) {
+                *service = None;
+            }
+            if let Some(current) = service.as_mut() {
+                let req = Request::new(EmptyRequest::default());
+                if let Err(status) = current.client.on_session_leave(req).await {
+                    return Err(self.on_service_error(&mut service, status));
+                }
+            }
+        }
+
+        self.session_context = None;
+        self.log.set_context(None, None)?;
+
+        Ok(())
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.crashes.load(Ordering::Relaxed) < MAX_SERVICE_CRASHES && self.work_dir.is_healthy()
+    }
+}
+
+/// The service process of GrpcShim, which is stopped when dropped.
+struct GrpcService {
+    child: tokio::process::Child,
+    client: GrpcShimClient<Channel>,
+    health: HealthClient<Channel>,
+    service_socket: String,
+    // The last time the service was probed as serving.
+    probed_at: Option<Instant>,
+    // Whether the service streams the updates of tasks; the services built with
+    // an earlier SDK only implement the unary call.
+    streaming: bool,
+}
+
+impl GrpcService {
+    async fn start(
+        executor_id: &str,
+        app: &ApplicationContext,
+        log: &ExecutorLogPtr,
+        cgroup: &Cgroup,
+        sandbox: &Sandbox,
+    ) -> Result<Self, FlameError> {
         let command = app.command.clone().unwrap_or_default();
         let args = app.arguments.clone();
         let log_level = env::var(RUST_LOG).unwrap_or(String::from(DEFAULT_SVC_LOG_LEVEL));
+        let service_socket = service_socket(
+            executor_id,
+            sandbox.is_enabled().then(|| sandbox.work_dir()),
+        );
         let mut envs = app.environments.clone();
         envs.insert(RUST_LOG.to_string(), log_level);
+        envs.insert(FLAME_SERVICE_SOCKET.to_string(), service_socket.clone());
+
+        // Remove the stale socket, e.g. the previous service of this executor crashed.
+        if fs::exists(&service_socket).unwrap_or(false) {
+            fs::remove_file(&service_socket).map_err(|e| {
+                FlameError::Internal(format!(
+                    "failed to remove stale socket <{service_socket}>: {e}"
+                ))
+            })?;
+        }
 
         log::debug!(
             "Try to start service by command <{command}> with args <{args:?}> and envs <{envs:?}>"
         );
 
         // Spawn child process
-        let mut cmd = tokio::process::Command::new(&command);
+        let mut cmd = sandbox.command(&command, &args);
+        cgroup.attach(&mut cmd)?;
 
         let mut child = cmd
             .envs(envs)
-            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .kill_on_drop(true)
             .spawn()
             .map_err(|e| {
@@ -77,16 +386,19 @@ impl GrpcShim {
                 ))
             })?;
 
-        let service_id = child.id().unwrap_or_default();
+        if let Some(stdout) = child.stdout.take() {
+            log.capture("stdout", stdout);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            log.capture("stderr", stderr);
+        }
 
+        let service_id = child.id().unwrap_or_default();
         log::debug!("The service <{service_id}> was started, waiting for registering.");
 
-        let service_socket = get_service_socket().await?;
-        log::debug!("Try to connect to service <{service_id}> at <{service_socket}>");
-
         let channel = Endpoint::try_from("http://[::]:50051")
             .unwrap()
-            .connect_with_connector({
+            .connect_with_connector_lazy({
                 let service_addr = service_socket.clone();
 
                 service_fn(move |_: Uri| {
@@ -98,93 +410,323 @@ impl GrpcShim {
                             .map_err(std::io::Error::other)
                     }
                 })
-            })
-            .await
-            .map_err(|e| {
-                FlameError::Network(format!("failed to connect to service <{service_id}>: {e}"))
-            })?;
-
-        let client = GrpcShimClient::new(channel);
+            });
 
-        Ok(Arc::new(Mutex::new(Self {
-            session_context: None,
-            client,
+        let mut service = Self {
             child,
+            client: GrpcShimClient::new(channel.clone()),
+            health: HealthClient::new(channel),
             service_socket,
-        })))
-    }
-}
+            probed_at: None,
+            streaming: true,
+        };
 
-impl Drop for GrpcShim {
-    fn drop(&mut self) {
-        let _ = self.child.kill();
-        let _ = std::fs::remove_file(&self.service_socket);
+        service.wait_for_serving().await?;
         log::debug!(
-            "The service <{}> was stopped",
-            self.child.id().unwrap_or_default()
+            "The service <{service_id}> is serving at <{}>",
+            service.service_socket
         );
+
+        Ok(service)
     }
-}
 
-#[async_trait]
-impl Shim for GrpcShim {
-    async fn on_session_enter(&mut self, ctx: &SessionContext) -> Result<(), FlameError> {
-        trace_fn!("GrpcShim::on_session_enter");
+    /// Wait for the service to be serving, until it exited or the startup timeout.
+    async fn wait_for_serving(&mut self) -> Result<(), FlameError> {
+        let start = Instant::now();
+        loop {
+            if let Some(status) = self.exit_status() {
+                return Err(FlameError::Internal(format!(
+                    "service exited with {status} during startup"
+                )));
+            }
+
+            if fs::exists(&self.service_socket).unwrap_or(false) && self.is_serving().await {
+                return Ok(());
+            }
+
+            if start.elapsed() > SERVICE_STARTUP_TIMEOUT {
+                return Err(FlameError::Internal(format!(
+                    "service was not serving at <{}> in {SERVICE_STARTUP_TIMEOUT:?}",
+                    self.service_socket
+                )));
+            }
+
+            tokio::time::sleep(SERVICE_PROBE_INTERVAL).await;
+        }
+    }
 
-        let req = Request::new(rpc::SessionContext::from(ctx.clone()));
-        self.client.on_session_enter(req).await?;
+    /// The invoker of the tasks, which calls the service without the lock of the shim.
+    fn invoker(&self) -> GrpcInvoker {
+        GrpcInvoker {
+            client: self.client.clone(),
+            service_socket: self.service_socket.clone(),
+            streaming: self.streaming,
+        }
+    }
 
-        Ok(())
+    /// Whether the service should be probed: it was not probed recently or a call failed.
+    fn should_probe(&self) -> bool {
+        self.probed_at
+            .is_none_or(|t| t.elapsed() > SERVICE_HEALTH_INTERVAL)
     }
 
-    async fn on_task_invoke(
+    async fn is_serving(&mut self) -> bool {
+        let req = Request::new(HealthCheckRequest::default());
+        match self.health.check(req).await {
+            Ok(resp) => {
+                let serving = resp.into_inner().status == ServingStatus::Serving as i32;
+                if serving {
+                    self.probed_at = Some(Instant::now());
+                }
+                serving
+            }
+            Err(e) => {
+                log::debug!("Failed to probe service <{}>: {e}", self.service_socket);
+                false
+            }
+        }
+    }
+
+    fn exit_status(&mut self) -> Option<ExitStatus> {
+        self.child.try_wait().ok().flatten()
+    }
+
+    /// The reason of the failed call: the exit status if the service exited.
+    fn error_reason(&mut self, status: &Status) -> String {
+        match self.exit_status() {
+            Some(exit_status) => format!("service exited with {exit_status}"),
+            None => status.message().to_string(),
+        }
+    }
+}
+
+/// The socket of the service of the executor; the sandboxed service can only write
+/// its work directory, so the socket is in it.
+fn service_socket(executor_id: &str, sandbox_work_dir: Option<&Path>) -> String {
+    match sandbox_work_dir {
+        Some(work_dir) => work_dir.join(SANDBOX_SOCKET_NAME).display().to_string(),
+        None => format!("{SHIM_SOCKET_DIRECTORY}/{executor_id}.sock"),
+    }
+}
+
+/// The client of the service for one task, so the tasks are invoked concurrently.
+struct GrpcInvoker {
+    client: GrpcShimClient<Channel>,
+    service_socket: String,
+    streaming: bool,
+}
+
+impl GrpcInvoker {
+    /// Invoke the task by streaming its updates to the updater, or by the unary call
+    /// if the service does not support streaming.
+    async fn invoke(
         &mut self,
         ctx: &TaskContext,
-    ) -> Result<Option<TaskOutput>, FlameError> {
-        trace_fn!("GrpcShim::on_task_invoke");
+        updater: &TaskUpdater,
+    ) -> Result<Option<TaskOutput>, Status> {
+        if self.streaming {
+            match self.invoke_stream(ctx, updater).await {
+                Err(status) if status.code() == Code::Unimplemented => {
+                    log::info!(
+                        "The service <{}> does not stream task updates, fallback to the unary call.",
+                        self.service_socket
+                    );
+                    self.streaming = false;
+                }
+                res => return res,
+            }
+        }
 
         let req = Request::new(rpc::TaskContext::from(ctx.clone()));
-        let resp = self.client.on_task_invoke(req).await?;
-        let output = resp.into_inner();
+        let output = self.client.on_task_invoke(req).await?.into_inner();
 
         Ok(output.data.map(|d| d.into()))
     }
 
-    async fn on_session_leave(&mut self) -> Result<(), FlameError> {
-        trace_fn!("GrpcShim::on_session_leave");
-
-        self.client
-            .on_session_leave(Request::new(EmptyRequest::default()))
-            .await?;
+    async fn invoke_stream(
+        &mut self,
+        ctx: &TaskContext,
+        updater: &TaskUpdater,
+    ) -> Result<Option<TaskOutput>, Status> {
+        let req = Request::new(rpc::TaskContext::from(ctx.clone()));
+        let mut stream = self.client.on_task_invoke_stream(req).await?.into_inner();
+
+        while let Some(update) = stream.message().await? {
+            if let Some(output) = update.output {
+                return Ok(output.data.map(|d| d.into()));
+            }
+
+            let update = TaskUpdate {
+                progress: update.progress.map(TaskProgress::from),
+                partial_output: update.partial_output.map(TaskOutput::from),
+            };
+            // The task keeps running even if its updates can not be reported.
+            if let Err(e) = updater.send(update).await {
+                log::debug!("Failed to report the update of task <{}>: {e}", ctx.task_id);
+            }
+        }
 
-        Ok(())
+        Err(Status::internal("the task stream closed without output"))
     }
 }
 
-async fn get_service_socket() -> Result<String, FlameError> {
-    let path = "/tmp/flame/shim/fsi.sock".to_string();
-    WaitForSvcSocketFuture::new(path).await
+impl Drop for GrpcService {
+    fn drop(&mut self) {
+        let _ = self.child.start_kill();
+        let _ = std::fs::remove_file(&self.service_socket);
+        log::debug!(
+            "The service <{}> was stopped",
+            self.child.id().unwrap_or_default()
+        );
+    }
 }
 
-struct WaitForSvcSocketFuture {
-    path: String,
-}
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
 
-impl WaitForSvcSocketFuture {
-    pub fn new(path: String) -> Self {
-        Self { path }
-    }
-}
+    use tokio::net::UnixListener;
+    use tokio_stream::wrappers::UnixListenerStream;
+    use tonic::transport::Server;
+    use tonic::Response;
+    use uuid::Uuid;
 
-impl Future for WaitForSvcSocketFuture {
-    type Output = Result<String, FlameError>;
+    use super::*;
+    use crate::workdir;
+    use ::rpc::health::health_server::{Health, HealthServer};
+    use ::rpc::health::HealthCheckResponse;
+    use common::apis::Shim as ShimType;
 
-    fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
-        if fs::exists(&self.path).unwrap_or(false) {
-            Poll::Ready(Ok(self.path.clone()))
-        } else {
-            ctx.waker().wake_by_ref();
-            Poll::Pending
+    // The fake service only tells the test that it started, and the test serves its health.
+    const FAKE_SERVICE: &str = r#"touch "$FLAME_SERVICE_SOCKET.ready"; exec sleep 60"#;
+
+    #[derive(Clone, Default)]
+    struct FakeHealth {
+        serving: Arc<AtomicBool>,
+    }
+
+    #[tonic::async_trait]
+    impl Health for FakeHealth {
+        async fn check(
+            &self,
+            _: Request<HealthCheckRequest>,
+        ) -> Result<Response<HealthCheckResponse>, Status> {
+            let status = match self.serving.load(Ordering::Relaxed) {
+                true => ServingStatus::Serving,
+                false => ServingStatus::NotServing,
+            };
+
+            Ok(Response::new(HealthCheckResponse {
+                status: status as i32,
+            }))
         }
     }
+
+    /// Serve the health on the socket of each service of the executor after it started.
+    fn serve_health(executor_id: &str, health: FakeHealth) {
+        let socket = service_socket(executor_id, None);
+        tokio::spawn(async move {
+            let ready = format!("{socket}.ready");
+            loop {
+                if fs::remove_file(&ready).is_ok() {
+                    let _ = fs::remove_file(&socket);
+                    let listener = UnixListener::bind(&socket).unwrap();
+                    tokio::spawn(
+                        Server::builder()
+                            .add_service(HealthServer::new(health.clone()))
+                            .serve_with_incoming(UnixListenerStream::new(listener)),
+                    );
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+    }
+
+    async fn new_shim() -> Result<(GrpcShim, FakeHealth), FlameError> {
+        fs::create_dir_all(SHIM_SOCKET_DIRECTORY)
+            .map_err(|e| FlameError::Internal(e.to_string()))?;
+
+        let executor = Executor::for_test(&format!("exe-grpc-{}", Uuid::new_v4().simple()));
+        let mut app = workdir::tests::new_app(None);
+        app.shim = ShimType::Grpc;
+        app.command = Some("sh".to_string());
+        app.arguments = vec!["-c".to_string(), FAKE_SERVICE.to_string()];
+
+        let health = FakeHealth::default();
+        health.serving.store(true, Ordering::Relaxed);
+        serve_health(&executor.id, health.clone());
+        let shim = GrpcShim::new(&executor, &app).await?;
+
+        Ok((shim, health))
+    }
+
+    #[test]
+    fn test_probe_service() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            let (shim, health) = new_shim().await?;
+
+            let mut service = shim.lock_service().await;
+            // The service was probed as serving at startup.
+            assert!(service.as_ref().is_some_and(|s| !s.should_probe()));
+
+            // The service is hung, it's probed after a failed call.
+            health.serving.store(false, Ordering::Relaxed);
+            if let Some(current) = service.as_mut() {
+                current.probed_at = None;
+            }
+            shim.probe_service(&mut service).await;
+            assert!(service.is_none());
+            assert_eq!(shim.crashes.load(Ordering::Relaxed), 1);
+            assert!(shim.is_healthy());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_restart_service() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            let (shim, _) = new_shim().await?;
+            let shim = Arc::new(shim);
+
+            // The service crashed.
+            if let Some(current) = shim.service.lock().await.as_mut() {
+                let _ = current.child.start_kill();
+                let _ = current.child.wait().await;
+            }
+
+            let restart = tokio::spawn({
+                let shim = shim.clone();
+                async move {
+                    let mut service = shim.lock_service().await;
+                    shim.ensure_service(&mut service).await.map(|_| ())
+                }
+            });
+
+            // The backoff of restarting is waited without the lock of the service.
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            assert!(shim.service.try_lock().is_ok_and(|s| s.is_none()));
+
+            restart
+                .await
+                .map_err(|e| FlameError::Internal(e.to_string()))??;
+            assert!(shim.service.lock().await.is_some());
+            assert_eq!(shim.crashes.load(Ordering::Relaxed), 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_service_socket() {
+        // The service writes its socket into the shared directory of the shims.
+        assert_eq!(service_socket("exe-1", None), "/tmp/flame/shim/exe-1.sock");
+
+        // The sandboxed service can only write its work directory.
+        let work_dir = Path::new("/tmp/flame_test_work/exe-1");
+        assert_eq!(
+            service_socket("exe-1", Some(work_dir)),
+            "/tmp/flame_test_work/exe-1/fsi.sock"
+        );
+    }
 }
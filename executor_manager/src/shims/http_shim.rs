@@ -0,0 +1,596 @@
+/*
+Copyright 2025 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::collections::HashMap;
+use std::env;
+use std::net::TcpListener;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use tokio::net::TcpStream;
+use tokio::process::Child;
+use tokio::sync::RwLock;
+
+use crate::cgroup::Cgroup;
+use crate::executor::Executor;
+use crate::sandbox::Sandbox;
+use crate::shims::{Shim, ShimPtr, TaskUpdater};
+use crate::workdir::WorkDir;
+use common::apis::{ApplicationContext, SessionContext, TaskContext, TaskOutput};
+use common::{lock_ptr, trace::TraceFn, trace_fn, FlameError};
+
+const RUST_LOG: &str = "RUST_LOG";
+const DEFAULT_SVC_LOG_LEVEL: &str = "info";
+
+const FLAME_HTTP_PORT: &str = "FLAME_HTTP_PORT";
+const FLAME_HTTP_HEALTH_PATH: &str = "FLAME_HTTP_HEALTH_PATH";
+const FLAME_HTTP_SESSION_ENTER_PATH: &str = "FLAME_HTTP_SESSION_ENTER_PATH";
+const FLAME_HTTP_TASK_PATH: &str = "FLAME_HTTP_TASK_PATH";
+const FLAME_HTTP_SESSION_LEAVE_PATH: &str = "FLAME_HTTP_SESSION_LEAVE_PATH";
+// The timeout in seconds of each request to the service, e.g. a task.
+const FLAME_HTTP_TIMEOUT: &str = "FLAME_HTTP_TIMEOUT";
+
+// The port of the external service, i.e. no command of the application.
+const DEFAULT_HTTP_PORT: u16 = 8000;
+const DEFAULT_SESSION_ENTER_PATH: &str = "/session/enter";
+const DEFAULT_TASK_PATH: &str = "/task";
+const DEFAULT_SESSION_LEAVE_PATH: &str = "/session/leave";
+
+const SESSION_ID_HEADER: &str = "x-flame-session-id";
+const TASK_ID_HEADER: &str = "x-flame-task-id";
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+const STARTUP_INTERVAL: Duration = Duration::from_millis(200);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The HttpShim starts the application command, waits for its local port (or health path)
+/// to be ready, and then forwards session enter/leave and tasks to the service by POST.
+///
+/// The shim is configured by the environments of the application, which are also passed
+/// to the service, e.g. the port to listen on:
+///   * `FLAME_HTTP_PORT`: the local port of the service; if not set, a free port is
+///     allocated for the service of each executor, or `8000` for the external service;
+///   * `FLAME_HTTP_HEALTH_PATH`: if set, GET it until success instead of waiting for the port;
+///   * `FLAME_HTTP_SESSION_ENTER_PATH`: default `/session/enter`, the body is the common data;
+///   * `FLAME_HTTP_TASK_PATH`: default `/task`, the body is the task input;
+///   * `FLAME_HTTP_SESSION_LEAVE_PATH`: default `/session/leave`, the body is empty;
+///   * `FLAME_HTTP_TIMEOUT`: default `600`, the timeout in seconds of each request.
+///
+/// The session/task IDs are sent by `x-flame-session-id`/`x-flame-task-id` headers; the
+/// response body of the task is the output, and a non-success status fails the task.
+/// The shim becomes unhealthy if the service exited, so the executor is released.
+pub struct HttpShim {
+    session_context: Option<SessionContext>,
+    // The service is checked for exit by the concurrent tasks.
+    child: Mutex<Option<Child>>,
+    client: reqwest::Client,
+    endpoint: String,
+    session_enter_path: String,
+    task_path: String,
+    session_leave_path: String,
+    cgroup: Cgroup,
+    sandbox: Sandbox,
+    work_dir: WorkDir,
+}
+
+impl HttpShim {
+    pub async fn new_ptr(
+        executor: &Executor,
+        app: &ApplicationContext,
+    ) -> Result<ShimPtr, FlameError> {
+        trace_fn!("HttpShim::new_ptr");
+
+        let get_env = |name: &str, default: &str| -> String {
+            app.environments
+                .get(name)
+                .cloned()
+                .unwrap_or(default.to_string())
+        };
+
+        let port = match (app.environments.get(FLAME_HTTP_PORT), &app.command) {
+            (Some(port), _) => port.parse::<u16>().map_err(|e| {
+                FlameError::InvalidConfig(format!("invalid {FLAME_HTTP_PORT} <{port}>: {e}"))
+            })?,
+            // The services of the executors in the same node listen on different ports.
+            (None, Some(_)) => free_port()?,
+            (None, None) => DEFAULT_HTTP_PORT,
+        };
+        let endpoint = format!("http://127.0.0.1:{port}");
+
+        let mut envs = app.environments.clone();
+        let log_level = env::var(RUST_LOG).unwrap_or(String::from(DEFAULT_SVC_LOG_LEVEL));
+        envs.insert(RUST_LOG.to_string(), log_level);
+        envs.insert(FLAME_HTTP_PORT.to_string(), port.to_string());
+
+        // The shim talks to the service by the network of the host.
+        let work_dir = WorkDir::new(&executor.id, app)?;
+        let sandbox = Sandbox::new(app, &work_dir)?;
+        if !sandbox.allow_network() {
+            return Err(FlameError::InvalidConfig(format!(
+                "the sandbox of application <{}> must allow network for the HTTP shim",
+                app.name
+            )));
+        }
+
+        // Start the service if any; otherwise, forward to the endpoint directly.
+        let cgroup = Cgroup::new(executor)?;
+        let mut child = match &app.command {
+            Some(command) => Some(start_service(
+                &sandbox,
+                &cgroup,
+                command,
+                &app.arguments,
+                envs,
+            )?),
+            None => None,
+        };
+
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(request_timeout(app)?)
+            .build()
+            .map_err(|e| FlameError::Internal(format!("failed to build HTTP client: {e}")))?;
+        match app.environments.get(FLAME_HTTP_HEALTH_PATH) {
+            Some(path) => {
+                wait_for_health(&client, &format!("{endpoint}{path}"), &mut child).await?
+            }
+            None => wait_for_port(port, &mut child).await?,
+        }
+
+        log::debug!("The service at <{endpoint}> is ready.");
+
+        Ok(Arc::new(RwLock::new(Self {
+            session_context: None,
+            child: Mutex::new(child),
+            client,
+            endpoint,
+            session_enter_path: get_env(FLAME_HTTP_SESSION_ENTER_PATH, DEFAULT_SESSION_ENTER_PATH),
+            task_path: get_env(FLAME_HTTP_TASK_PATH, DEFAULT_TASK_PATH),
+            session_leave_path: get_env(FLAME_HTTP_SESSION_LEAVE_PATH, DEFAULT_SESSION_LEAVE_PATH),
+            cgroup,
+            sandbox,
+            work_dir,
+        })))
+    }
+
+    /// POST the body to the path of the service, and return the response body.
+    async fn post(
+        &self,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: Option<Bytes>,
+    ) -> Result<Bytes, FlameError> {
+        let url = format!("{}{path}", self.endpoint);
+
+        let mut req = self.client.post(&url).body(body.unwrap_or_default());
+        for (k, v) in headers {
+            req = req.header(*k, *v);
+        }
+
+        let resp = req.send().await.map_err(|e| match self.exit_status() {
+            Some(status) => FlameError::Internal(format!("service exited with {status}")),
+            None => FlameError::Internal(format!("failed to post <{url}>: {e}")),
+        })?;
+
+        let status = resp.status();
+        let body = resp.bytes().await.map_err(|e| {
+            FlameError::Internal(format!("failed to read response of <{url}>: {e}"))
+        })?;
+
+        if !status.is_success() {
+            return Err(FlameError::Internal(format!(
+                "<{url}> returned {status}: {}",
+                String::from_utf8_lossy(&body)
+            )));
+        }
+
+        Ok(body)
+    }
+
+    fn exit_status(&self) -> Option<ExitStatus> {
+        let mut child = lock_ptr!(self.child).ok()?;
+        child.as_mut().and_then(exit_status)
+    }
+}
+
+/// Allocate a free local port for the service by binding port 0.
+fn free_port() -> Result<u16, FlameError> {
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .map_err(|e| FlameError::Internal(format!("failed to allocate port: {e}")))?;
+    let addr = listener
+        .local_addr()
+        .map_err(|e| FlameError::Internal(format!("failed to allocate port: {e}")))?;
+
+    Ok(addr.port())
+}
+
+fn request_timeout(app: &ApplicationContext) -> Result<Duration, FlameError> {
+    match app.environments.get(FLAME_HTTP_TIMEOUT) {
+        Some(v) => v.parse::<u64>().map(Duration::from_secs).map_err(|e| {
+            FlameError::InvalidConfig(format!("invalid {FLAME_HTTP_TIMEOUT} <{v}>: {e}"))
+        }),
+        None => Ok(DEFAULT_REQUEST_TIMEOUT),
+    }
+}
+
+fn exit_status(child: &mut Child) -> Option<ExitStatus> {
+    child.try_wait().ok().flatten()
+}
+
+/// Fail the startup if the service exited, e.g. the port was taken by others.
+fn check_startup(child: &mut Option<Child>) -> Result<(), FlameError> {
+    match child.as_mut().and_then(exit_status) {
+        Some(status) => Err(FlameError::Internal(format!(
+            "service exited with {status} during startup"
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn start_service(
+    sandbox: &Sandbox,
+    cgroup: &Cgroup,
+    command: &str,
+    args: &[String],
+    envs: HashMap<String, String>,
+) -> Result<tokio::process::Child, FlameError> {
+    log::debug!(
+        "Try to start service by command <{command}> with args <{args:?}> and envs <{envs:?}>"
+    );
+
+    let mut cmd = sandbox.command(command, args);
+    cgroup.attach(&mut cmd)?;
+    cmd.envs(envs).kill_on_drop(true).spawn().map_err(|e| {
+        FlameError::InvalidConfig(format!(
+            "failed to start service by command <{command}>: {e}"
+        ))
+    })
+}
+
+async fn wait_for_port(port: u16, child: &mut Option<Child>) -> Result<(), FlameError> {
+    let start = tokio::time::Instant::now();
+    loop {
+        check_startup(child)?;
+        if TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+            return Ok(());
+        }
+        if start.elapsed() > STARTUP_TIMEOUT {
+            return Err(FlameError::Network(format!(
+                "service did not listen on port <{port}> in {STARTUP_TIMEOUT:?}"
+            )));
+        }
+        tokio::time::sleep(STARTUP_INTERVAL).await;
+    }
+}
+
+async fn wait_for_health(
+    client: &reqwest::Client,
+    url: &str,
+    child: &mut Option<Child>,
+) -> Result<(), FlameError> {
+    let start = tokio::time::Instant::now();
+    loop {
+        check_startup(child)?;
+        if let Ok(resp) = client.get(url).send().await {
+            if resp.status().is_success() {
+                return Ok(());
+            }
+        }
+        if start.elapsed() > STARTUP_TIMEOUT {
+            return Err(FlameError::Network(format!(
+                "service was not healthy at <{url}> in {STARTUP_TIMEOUT:?}"
+            )));
+        }
+        tokio::time::sleep(STARTUP_INTERVAL).await;
+    }
+}
+
+impl Drop for HttpShim {
+    fn drop(&mut self) {
+        if let Ok(Some(child)) = self.child.get_mut() {
+            let _ = child.start_kill();
+            log::debug!(
+                "The service <{}> was stopped",
+                child.id().unwrap_or_default()
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl Shim for HttpShim {
+    async fn on_session_enter(&mut self, ctx: &SessionContext) -> Result<(), FlameError> {
+        trace_fn!("HttpShim::on_session_enter");
+
+        self.post(
+            &self.session_enter_path,
+            &[(SESSION_ID_HEADER, &ctx.session_id)],
+            ctx.common_data.clone(),
+        )
+        .await
+        .map_err(|e| self.cgroup.check_oom(e))?;
+        self.session_context = Some(ctx.clone());
+
+        Ok(())
+    }
+
+    async fn on_task_invoke(
+        &self,
+        ctx: &TaskContext,
+        _updater: &TaskUpdater,
+    ) -> Result<Option<TaskOutput>, FlameError> {
+        trace_fn!("HttpShim::on_task_invoke");
+
+        let headers = [
+            (SESSION_ID_HEADER, ctx.session_id.as_str()),
+            (TASK_ID_HEADER, ctx.task_id.as_str()),
+        ];
+        let output = self
+            .work_dir
+            .guard(self.post(&self.task_path, &headers, ctx.input.clone()))
+            .await
+            .and_then(|res| res)
+            .map_err(|e| self.cgroup.check_oom(e));
+        let output = self.work_dir.check(output).await?;
+
+        Ok(Some(output))
+    }
+
+    async fn on_session_leave(&mut self) -> Result<(), FlameError> {
+        trace_fn!("HttpShim::on_session_leave");
+
+        let headers = match &self.session_context {
+            Some(ctx) => vec![(SESSION_ID_HEADER, ctx.session_id.as_str())],
+            None => vec![],
+        };
+        self.post(&self.session_leave_path, &headers, None)
+            .await
+            .map_err(|e| self.cgroup.check_oom(e))?;
+        self.session_context = None;
+
+        Ok(())
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.exit_status().is_none() && self.work_dir.is_healthy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::workdir;
+    use common::apis::Shim as ShimType;
+
+    /// The request received by the test server.
+    #[derive(Clone, Debug, Default)]
+    struct Received {
+        method: String,
+        path: String,
+        headers: HashMap<String, String>,
+        body: String,
+    }
+
+    /// Serve a minimal HTTP server on a local port for the tests: it replies the body
+    /// of the request, or 500 for the path `/fail`.
+    async fn serve() -> (u16, Arc<Mutex<Vec<Received>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = Arc::new(Mutex::new(vec![]));
+
+        let requests = received.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let req = read_request(&mut stream).await;
+                let (status, body) = match req.path.as_str() {
+                    "/fail" => ("500 Internal Server Error", "boom".to_string()),
+                    _ => ("200 OK", req.body.clone()),
+                };
+                requests.lock().unwrap().push(req);
+                let resp = format!(
+                    "HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(resp.as_bytes()).await;
+            }
+        });
+
+        (port, received)
+    }
+
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> Received {
+        let mut buf = vec![];
+        let header_end = loop {
+            let mut chunk = [0u8; 1024];
+            let n = stream.read(&mut chunk).await.unwrap_or_default();
+            buf.extend_from_slice(&chunk[..n]);
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos;
+            }
+            if n == 0 {
+                return Received::default();
+            }
+        };
+
+        let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+        let mut lines = head.lines();
+        let mut request_line = lines.next().unwrap_or_default().split(' ');
+        let method = request_line.next().unwrap_or_default().to_string();
+        let path = request_line.next().unwrap_or_default().to_string();
+        let headers: HashMap<String, String> = lines
+            .filter_map(|l| l.split_once(':'))
+            .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+            .collect();
+
+        let len = headers
+            .get("content-length")
+            .and_then(|l| l.parse::<usize>().ok())
+            .unwrap_or_default();
+        let mut body = buf[header_end + 4..].to_vec();
+        while body.len() < len {
+            let mut chunk = [0u8; 1024];
+            let n = stream.read(&mut chunk).await.unwrap_or_default();
+            if n == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..n]);
+        }
+
+        Received {
+            method,
+            path,
+            headers,
+            body: String::from_utf8_lossy(&body).to_string(),
+        }
+    }
+
+    async fn new_shim(
+        port: u16,
+        command: Option<&str>,
+        envs: &[(&str, &str)],
+    ) -> Result<ShimPtr, FlameError> {
+        let executor = Executor::for_test(&format!("exe-http-{}", Uuid::new_v4().simple()));
+        let mut app = workdir::tests::new_app(None);
+        app.shim = ShimType::Http;
+        app.environments
+            .insert(FLAME_HTTP_PORT.to_string(), port.to_string());
+        for (k, v) in envs {
+            app.environments.insert(k.to_string(), v.to_string());
+        }
+        if let Some(command) = command {
+            app.command = Some("sh".to_string());
+            app.arguments = vec!["-c".to_string(), command.to_string()];
+        }
+
+        HttpShim::new_ptr(&executor, &app).await
+    }
+
+    fn new_task(input: &str) -> TaskContext {
+        TaskContext {
+            task_id: "1".to_string(),
+            session_id: "ssn-1".to_string(),
+            input: Some(Bytes::from(input.to_string())),
+            output: None,
+        }
+    }
+
+    #[test]
+    fn test_post_task() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            let (port, received) = serve().await;
+            let shim = new_shim(port, None, &[]).await?;
+
+            let (updater, _updates) = tokio::sync::mpsc::channel(1);
+            let output = shim
+                .read()
+                .await
+                .on_task_invoke(&new_task("hello"), &updater)
+                .await?;
+            assert_eq!(output, Some(Bytes::from("hello")));
+
+            let req = received.lock().unwrap().last().cloned().unwrap_or_default();
+            assert_eq!(req.method, "POST");
+            assert_eq!(req.path, DEFAULT_TASK_PATH);
+            assert_eq!(req.body, "hello");
+            assert_eq!(
+                req.headers.get(SESSION_ID_HEADER).map(String::as_str),
+                Some("ssn-1")
+            );
+            assert_eq!(
+                req.headers.get(TASK_ID_HEADER).map(String::as_str),
+                Some("1")
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_task_failure() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            let (port, _) = serve().await;
+            let shim = new_shim(port, None, &[(FLAME_HTTP_TASK_PATH, "/fail")]).await?;
+
+            // The non-success status fails the task with the response body.
+            let (updater, _updates) = tokio::sync::mpsc::channel(1);
+            let shim = shim.read().await;
+            match shim.on_task_invoke(&new_task("hello"), &updater).await {
+                Err(FlameError::Internal(msg)) => {
+                    assert!(msg.contains("500"), "{msg}");
+                    assert!(msg.contains("boom"), "{msg}");
+                }
+                res => panic!("unexpected result: {res:?}"),
+            }
+            assert!(shim.is_healthy());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_service_exit() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            // The service exited during startup.
+            let port = free_port()?;
+            assert!(new_shim(port, Some("exit 3"), &[]).await.is_err());
+
+            // The service exited after startup, so the executor is released.
+            let (port, _) = serve().await;
+            let shim = new_shim(port, Some("sleep 0.2"), &[]).await?;
+            let mut healthy = true;
+            for _ in 0..50 {
+                healthy = shim.read().await.is_healthy();
+                if !healthy {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            assert!(!healthy);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_request_timeout() -> Result<(), FlameError> {
+        let mut app = workdir::tests::new_app(None);
+        assert_eq!(request_timeout(&app)?, DEFAULT_REQUEST_TIMEOUT);
+
+        app.environments
+            .insert(FLAME_HTTP_TIMEOUT.to_string(), "30".to_string());
+        assert_eq!(request_timeout(&app)?, Duration::from_secs(30));
+
+        app.environments
+            .insert(FLAME_HTTP_TIMEOUT.to_string(), "soon".to_string());
+        assert!(request_timeout(&app).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_free_port() -> Result<(), FlameError> {
+        let port = free_port()?;
+        assert_ne!(port, 0);
+        // The port is released for the service to listen on.
+        assert!(TcpListener::bind(("127.0.0.1", port)).is_ok());
+
+        Ok(())
+    }
+}
@@ -14,9 +14,9 @@ limitations under the License.
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 
-use crate::shims::{Shim, ShimPtr};
+use crate::shims::{Shim, ShimPtr, TaskUpdater};
 use common::apis::{ApplicationContext, SessionContext, TaskContext, TaskOutput};
 use common::{trace::TraceFn, trace_fn, FlameError};
 
@@ -29,7 +29,7 @@ impl LogShim {
     pub fn new_ptr(_: &ApplicationContext) -> ShimPtr {
         trace_fn!("LogShim::new_ptr");
 
-        Arc::new(Mutex::new(Self {
+        Arc::new(RwLock::new(Self {
             session_context: None,
         }))
     }
@@ -52,8 +52,9 @@ impl Shim for LogShim {
     }
 
     async fn on_task_invoke(
-        &mut self,
+        &self,
         ctx: &TaskContext,
+        _updater: &TaskUpdater,
     ) -> Result<Option<TaskOutput>, FlameError> {
         trace_fn!("LogShim::on_task_invoke");
 
@@ -12,28 +12,57 @@ limitations under the License.
 */
 
 mod grpc_shim;
+mod http_shim;
 mod log_shim;
+mod shell_shim;
+mod stdio_shim;
+mod wasm_cache;
 mod wasm_shim;
 
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use grpc_shim::GrpcShim;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, RwLock};
 
+use self::http_shim::HttpShim;
 use self::log_shim::LogShim;
+use self::shell_shim::ShellShim;
+use self::stdio_shim::StdioShim;
 use self::wasm_shim::WasmShim;
+use crate::executor::Executor;
+use crate::package;
 
-use common::apis::{ApplicationContext, SessionContext, Shim as ShimType, TaskContext, TaskOutput};
+use common::apis::{
+    ApplicationContext, SessionContext, Shim as ShimType, TaskContext, TaskOutput, TaskProgress,
+};
 
 use common::FlameError;
 
-pub type ShimPtr = Arc<Mutex<dyn Shim>>;
+// The tasks are invoked by the shared reference of the shim, so they could run concurrently;
+// the session is entered and left exclusively.
+pub type ShimPtr = Arc<RwLock<dyn Shim>>;
+
+/// The update of the running task, which is relayed to the watchers of the task.
+#[derive(Clone, Debug, Default)]
+pub struct TaskUpdate {
+    pub progress: Option<TaskProgress>,
+    pub partial_output: Option<TaskOutput>,
+}
+
+/// The shims report the updates of the running task by the updater.
+pub type TaskUpdater = mpsc::Sender<TaskUpdate>;
+
+pub async fn new(executor: &Executor, app: &ApplicationContext) -> Result<ShimPtr, FlameError> {
+    // Resolve the command in the package of the application, if any.
+    let app = &package::prepare(app).await?;
 
-pub async fn new(app: &ApplicationContext) -> Result<ShimPtr, FlameError> {
     match app.shim {
-        ShimType::Wasm => Ok(WasmShim::new_ptr(app).await?),
-        ShimType::Grpc => Ok(GrpcShim::new_ptr(app).await?),
+        ShimType::Wasm => Ok(WasmShim::new_ptr(executor, app).await?),
+        ShimType::Grpc => Ok(GrpcShim::new_ptr(executor, app).await?),
+        ShimType::Stdio => Ok(StdioShim::new_ptr(executor, app).await?),
+        ShimType::Shell => Ok(ShellShim::new_ptr(executor, app)?),
+        ShimType::Http => Ok(HttpShim::new_ptr(executor, app).await?),
         _ => Ok(LogShim::new_ptr(app)),
     }
 }
@@ -41,7 +70,17 @@ pub async fn new(app: &ApplicationContext) -> Result<ShimPtr, FlameError> {
 #[async_trait]
 pub trait Shim: Send + Sync + 'static {
     async fn on_session_enter(&mut self, ctx: &SessionContext) -> Result<(), FlameError>;
-    async fn on_task_invoke(&mut self, ctx: &TaskContext)
-        -> Result<Option<TaskOutput>, FlameError>;
+    /// Invoke the task, which may be called concurrently up to the concurrency of the
+    /// application; the shim serializes the calls if its service can not handle them at once.
+    async fn on_task_invoke(
+        &self,
+        ctx: &TaskContext,
+        updater: &TaskUpdater,
+    ) -> Result<Option<TaskOutput>, FlameError>;
     async fn on_session_leave(&mut self) -> Result<(), FlameError>;
+
+    /// Whether the shim is still healthy, e.g. its service did not crash repeatedly.
+    fn is_healthy(&self) -> bool {
+        true
+    }
 }
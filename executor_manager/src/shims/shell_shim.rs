@@ -0,0 +1,253 @@
+/*
+Copyright 2025 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::cgroup::Cgroup;
+use crate::executor::Executor;
+use crate::sandbox::Sandbox;
+use crate::shims::{Shim, ShimPtr, TaskUpdater};
+use crate::workdir::WorkDir;
+use common::apis::{ApplicationContext, SessionContext, TaskContext, TaskOutput};
+use common::{trace::TraceFn, trace_fn, FlameError};
+
+const FLAME_SESSION_ID: &str = "FLAME_SESSION_ID";
+const FLAME_TASK_ID: &str = "FLAME_TASK_ID";
+const FLAME_COMMON_DATA: &str = "FLAME_COMMON_DATA";
+
+/// The ShellShim runs the application command once per task in the work directory of the executor.
+/// The input of the task is piped to stdin and stdout is captured as the output; the
+/// session/task IDs are passed by `FLAME_SESSION_ID`/`FLAME_TASK_ID`, and the common data
+/// of the session is written to a file whose path is passed by `FLAME_COMMON_DATA`.
+/// A non-zero exit code fails the task with stderr attached.
+pub struct ShellShim {
+    app: ApplicationContext,
+    session_context: Option<SessionContext>,
+    common_data_file: Option<PathBuf>,
+    cgroup: Cgroup,
+    sandbox: Sandbox,
+    work_dir: WorkDir,
+}
+
+impl ShellShim {
+    pub fn new_ptr(executor: &Executor, app: &ApplicationContext) -> Result<ShimPtr, FlameError> {
+        trace_fn!("ShellShim::new_ptr");
+
+        let work_dir = WorkDir::new(&executor.id, app)?;
+        let sandbox = Sandbox::new(app, &work_dir)?;
+
+        Ok(Arc::new(RwLock::new(Self {
+            app: app.clone(),
+            session_context: None,
+            common_data_file: None,
+            cgroup: Cgroup::new(executor)?,
+            sandbox,
+            work_dir,
+        })))
+    }
+
+    /// Run the command for the task, and return its stdout as the output.
+    async fn run_task(&self, ctx: &TaskContext) -> Result<Option<TaskOutput>, FlameError> {
+        let command = self.app.command.clone().unwrap_or_default();
+        let args = self.app.arguments.clone();
+        let working_directory = self.work_dir.path().display().to_string();
+
+        let mut envs = self.app.environments.clone();
+        envs.insert(FLAME_SESSION_ID.to_string(), ctx.session_id.clone());
+        envs.insert(FLAME_TASK_ID.to_string(), ctx.task_id.clone());
+        if let Some(path) = &self.common_data_file {
+            envs.insert(FLAME_COMMON_DATA.to_string(), path.display().to_string());
+        }
+
+        log::debug!(
+            "Try to run task <{}/{}> by command <{command}> with args <{args:?}> in <{working_directory}>",
+            ctx.session_id,
+            ctx.task_id
+        );
+
+        let mut cmd = self.sandbox.command(&command, &args);
+        self.cgroup.attach(&mut cmd)?;
+        let mut child = cmd
+            .envs(envs)
+            .current_dir(&working_directory)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                FlameError::InvalidConfig(format!("failed to run command <{command}>: {e}"))
+            })?;
+
+        // Write the input in background, so the command can consume stdin and produce
+        // stdout at the same time; stdin is closed once the input is written.
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or(FlameError::Internal("no stdin of command".to_string()))?;
+        let input = ctx.input.clone().unwrap_or_default();
+        let writer = tokio::spawn(async move {
+            if let Err(e) = stdin.write_all(&input).await {
+                log::warn!("Failed to write task input: {e}");
+            }
+        });
+
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|e| FlameError::Internal(format!("failed to wait for command: {e}")))?;
+        let _ = writer.await;
+
+        if !output.status.success() {
+            return Err(self.cgroup.check_oom(FlameError::Internal(format!(
+                "command <{command}> failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            ))));
+        }
+
+        Ok(Some(Bytes::from(output.stdout)))
+    }
+
+    fn remove_common_data(&mut self) {
+        if let Some(path) = self.common_data_file.take() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                log::warn!("Failed to remove common data <{}>: {e}", path.display());
+            }
+        }
+    }
+}
+
+impl Drop for ShellShim {
+    fn drop(&mut self) {
+        self.remove_common_data();
+    }
+}
+
+#[async_trait]
+impl Shim for ShellShim {
+    async fn on_session_enter(&mut self, ctx: &SessionContext) -> Result<(), FlameError> {
+        trace_fn!("ShellShim::on_session_enter");
+
+        self.remove_common_data();
+        if let Some(data) = &ctx.common_data {
+            let path = self
+                .work_dir
+                .path()
+                .join(format!("flame-common-data-{}", Uuid::new_v4()));
+            tokio::fs::write(&path, data).await.map_err(|e| {
+                FlameError::Internal(format!(
+                    "failed to write common data <{}>: {e}",
+                    path.display()
+                ))
+            })?;
+            self.common_data_file = Some(path);
+        }
+
+        self.session_context = Some(ctx.clone());
+
+        Ok(())
+    }
+
+    async fn on_task_invoke(
+        &self,
+        ctx: &TaskContext,
+        _updater: &TaskUpdater,
+    ) -> Result<Option<TaskOutput>, FlameError> {
+        trace_fn!("ShellShim::on_task_invoke");
+
+        // The command is killed if the task is stopped by the quota.
+        let res = self
+            .work_dir
+            .guard(self.run_task(ctx))
+            .await
+            .and_then(|res| res);
+        self.work_dir.check(res).await
+    }
+
+    async fn on_session_leave(&mut self) -> Result<(), FlameError> {
+        trace_fn!("ShellShim::on_session_leave");
+
+        self.remove_common_data();
+        self.session_context = None;
+
+        Ok(())
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.work_dir.is_healthy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workdir;
+
+    fn new_shim(script: &str) -> Result<ShimPtr, FlameError> {
+        let executor = Executor::for_test(&format!("exe-shell-{}", Uuid::new_v4().simple()));
+        let mut app = workdir::tests::new_app(None);
+        app.command = Some("sh".to_string());
+        app.arguments = vec!["-c".to_string(), script.to_string()];
+
+        ShellShim::new_ptr(&executor, &app)
+    }
+
+    fn invoke(shim: &ShimPtr, input: &str) -> Result<Option<TaskOutput>, FlameError> {
+        tokio_test::block_on(async {
+            let (updater, _updates) = tokio::sync::mpsc::channel(1);
+            let ctx = TaskContext {
+                task_id: "1".to_string(),
+                session_id: "ssn-1".to_string(),
+                input: Some(Bytes::from(input.to_string())),
+                output: None,
+            };
+            shim.read().await.on_task_invoke(&ctx, &updater).await
+        })
+    }
+
+    #[test]
+    fn test_task_output() -> Result<(), FlameError> {
+        let shim = tokio_test::block_on(async { new_shim("echo $FLAME_TASK_ID; cat") })?;
+
+        let output = invoke(&shim, "hello")?;
+        assert_eq!(output, Some(Bytes::from("1\nhello")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_task_exit_code() -> Result<(), FlameError> {
+        let shim = tokio_test::block_on(async { new_shim("echo oops >&2; exit 3") })?;
+
+        // The non-zero exit code fails the task with stderr attached.
+        match invoke(&shim, "") {
+            Err(FlameError::Internal(msg)) => {
+                assert!(msg.contains("exit status: 3"), "{msg}");
+                assert!(msg.contains("oops"), "{msg}");
+            }
+            res => panic!("unexpected result: {res:?}"),
+        }
+
+        Ok(())
+    }
+}
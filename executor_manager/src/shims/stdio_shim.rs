@@ -0,0 +1,434 @@
+/*
+Copyright 2025 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::env;
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::cgroup::Cgroup;
+use crate::executor::Executor;
+use crate::logs::{ExecutorLog, ExecutorLogPtr};
+use crate::sandbox::Sandbox;
+use crate::shims::{Shim, ShimPtr, TaskUpdater};
+use crate::workdir::WorkDir;
+use common::apis::{ApplicationContext, SessionContext, TaskContext, TaskOutput};
+use common::{lock_ptr, trace::TraceFn, trace_fn, FlameError};
+
+const RUST_LOG: &str = "RUST_LOG";
+const DEFAULT_SVC_LOG_LEVEL: &str = "info";
+
+const SESSION_ENTER_FRAME: u8 = 1;
+const TASK_INVOKE_FRAME: u8 = 2;
+const SESSION_LEAVE_FRAME: u8 = 3;
+
+const OUTPUT_FRAME: u8 = 1;
+const ERROR_FRAME: u8 = 2;
+
+// The time to wait for the exchange to end after the service was stopped.
+const SERVICE_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The StdioShim starts the application command once per session, and talks to it by
+/// length-prefixed frames on its stdin/stdout, so any language can be a Flame service.
+///
+/// Each frame is a 1-byte kind, a 4-byte big-endian payload length and the payload.
+/// The shim writes the following frames to the stdin of the service:
+///   * `1`, session enter: the payload is the common data of the session, if any;
+///   * `2`, task invoke: the payload is the input of the task, if any;
+///   * `3`, session leave: the payload is empty.
+///
+/// The service replies exactly one frame on its stdout for each of them:
+///   * `1`, output: the payload is the output of the task, empty for session enter/leave;
+///   * `2`, error: the payload is an UTF-8 error message.
+///
+/// The stderr of the service is captured into the log of the executor. The frames are
+/// exchanged one by one, so the concurrent tasks are invoked in turn. The service is not
+/// restarted if it exited, as its state of the session is lost; the shim becomes
+/// unhealthy instead, so the executor is released. An exchange is never cancelled in the
+/// middle of a frame, e.g. by the exceeded disk quota; the service is stopped instead.
+pub struct StdioShim {
+    session_context: Option<SessionContext>,
+    // The service is checked for exit without the lock of the pipe.
+    child: std::sync::Mutex<Child>,
+    pipe: Mutex<StdioPipe>,
+    log: ExecutorLogPtr,
+    cgroup: Cgroup,
+    sandbox: Sandbox,
+    work_dir: WorkDir,
+}
+
+impl StdioShim {
+    pub async fn new_ptr(
+        executor: &Executor,
+        app: &ApplicationContext,
+    ) -> Result<ShimPtr, FlameError> {
+        trace_fn!("StdioShim::new_ptr");
+
+        let command = app.command.clone().unwrap_or_default();
+        let args = app.arguments.clone();
+        let log_level = env::var(RUST_LOG).unwrap_or(String::from(DEFAULT_SVC_LOG_LEVEL));
+        let mut envs = app.environments.clone();
+        envs.insert(RUST_LOG.to_string(), log_level);
+
+        log::debug!(
+            "Try to start service by command <{command}> with args <{args:?}> and envs <{envs:?}>"
+        );
+
+        let work_dir = WorkDir::new(&executor.id, app)?;
+        let sandbox = Sandbox::new(app, &work_dir)?;
+        let cgroup = Cgroup::new(executor)?;
+        let mut cmd = sandbox.command(&command, &args);
+        cgroup.attach(&mut cmd)?;
+        let mut child = cmd
+            .envs(envs)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                FlameError::InvalidConfig(format!(
+                    "failed to start service by command <{command}>: {e}"
+                ))
+            })?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or(FlameError::Internal("no stdin of service".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or(FlameError::Internal("no stdout of service".to_string()))?;
+
+        let log = ExecutorLog::new_ptr(&executor.id);
+        if let Some(stderr) = child.stderr.take() {
+            log.capture("stderr", stderr);
+        }
+
+        log::debug!(
+            "The service <{}> was started.",
+            child.id().unwrap_or_default()
+        );
+
+        Ok(Arc::new(RwLock::new(Self {
+            session_context: None,
+            child: std::sync::Mutex::new(child),
+            pipe: Mutex::new(StdioPipe {
+                stdin: BufWriter::new(stdin),
+                stdout: BufReader::new(stdout),
+            }),
+            log,
+            cgroup,
+            sandbox,
+            work_dir,
+        })))
+    }
+
+    /// Send a request frame to the service, and wait for its reply; the log of the
+    /// service is tagged with the task during the exchange, if any.
+    async fn call(
+        &self,
+        kind: u8,
+        payload: Option<Bytes>,
+        task: Option<&TaskContext>,
+    ) -> Result<Bytes, FlameError> {
+        let reply = {
+            let mut pipe = self.pipe.lock().await;
+            if let Some(task) = task {
+                self.log
+                    .set_context(Some(task.session_id.clone()), Some(task.task_id.clone()))?;
+            }
+            let StdioPipe { stdin, stdout } = &mut *pipe;
+            let reply = match write_frame(stdin, kind, &payload.unwrap_or_default()).await {
+                Ok(()) => read_frame(stdout).await,
+                Err(e) => Err(e),
+            };
+            if let Some(task) = task {
+                self.log.set_context(Some(task.session_id.clone()), None)?;
+            }
+
+            reply
+        };
+
+        let (kind, payload) = reply.map_err(|e| match self.exit_status() {
+            Some(status) => FlameError::Internal(format!("service exited with {status}")),
+            None => e,
+        })?;
+
+        match kind {
+            OUTPUT_FRAME => Ok(Bytes::from(payload)),
+            ERROR_FRAME => Err(FlameError::Internal(
+                String::from_utf8_lossy(&payload).to_string(),
+            )),
+            _ => Err(FlameError::InvalidState(format!(
+                "unknown frame <{kind}> from service"
+            ))),
+        }
+    }
+
+    fn exit_status(&self) -> Option<ExitStatus> {
+        let mut child = lock_ptr!(self.child).ok()?;
+        child.try_wait().ok().flatten()
+    }
+
+    /// Stop the service, which fails its ongoing exchange by the closed pipes.
+    fn stop(&self) {
+        if let Ok(mut child) = lock_ptr!(self.child) {
+            if let Err(e) = child.start_kill() {
+                log::warn!("Failed to stop service: {e}");
+            }
+        }
+    }
+}
+
+/// Write a frame: the kind, the big-endian length of the payload and the payload.
+async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    kind: u8,
+    payload: &[u8],
+) -> Result<(), FlameError> {
+    let len = u32::try_from(payload.len())
+        .map_err(|_| FlameError::InvalidState("frame is too large".to_string()))?;
+
+    writer.write_u8(kind).await.map_err(io_error)?;
+    writer.write_u32(len).await.map_err(io_error)?;
+    writer.write_all(payload).await.map_err(io_error)?;
+    writer.flush().await.map_err(io_error)?;
+
+    Ok(())
+}
+
+/// Read a frame written by `write_frame`, and return its kind and payload.
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> Result<(u8, Vec<u8>), FlameError> {
+    let kind = reader.read_u8().await.map_err(io_error)?;
+    let len = reader.read_u32().await.map_err(io_error)?;
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload).await.map_err(io_error)?;
+
+    Ok((kind, payload))
+}
+
+struct StdioPipe {
+    stdin: BufWriter<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+fn io_error(e: std::io::Error) -> FlameError {
+    FlameError::Internal(format!("failed to talk to service: {e}"))
+}
+
+impl Drop for StdioShim {
+    fn drop(&mut self) {
+        if let Ok(child) = self.child.get_mut() {
+            let _ = child.start_kill();
+            log::debug!(
+                "The service <{}> was stopped",
+                child.id().unwrap_or_default()
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl Shim for StdioShim {
+    async fn on_session_enter(&mut self, ctx: &SessionContext) -> Result<(), FlameError> {
+        trace_fn!("StdioShim::on_session_enter");
+
+        self.log.set_context(Some(ctx.session_id.clone()), None)?;
+        self.call(SESSION_ENTER_FRAME, ctx.common_data.clone(), None)
+            .await
+            .map_err(|e| self.cgroup.check_oom(e))?;
+        self.session_context = Some(ctx.clone());
+
+        Ok(())
+    }
+
+    async fn on_task_invoke(
+        &self,
+        ctx: &TaskContext,
+        _updater: &TaskUpdater,
+    ) -> Result<Option<TaskOutput>, FlameError> {
+        trace_fn!("StdioShim::on_task_invoke");
+
+        let call = self.call(TASK_INVOKE_FRAME, ctx.input.clone(), Some(ctx));
+        tokio::pin!(call);
+        let output = tokio::select! {
+            res = &mut call => res,
+            // The work directory is unhealthy now, so the executor is released.
+            err = self.work_dir.exceeded() => {
+                self.stop();
+                // The pipes are closed by the stopped service, unless its children hold them.
+                let _ = tokio::time::timeout(SERVICE_STOP_TIMEOUT, call).await;
+                Err(err)
+            }
+        }
+        .map_err(|e| self.cgroup.check_oom(e));
+        let output = self.work_dir.check(output).await?;
+
+        Ok(Some(output))
+    }
+
+    async fn on_session_leave(&mut self) -> Result<(), FlameError> {
+        trace_fn!("StdioShim::on_session_leave");
+
+        self.call(SESSION_LEAVE_FRAME, None, None)
+            .await
+            .map_err(|e| self.cgroup.check_oom(e))?;
+        self.session_context = None;
+        self.log.set_context(None, None)?;
+
+        Ok(())
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.exit_status().is_none() && self.work_dir.is_healthy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::logs::{self, LOG_DIRECTORY};
+    use crate::workdir;
+
+    async fn new_shim(script: &str) -> Result<(String, ShimPtr), FlameError> {
+        let executor = Executor::for_test(&format!("exe-stdio-{}", Uuid::new_v4().simple()));
+        let mut app = workdir::tests::new_app(None);
+        app.command = Some("sh".to_string());
+        app.arguments = vec!["-c".to_string(), script.to_string()];
+
+        let shim = StdioShim::new_ptr(&executor, &app).await?;
+
+        Ok((executor.id, shim))
+    }
+
+    #[test]
+    fn test_quota_stop() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            tokio::time::pause();
+
+            // The service never replies, and the quota is exceeded while the task runs.
+            let (executor_id, shim) = new_shim("exec sleep 60").await?;
+            fs::write(
+                Path::new("/tmp/flame_test_work")
+                    .join(&executor_id)
+                    .join("data"),
+                vec![0u8; 4096],
+            )
+            .map_err(|e| FlameError::Internal(e.to_string()))?;
+
+            let (updater, _updates) = tokio::sync::mpsc::channel(1);
+            let ctx = TaskContext {
+                task_id: "1".to_string(),
+                session_id: "ssn-1".to_string(),
+                input: Some(Bytes::from("hello")),
+                output: None,
+            };
+            let shim = shim.read().await;
+            let res = shim.on_task_invoke(&ctx, &updater).await;
+            assert!(res.is_err());
+            // The service is stopped, so the executor is released.
+            assert!(!shim.is_healthy());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_stderr_log() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            fs::create_dir_all(LOG_DIRECTORY).map_err(|e| FlameError::Internal(e.to_string()))?;
+
+            // The service echoes the frames, so the session enter is replied as output.
+            let (executor_id, shim) = new_shim("echo oops >&2; cat").await?;
+            let ctx = SessionContext {
+                session_id: "ssn-1".to_string(),
+                application: workdir::tests::new_app(None),
+                slots: 1,
+                common_data: None,
+            };
+            shim.write().await.on_session_enter(&ctx).await?;
+
+            let path = Path::new(LOG_DIRECTORY).join(format!("{executor_id}.log"));
+            let mut captured = false;
+            for _ in 0..50 {
+                let log = fs::read_to_string(&path).unwrap_or_default();
+                if log
+                    .lines()
+                    .any(|l| l.contains("stderr") && l.ends_with("\toops"))
+                {
+                    captured = true;
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            logs::remove(&executor_id);
+            assert!(captured);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_frame_round_trip() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            let (mut writer, mut reader) = tokio::io::duplex(64);
+
+            write_frame(&mut writer, TASK_INVOKE_FRAME, b"hello").await?;
+            write_frame(&mut writer, SESSION_LEAVE_FRAME, &[]).await?;
+
+            assert_eq!(
+                read_frame(&mut reader).await?,
+                (TASK_INVOKE_FRAME, b"hello".to_vec())
+            );
+            assert_eq!(
+                read_frame(&mut reader).await?,
+                (SESSION_LEAVE_FRAME, vec![])
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_frame_encoding() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            let mut buf = vec![];
+            write_frame(&mut buf, OUTPUT_FRAME, b"abc").await?;
+            assert_eq!(buf, vec![OUTPUT_FRAME, 0, 0, 0, 3, b'a', b'b', b'c']);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_read_truncated_frame() {
+        tokio_test::block_on(async {
+            // The service exited after writing the header of the frame.
+            let mut buf: &[u8] = &[ERROR_FRAME, 0, 0, 0, 8, b'e'];
+            assert!(read_frame(&mut buf).await.is_err());
+        })
+    }
+}
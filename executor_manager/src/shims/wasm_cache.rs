@@ -0,0 +1,335 @@
+/*
+Copyright 2025 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{self, DirBuilder};
+use std::hash::{Hash, Hasher};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use wasmtime::component::Component;
+use wasmtime::{Config, Engine};
+
+use common::{lock_ptr, FlameError};
+
+// The compiled components of the node, which are reused after the executor manager restarted.
+const COMPONENT_CACHE_DIRECTORY: &str = "/tmp/flame/components";
+const COMPONENT_FILE_EXTENSION: &str = "cwasm";
+// The permission bits of the files writable by the group or others, and the sticky bit.
+const GROUP_OTHER_WRITE: u32 = 0o022;
+const STICKY: u32 = 0o1000;
+// The components are interrupted in each epoch to check their timeout and yield.
+const EPOCH_TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+static ENGINE: OnceLock<Engine> = OnceLock::new();
+
+lazy_static! {
+    // The compiled components by their cache key, shared by the executors of the node.
+    static ref COMPONENTS: Mutex<HashMap<String, Component>> = Mutex::new(HashMap::new());
+    // The components being compiled, so each component is compiled once by the executors.
+    static ref COMPILING: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// The engine shared by the components of all executors in the executor manager; its
+/// epoch is increased every `EPOCH_TICK_INTERVAL` to interrupt the components.
+pub fn engine() -> Result<Engine, FlameError> {
+    if let Some(engine) = ENGINE.get() {
+        return Ok(engine.clone());
+    }
+
+    let engine = new_engine()?;
+
+    match ENGINE.set(engine.clone()) {
+        Ok(()) => {
+            let ticker = engine.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(EPOCH_TICK_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    ticker.increment_epoch();
+                }
+            });
+
+            Ok(engine)
+        }
+        // Another executor created the engine first.
+        Err(_) => ENGINE
+            .get()
+            .cloned()
+            .ok_or(FlameError::Uninitialized("wasm engine".to_string())),
+    }
+}
+
+/// Create an engine for the components, which are interrupted by the epoch.
+pub fn new_engine() -> Result<Engine, FlameError> {
+    let mut config = Config::default();
+    config.wasm_component_model(true);
+    config.async_support(true);
+    config.epoch_interruption(true);
+
+    Engine::new(&config).map_err(|e| FlameError::Internal(e.to_string()))
+}
+
+/// Load the component: it's reused if the same component was compiled by the engine,
+/// either in memory or in the cache on disk; otherwise, it's compiled and cached.
+pub async fn load(engine: &Engine, path: &str) -> Result<Component, FlameError> {
+    let bytes = tokio::fs::read(path).await.map_err(|e| {
+        FlameError::InvalidConfig(format!("failed to read component <{path}>: {e}"))
+    })?;
+    let key = cache_key(engine, &bytes);
+
+    let lock = {
+        let mut compiling = lock_ptr!(COMPILING)?;
+        compiling.entry(key.clone()).or_default().clone()
+    };
+    let _guard = lock.lock().await;
+
+    if let Some(component) = lock_ptr!(COMPONENTS)?.get(&key) {
+        log::debug!("The component <{path}> was compiled as <{key}>.");
+        return Ok(component.clone());
+    }
+
+    let (engine, src, cache_key) = (engine.clone(), path.to_string(), key.clone());
+    let component =
+        tokio::task::spawn_blocking(move || load_or_compile(&engine, &src, &cache_key, &bytes))
+            .await
+            .map_err(|e| FlameError::Internal(format!("failed to compile component: {e}")))??;
+
+    lock_ptr!(COMPONENTS)?.insert(key, component.clone());
+
+    Ok(component)
+}
+
+/// The key of the compiled component: the SHA-256 of its content and the compatibility
+/// hash of the engine, as the compiled component only works with the same engine config.
+fn cache_key(engine: &Engine, bytes: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+
+    let mut sha = Sha256::new();
+    sha.update(bytes);
+    sha.update(hasher.finish().to_le_bytes());
+
+    format!("{:x}", sha.finalize())
+}
+
+fn load_or_compile(
+    engine: &Engine,
+    path: &str,
+    key: &str,
+    bytes: &[u8],
+) -> Result<Component, FlameError> {
+    let dir = Path::new(COMPONENT_CACHE_DIRECTORY);
+    // The cache is skipped if it may be written by others, e.g. planted in `/tmp`.
+    if let Err(e) = verify_dir(dir) {
+        log::warn!("The component cache is not trusted, compile component <{path}>: {e}");
+        return compile(engine, path, bytes);
+    }
+
+    let cache = dir.join(format!("{key}.{COMPONENT_FILE_EXTENSION}"));
+    if cache.is_file() {
+        match deserialize(engine, &cache) {
+            Ok(component) => {
+                log::debug!("Load component <{path}> from <{}>.", cache.display());
+                return Ok(component);
+            }
+            Err(e) => log::warn!("Failed to load component <{path}> from cache, recompile it: {e}"),
+        }
+    }
+
+    let component = compile(engine, path, bytes)?;
+
+    // The component works without the cache, so the errors of caching are ignored.
+    if let Err(e) = store(&component, &cache) {
+        log::warn!("Failed to cache component <{path}>: {e}");
+    }
+
+    Ok(component)
+}
+
+fn compile(engine: &Engine, path: &str, bytes: &[u8]) -> Result<Component, FlameError> {
+    log::info!("Try to compile component <{path}>.");
+    Component::from_binary(engine, bytes).map_err(|e| {
+        FlameError::InvalidConfig(format!("failed to compile component <{path}>: {e:#}"))
+    })
+}
+
+/// Verify that the cache directory can only be written by the executor manager: it's
+/// owned by the effective user and not writable by others, and its ancestors are owned
+/// by the user or root and not writable by others unless sticky, e.g. `/tmp`. The
+/// directory is created if not found.
+fn verify_dir(dir: &Path) -> Result<(), FlameError> {
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(|e| io_error(dir, e))?;
+
+    verify_owned(dir)?;
+    for ancestor in dir.ancestors().skip(1) {
+        let meta = fs::metadata(ancestor).map_err(|e| io_error(ancestor, e))?;
+        let mode = meta.mode();
+        if meta.uid() != 0 && meta.uid() != euid() {
+            return Err(untrusted(ancestor, "is owned by another user"));
+        }
+        if mode & GROUP_OTHER_WRITE != 0 && mode & STICKY == 0 {
+            return Err(untrusted(ancestor, "is writable by others"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify that the path is not a symlink, owned by the effective user and not writable
+/// by others.
+fn verify_owned(path: &Path) -> Result<(), FlameError> {
+    let meta = fs::symlink_metadata(path).map_err(|e| io_error(path, e))?;
+    if meta.file_type().is_symlink() {
+        return Err(untrusted(path, "is a symlink"));
+    }
+    if meta.uid() != euid() {
+        return Err(untrusted(path, "is owned by another user"));
+    }
+    if meta.mode() & GROUP_OTHER_WRITE != 0 {
+        return Err(untrusted(path, "is writable by others"));
+    }
+
+    Ok(())
+}
+
+#[allow(unsafe_code)]
+fn euid() -> u32 {
+    // SAFETY: geteuid has no preconditions and never fails.
+    unsafe { libc::geteuid() }
+}
+
+fn untrusted(path: &Path, reason: &str) -> FlameError {
+    FlameError::InvalidState(format!("<{}> {reason}", path.display()))
+}
+
+/// Write the compiled component into the cache by a temporary file, so the cache is never partial.
+fn store(component: &Component, cache: &Path) -> Result<(), FlameError> {
+    let data = component
+        .serialize()
+        .map_err(|e| FlameError::Internal(format!("failed to serialize component: {e}")))?;
+    let tmp = cache.with_extension(format!("{}.tmp", Uuid::new_v4()));
+    fs::write(&tmp, data).map_err(|e| io_error(&tmp, e))?;
+    fs::rename(&tmp, cache).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        io_error(cache, e)
+    })
+}
+
+#[allow(unsafe_code)]
+fn deserialize(engine: &Engine, cache: &Path) -> Result<Component, FlameError> {
+    verify_owned(cache)?;
+
+    // SAFETY: the cache is only written by the executor manager, see `verify_dir` and
+    // `verify_owned`, from the components compiled by an engine of the same config, which
+    // is part of the key; wasmtime also rejects the files compiled by an incompatible engine.
+    unsafe { Component::deserialize_file(engine, cache) }
+        .map_err(|e| FlameError::Internal(format!("failed to load <{}>: {e:#}", cache.display())))
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> FlameError {
+    FlameError::Internal(format!("failed to write <{}>: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn new_dir(name: &str) -> Result<PathBuf, FlameError> {
+        let dir = Path::new("/tmp").join(format!("flame_test_{name}_{}", Uuid::new_v4().simple()));
+        verify_dir(&dir)?;
+
+        Ok(dir)
+    }
+
+    fn set_mode(path: &Path, mode: u32) -> Result<(), FlameError> {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).map_err(|e| io_error(path, e))
+    }
+
+    #[test]
+    fn test_cache_key() -> Result<(), FlameError> {
+        let engine = new_engine()?;
+        let key = cache_key(&engine, b"component");
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, cache_key(&engine, b"component"));
+        assert_ne!(key, cache_key(&engine, b"another component"));
+
+        // The components compiled by an engine of another config are not reused.
+        let mut config = Config::default();
+        config.wasm_component_model(true);
+        let other = Engine::new(&config).map_err(|e| FlameError::Internal(e.to_string()))?;
+        assert_ne!(key, cache_key(&other, b"component"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_dir() -> Result<(), FlameError> {
+        let dir = new_dir("cache_dir")?;
+        assert_eq!(
+            fs::metadata(&dir).map_err(|e| io_error(&dir, e))?.mode() & 0o777,
+            0o700
+        );
+
+        // The directory writable by others is not trusted.
+        set_mode(&dir, 0o777)?;
+        assert!(verify_dir(&dir).is_err());
+
+        // Neither is the directory under the one writable by others.
+        let child = dir.join("components");
+        assert!(verify_dir(&child).is_err());
+
+        set_mode(&dir, 0o700)?;
+        assert!(verify_dir(&child).is_ok());
+
+        let _ = fs::remove_dir_all(&dir);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_owned() -> Result<(), FlameError> {
+        let dir = new_dir("cache_file")?;
+        let file = dir.join(format!("key.{COMPONENT_FILE_EXTENSION}"));
+        fs::write(&file, b"cwasm").map_err(|e| io_error(&file, e))?;
+        set_mode(&file, 0o644)?;
+        assert!(verify_owned(&file).is_ok());
+
+        // The planted file writable by others is not loaded.
+        set_mode(&file, 0o666)?;
+        assert!(verify_owned(&file).is_err());
+
+        // Neither is the symlink to another file.
+        let link = dir.join(format!("link.{COMPONENT_FILE_EXTENSION}"));
+        std::os::unix::fs::symlink(&file, &link).map_err(|e| io_error(&link, e))?;
+        assert!(verify_owned(&link).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+
+        Ok(())
+    }
+}
@@ -11,18 +11,48 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use async_trait::async_trait;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
 use wasmtime::component::*;
-use wasmtime::{Config, Engine, Store};
-use wasmtime_wasi::preview2::{command, Table, WasiCtx, WasiCtxBuilder, WasiView};
-
+use wasmtime::{Engine, Store, StoreContextMut, StoreLimits, StoreLimitsBuilder, UpdateDeadline};
+use wasmtime_wasi::preview2::pipe::AsyncWriteStream;
+use wasmtime_wasi::preview2::{
+    command, DirPerms, FilePerms, HostOutputStream, StdoutStream, Table, WasiCtx, WasiCtxBuilder,
+    WasiView,
+};
+use wasmtime_wasi::{ambient_authority, Dir};
+
+use crate::executor::Executor;
+use crate::logs::{ExecutorLog, ExecutorLogPtr};
+use crate::shims::wasm_cache;
+use crate::shims::wasm_shim::component::flame::host;
 use crate::shims::wasm_shim::exports::component::flame::service;
-use crate::shims::{Shim, ShimPtr};
-use common::{self, apis, trace::TraceFn, trace_fn, FlameError};
+use crate::shims::{Shim, ShimPtr, TaskUpdate, TaskUpdater};
+use crate::workdir::WorkDir;
+use common::ptr::{self, MutexPtr};
+use common::{self, apis, lock_ptr, trace::TraceFn, trace_fn, FlameError};
+
+// If `true`, the stdout/stderr of the component are captured into the log of the
+// executor instead of inherited from the executor manager.
+const FLAME_WASM_CAPTURE_OUTPUT: &str = "FLAME_WASM_CAPTURE_OUTPUT";
+// The guest path of the work directory, which is the current directory of the component.
+const WORK_DIR_GUEST_PATH: &str = ".";
+const OUTPUT_BUFFER_SIZE: usize = 64 * 1024;
+// The timeout in seconds of each call of the component, e.g. a task.
+const FLAME_WASM_TIMEOUT: &str = "FLAME_WASM_TIMEOUT";
+// The size in bytes of the scratch of the session, which is taken from the memory of the
+// executor; a quarter of the memory by default.
+const FLAME_WASM_SCRATCH_SIZE: &str = "FLAME_WASM_SCRATCH_SIZE";
+const DEFAULT_SCRATCH_RATIO: u64 = 4;
+// The streams in the log of the executor for the logs and progress reported by the component.
+const LOG_STREAM: &str = "log";
+const PROGRESS_STREAM: &str = "progress";
 
 wasmtime::component::bindgen!({
     path: "wit/flame.wit",
@@ -30,47 +60,155 @@ wasmtime::component::bindgen!({
     async: true
 });
 
+/// The WasmShim runs the component of the application in the executor manager. The
+/// component gets the command and arguments of the application as its args, the
+/// environments as its env vars, and the work directory of the executor as its current
+/// directory; the package of the application, if any, is preopened read-only.
+///
+/// The component can call back the host to write logs, read the common data of the
+/// session, report the progress of the task and keep data in the scratch of the session.
+///
+/// The memory of the executor is shared by the component and the scratch of the session:
+/// the scratch has `FLAME_WASM_SCRATCH_SIZE` bytes of it, and the component has the rest.
+/// Each call of the component is interrupted once it exceeds `FLAME_WASM_TIMEOUT` seconds,
+/// if set.
+/// If the component trapped, e.g. timed out or out of memory, the call fails and the
+/// component is instantiated again for the next call.
 pub struct WasmShim {
+    executor_id: String,
+    cmd: String,
+    app: apis::ApplicationContext,
     session_context: Option<apis::SessionContext>,
-    instance: Flame,
+    engine: Engine,
+    linker: Linker<ServerWasiView>,
+    component: Component,
+    // The instance of the component, which is dropped once it trapped; it runs one call
+    // at a time, so the concurrent tasks are invoked in turn.
+    instance: Mutex<Option<WasmInstance>>,
+    // The memory limit of the component in bytes, or 0 if unlimited.
+    memory: u64,
+    // The timeout of each call of the component.
+    timeout: Option<Duration>,
+    // The state of the host, which is kept across the instances of the component.
+    host: MutexPtr<HostState>,
+    // The output of the component is captured into the log of the executor, if configured.
+    log: ExecutorLogPtr,
+    // The component is run in the work directory of the executor.
+    work_dir: WorkDir,
+}
+
+struct WasmInstance {
+    flame: Flame,
     store: Store<ServerWasiView>,
 }
 
 impl WasmShim {
-    pub async fn new_ptr(app: &apis::ApplicationContext) -> Result<ShimPtr, common::FlameError> {
+    pub async fn new_ptr(
+        executor: &Executor,
+        app: &apis::ApplicationContext,
+    ) -> Result<ShimPtr, common::FlameError> {
         trace_fn!("WasmShim::new_ptr");
 
-        let mut config = Config::default();
-        config.wasm_component_model(true);
-        config.async_support(true);
+        let log = ExecutorLog::new_ptr(&executor.id);
+        let work_dir = WorkDir::new(&executor.id, app)?;
+        let timeout = wasm_timeout(app)?;
+        let (memory, scratch_size) = memory_budget(app, executor.resreq.memory)?;
 
-        let engine =
-            Engine::new(&config).map_err(|e| common::FlameError::Internal(e.to_string()))?;
+        let engine = wasm_cache::engine()?;
         let mut linker = Linker::new(&engine);
         command::add_to_linker(&mut linker)
             .map_err(|e| common::FlameError::Internal(e.to_string()))?;
-        let wasi_view = ServerWasiView::new();
-        let mut store = Store::new(&engine, wasi_view);
-
+        Flame::add_to_linker(&mut linker, |view: &mut ServerWasiView| view)
+            .map_err(|e| common::FlameError::Internal(e.to_string()))?;
         let cmd = app
             .command
             .clone()
             .ok_or(FlameError::InvalidConfig("command is empty".to_string()))?;
 
-        let component = Component::from_file(&engine, cmd)
-            .context("Component file not found")
-            .map_err(|e| common::FlameError::Internal(e.to_string()))?;
-
-        let (instance, _) = Flame::instantiate_async(&mut store, &component, &linker)
-            .await
-            .context("Failed to instantiate the flame world")
-            .map_err(|e| common::FlameError::Internal(e.to_string()))?;
+        // The component is compiled once, and shared by the executors of the application.
+        let component = wasm_cache::load(&engine, &cmd).await?;
 
-        Ok(Arc::new(Mutex::new(WasmShim {
-            store,
-            instance,
+        let shim = WasmShim {
+            executor_id: executor.id.clone(),
+            cmd,
+            app: app.clone(),
             session_context: None,
-        })))
+            engine,
+            linker,
+            component,
+            instance: Mutex::new(None),
+            memory,
+            timeout,
+            host: ptr::new_ptr(HostState::new(scratch_size)),
+            log,
+            work_dir,
+        };
+        {
+            let mut instance = shim.instance.lock().await;
+            shim.ensure_instance(&mut instance).await?;
+        }
+
+        Ok(Arc::new(RwLock::new(shim)))
+    }
+
+    /// Make sure the component is instantiated, instantiate it again if it trapped.
+    async fn ensure_instance<'a>(
+        &self,
+        instance: &'a mut Option<WasmInstance>,
+    ) -> Result<&'a mut WasmInstance, FlameError> {
+        if instance.is_none() {
+            let wasi_view = ServerWasiView::new(
+                &self.cmd,
+                &self.app,
+                &self.work_dir,
+                &self.log,
+                &self.host,
+                self.memory,
+            )?;
+            let mut store = Store::new(&self.engine, wasi_view);
+            store.limiter(|view| &mut view.limits);
+            store.epoch_deadline_callback(on_epoch_deadline);
+            set_deadline(&mut store, self.timeout);
+
+            let (flame, _) = Flame::instantiate_async(&mut store, &self.component, &self.linker)
+                .await
+                .context("Failed to instantiate the flame world")
+                .map_err(|e| common::FlameError::Internal(e.to_string()))?;
+            let mut new_instance = WasmInstance { flame, store };
+
+            // Re-enter the session for the new instance.
+            if let Some(ctx) = &self.session_context {
+                set_deadline(&mut new_instance.store, self.timeout);
+                new_instance
+                    .flame
+                    .interface0
+                    .call_on_session_enter(&mut new_instance.store, &session_context(ctx))
+                    .await
+                    .map_err(|e| trap_error(&self.executor_id, e))?
+                    .map_err(|e| common::FlameError::Internal(e.to_string()))?;
+            }
+
+            *instance = Some(new_instance);
+        }
+
+        instance
+            .as_mut()
+            .ok_or(FlameError::Internal("no instance".to_string()))
+    }
+
+    /// Check the result of calling the component: if it trapped, the instance is dropped.
+    fn check_call<T, E: ToString>(
+        &self,
+        instance: &mut Option<WasmInstance>,
+        res: anyhow::Result<Result<T, E>>,
+    ) -> Result<T, FlameError> {
+        match res {
+            Ok(res) => res.map_err(|e| common::FlameError::Internal(e.to_string())),
+            Err(e) => {
+                *instance = None;
+                Err(trap_error(&self.executor_id, e))
+            }
+        }
     }
 }
 
@@ -82,18 +220,20 @@ impl Shim for WasmShim {
     ) -> Result<(), common::FlameError> {
         trace_fn!("WasmShim::on_session_enter");
 
-        let ssn_ctx = service::SessionContext {
-            session_id: ctx.session_id.clone(),
-            common_data: ctx.common_data.clone().map(apis::CommonData::into),
-        };
-
-        let _ = self
-            .instance
-            .interface0
-            .call_on_session_enter(&mut self.store, &ssn_ctx)
-            .await
-            .map_err(|e| common::FlameError::Internal(e.to_string()))?
-            .map_err(|e| common::FlameError::Internal(e.to_string()))?;
+        self.log.set_context(Some(ctx.session_id.clone()), None)?;
+        lock_ptr!(self.host)?.enter(ctx);
+
+        {
+            let mut instance = self.instance.lock().await;
+            let current = self.ensure_instance(&mut instance).await?;
+            set_deadline(&mut current.store, self.timeout);
+            let res = current
+                .flame
+                .interface0
+                .call_on_session_enter(&mut current.store, &session_context(ctx))
+                .await;
+            self.check_call(&mut instance, res)?;
+        }
 
         self.session_context = Some(ctx.clone());
 
@@ -101,8 +241,9 @@ impl Shim for WasmShim {
     }
 
     async fn on_task_invoke(
-        &mut self,
+        &self,
         ctx: &apis::TaskContext,
+        updater: &TaskUpdater,
     ) -> Result<Option<apis::TaskOutput>, common::FlameError> {
         trace_fn!("WasmShim::on_task_invoke");
 
@@ -111,19 +252,39 @@ impl Shim for WasmShim {
             task_id: ctx.task_id.clone(),
         };
 
-        let output = self
-            .instance
-            .interface0
-            .call_on_task_invoke(
-                &mut self.store,
-                &task_ctx,
-                ctx.input.clone().map(apis::TaskInput::into).as_ref(),
-            )
-            .await
-            .map_err(|e| common::FlameError::Internal(e.to_string()))?
-            .map_err(|e| common::FlameError::Internal(e.to_string()))?;
+        let mut instance = self.instance.lock().await;
+        self.log
+            .set_context(Some(ctx.session_id.clone()), Some(ctx.task_id.clone()))?;
+
+        let output = match self.ensure_instance(&mut instance).await {
+            Ok(current) => {
+                set_deadline(&mut current.store, self.timeout);
+                current.store.data_mut().updater = Some(updater.clone());
+                let res = self
+                    .work_dir
+                    .guard(current.flame.interface0.call_on_task_invoke(
+                        &mut current.store,
+                        &task_ctx,
+                        ctx.input.clone().map(apis::TaskInput::into).as_ref(),
+                    ))
+                    .await;
+                current.store.data_mut().updater = None;
+                match res {
+                    Ok(res) => self.check_call(&mut instance, res),
+                    // The instance is dropped as its call was stopped by the quota.
+                    Err(e) => {
+                        *instance = None;
+                        Err(e)
+                    }
+                }
+            }
+            Err(e) => Err(e),
+        };
+        let output = self.work_dir.check(output).await;
+
+        self.log.set_context(Some(ctx.session_id.clone()), None)?;
 
-        Ok(output.map(apis::TaskOutput::from))
+        Ok(output?.map(apis::TaskOutput::from))
     }
 
     async fn on_session_leave(&mut self) -> Result<(), common::FlameError> {
@@ -133,30 +294,90 @@ impl Shim for WasmShim {
             session_id: self.session_context.clone().unwrap().session_id.clone(),
             common_data: None,
         };
-
-        let _ = self
-            .instance
-            .interface0
-            .call_on_session_leave(&mut self.store, &ssn_ctx)
-            .await
-            .map_err(|e| common::FlameError::Internal(e.to_string()))?
-            .map_err(|e| common::FlameError::Internal(e.to_string()))?;
+        self.session_context = None;
+        lock_ptr!(self.host)?.leave();
+
+        // The session is gone with the trapped instance, if any.
+        let mut instance = self.instance.lock().await;
+        if let Some(current) = instance.as_mut() {
+            set_deadline(&mut current.store, self.timeout);
+            let res = current
+                .flame
+                .interface0
+                .call_on_session_leave(&mut current.store, &ssn_ctx)
+                .await;
+            self.check_call(&mut instance, res)?;
+        }
+
+        self.log.set_context(None, None)?;
 
         Ok(())
     }
+
+    fn is_healthy(&self) -> bool {
+        self.work_dir.is_healthy()
+    }
 }
 
 struct ServerWasiView {
     table: Table,
     ctx: WasiCtx,
+    log: ExecutorLogPtr,
+    host: MutexPtr<HostState>,
+    limits: StoreLimits,
+    // The deadline of the current call of the component.
+    deadline: Option<Instant>,
+    // The progress of the running task is reported by the updater.
+    updater: Option<TaskUpdater>,
 }
 
 impl ServerWasiView {
-    fn new() -> Self {
-        let table = Table::new();
-        let ctx = WasiCtxBuilder::new().inherit_stdio().build();
-
-        Self { table, ctx }
+    fn new(
+        cmd: &str,
+        app: &apis::ApplicationContext,
+        work_dir: &WorkDir,
+        log: &ExecutorLogPtr,
+        host: &MutexPtr<HostState>,
+        memory: u64,
+    ) -> Result<Self, FlameError> {
+        let mut builder = WasiCtxBuilder::new();
+        builder
+            .arg(cmd)
+            .args(&app.arguments)
+            .envs(&app.environments.iter().collect::<Vec<_>>())
+            .preopened_dir(
+                open_dir(work_dir.path())?,
+                DirPerms::all(),
+                FilePerms::all(),
+                WORK_DIR_GUEST_PATH,
+            );
+
+        if let Some(package_dir) = &app.package_dir {
+            builder.preopened_dir(
+                open_dir(Path::new(package_dir))?,
+                DirPerms::READ,
+                FilePerms::READ,
+                package_dir,
+            );
+        }
+
+        if capture_output(app)? {
+            builder
+                .stdout(LogOutput::new(log, "stdout"))
+                .stderr(LogOutput::new(log, "stderr"));
+        } else {
+            builder.inherit_stdio();
+        }
+
+        Ok(Self {
+            table: Table::new(),
+            ctx: builder.build(),
+            log: log.clone(),
+            host: host.clone(),
+            limits: store_limits(memory),
+            deadline: None,
+            updater: None,
+        })
     }
 }
 
@@ -177,3 +398,453 @@ impl WasiView for ServerWasiView {
         &mut self.ctx
     }
 }
+
+/// The state of the host for the current session of the component.
+#[derive(Default)]
+struct HostState {
+    common_data: Option<apis::CommonData>,
+    scratch: HashMap<String, Vec<u8>>,
+    // The size of the keys and values in the scratch, which is limited by its share of
+    // the memory of the executor, as the scratch is in the executor manager.
+    scratch_size: u64,
+    scratch_limit: u64,
+}
+
+impl HostState {
+    fn new(scratch_limit: u64) -> Self {
+        Self {
+            scratch_limit,
+            ..Self::default()
+        }
+    }
+
+    fn enter(&mut self, ctx: &apis::SessionContext) {
+        self.common_data = ctx.common_data.clone();
+        self.clear_scratch();
+    }
+
+    fn leave(&mut self) {
+        self.common_data = None;
+        self.clear_scratch();
+    }
+
+    fn set(&mut self, key: String, value: Vec<u8>) -> Result<(), FlameError> {
+        let size = (key.len() + value.len()) as u64;
+        let old_size = self
+            .scratch
+            .get(&key)
+            .map(|v| (key.len() + v.len()) as u64)
+            .unwrap_or_default();
+        let scratch_size = self.scratch_size - old_size + size;
+        if self.scratch_limit > 0 && scratch_size > self.scratch_limit {
+            return Err(FlameError::Internal(format!(
+                "the scratch of the session used {scratch_size} bytes, exceeded the limit {} bytes",
+                self.scratch_limit
+            )));
+        }
+
+        self.scratch.insert(key, value);
+        self.scratch_size = scratch_size;
+
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &str) {
+        if let Some(value) = self.scratch.remove(key) {
+            self.scratch_size -= (key.len() + value.len()) as u64;
+        }
+    }
+
+    fn clear_scratch(&mut self) {
+        self.scratch.clear();
+        self.scratch_size = 0;
+    }
+}
+
+#[async_trait]
+impl host::Host for ServerWasiView {
+    async fn log(&mut self, level: host::LogLevel, message: String) -> wasmtime::Result<()> {
+        let level = match level {
+            host::LogLevel::Trace => "TRACE",
+            host::LogLevel::Debug => "DEBUG",
+            host::LogLevel::Info => "INFO",
+            host::LogLevel::Warn => "WARN",
+            host::LogLevel::Error => "ERROR",
+        };
+        self.log.append(LOG_STREAM, &format!("{level} {message}"))?;
+
+        Ok(())
+    }
+
+    async fn common_data(&mut self) -> wasmtime::Result<Option<Vec<u8>>> {
+        let host = lock_ptr!(self.host)?;
+        Ok(host.common_data.clone().map(apis::CommonData::into))
+    }
+
+    async fn report_progress(
+        &mut self,
+        percentage: f32,
+        message: Option<String>,
+    ) -> wasmtime::Result<()> {
+        let percentage = if percentage.is_nan() {
+            0.0
+        } else {
+            percentage.clamp(0.0, 100.0)
+        };
+        let line = match &message {
+            Some(message) => format!("{percentage:.1}% {message}"),
+            None => format!("{percentage:.1}%"),
+        };
+        self.log.append(PROGRESS_STREAM, &line)?;
+
+        if let Some(updater) = &self.updater {
+            let update = TaskUpdate {
+                progress: Some(apis::TaskProgress {
+                    percentage,
+                    message,
+                }),
+                partial_output: None,
+            };
+            // The task keeps running even if its progress can not be reported.
+            if let Err(e) = updater.send(update).await {
+                log::debug!("Failed to report the progress of task: {e}");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get(&mut self, key: String) -> wasmtime::Result<Option<Vec<u8>>> {
+        let host = lock_ptr!(self.host)?;
+        Ok(host.scratch.get(&key).cloned())
+    }
+
+    async fn set(&mut self, key: String, value: Vec<u8>) -> wasmtime::Result<()> {
+        lock_ptr!(self.host)?.set(key, value)?;
+
+        Ok(())
+    }
+
+    async fn delete(&mut self, key: String) -> wasmtime::Result<()> {
+        lock_ptr!(self.host)?.delete(&key);
+
+        Ok(())
+    }
+}
+
+/// The stdout/stderr of the component, which is captured into the log of the executor.
+struct LogOutput {
+    log: ExecutorLogPtr,
+    stream: &'static str,
+}
+
+impl LogOutput {
+    fn new(log: &ExecutorLogPtr, stream: &'static str) -> Self {
+        Self {
+            log: log.clone(),
+            stream,
+        }
+    }
+}
+
+impl StdoutStream for LogOutput {
+    fn stream(&self) -> Box<dyn HostOutputStream> {
+        let (writer, reader) = tokio::io::duplex(OUTPUT_BUFFER_SIZE);
+        self.log.capture(self.stream, reader);
+
+        Box::new(AsyncWriteStream::new(OUTPUT_BUFFER_SIZE, writer))
+    }
+
+    fn isatty(&self) -> bool {
+        false
+    }
+}
+
+fn session_context(ctx: &apis::SessionContext) -> service::SessionContext {
+    service::SessionContext {
+        session_id: ctx.session_id.clone(),
+        common_data: ctx.common_data.clone().map(apis::CommonData::into),
+    }
+}
+
+/// The limits of the store by the memory limit of the component; the growth of the memory
+/// fails by a trap instead of an error, which the component usually can not handle.
+fn store_limits(memory: u64) -> StoreLimits {
+    match memory {
+        0 => StoreLimits::default(),
+        memory => StoreLimitsBuilder::new()
+            .memory_size(usize::try_from(memory).unwrap_or(usize::MAX))
+            .trap_on_grow_failure(true)
+            .build(),
+    }
+}
+
+/// Start the timer of the next call of the component.
+fn set_deadline(store: &mut Store<ServerWasiView>, timeout: Option<Duration>) {
+    store.data_mut().deadline = timeout.map(|t| Instant::now() + t);
+    store.set_epoch_deadline(1);
+}
+
+/// The component reached the epoch deadline: trap it if it timed out; otherwise, yield
+/// to other tasks of the executor manager, and check it again in the next epoch.
+fn on_epoch_deadline(ctx: StoreContextMut<ServerWasiView>) -> anyhow::Result<UpdateDeadline> {
+    check_deadline(ctx.data().deadline)
+}
+
+fn check_deadline(deadline: Option<Instant>) -> anyhow::Result<UpdateDeadline> {
+    match deadline {
+        Some(deadline) if Instant::now() >= deadline => {
+            Err(anyhow!("the call of the component timed out"))
+        }
+        _ => Ok(UpdateDeadline::Yield(1)),
+    }
+}
+
+fn trap_error(executor_id: &str, e: anyhow::Error) -> FlameError {
+    let e = FlameError::Internal(format!("the component trapped: {e:#}"));
+    log::error!("The component of executor <{executor_id}> failed: {e}");
+
+    e
+}
+
+fn wasm_timeout(app: &apis::ApplicationContext) -> Result<Option<Duration>, FlameError> {
+    match app.environments.get(FLAME_WASM_TIMEOUT) {
+        Some(v) => v
+            .parse::<u64>()
+            .map(|secs| Some(Duration::from_secs(secs)))
+            .map_err(|e| {
+                FlameError::InvalidConfig(format!("invalid {FLAME_WASM_TIMEOUT} <{v}>: {e}"))
+            }),
+        None => Ok(None),
+    }
+}
+
+/// Split the memory of the executor into the limits of the component and the scratch,
+/// so they're not counted twice; 0 is unlimited.
+fn memory_budget(app: &apis::ApplicationContext, memory: u64) -> Result<(u64, u64), FlameError> {
+    let scratch_size = match app.environments.get(FLAME_WASM_SCRATCH_SIZE) {
+        Some(v) => v.parse::<u64>().map_err(|e| {
+            FlameError::InvalidConfig(format!("invalid {FLAME_WASM_SCRATCH_SIZE} <{v}>: {e}"))
+        })?,
+        None => memory / DEFAULT_SCRATCH_RATIO,
+    };
+
+    match memory {
+        0 => Ok((0, scratch_size)),
+        memory if scratch_size < memory => Ok((memory - scratch_size, scratch_size)),
+        memory => Err(FlameError::InvalidConfig(format!(
+            "{FLAME_WASM_SCRATCH_SIZE} <{scratch_size}> exceeds the memory <{memory}>"
+        ))),
+    }
+}
+
+fn capture_output(app: &apis::ApplicationContext) -> Result<bool, FlameError> {
+    match app.environments.get(FLAME_WASM_CAPTURE_OUTPUT) {
+        Some(v) => v.parse::<bool>().map_err(|e| {
+            FlameError::InvalidConfig(format!("invalid {FLAME_WASM_CAPTURE_OUTPUT} <{v}>: {e}"))
+        }),
+        None => Ok(false),
+    }
+}
+
+fn open_dir(path: &Path) -> Result<Dir, FlameError> {
+    Dir::open_ambient_dir(path, ambient_authority())
+        .map_err(|e| FlameError::Internal(format!("failed to open <{}>: {e}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    use wasmtime::{Instance, Module};
+
+    use super::*;
+
+    const LOOP_WAT: &str = r#"(module (func (export "run") (loop br 0)))"#;
+    const GROW_WAT: &str = r#"(module
+        (memory 1)
+        (func (export "grow") (param i32) (result i32) (memory.grow (local.get 0))))"#;
+    const PAGE_SIZE: u64 = 64 * 1024;
+
+    /// Increase the epoch of the engine until the returned flag is set.
+    fn start_ticker(engine: &Engine) -> Arc<AtomicBool> {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let (engine, flag) = (engine.clone(), stopped.clone());
+        thread::spawn(move || {
+            while !flag.load(Ordering::Relaxed) {
+                engine.increment_epoch();
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+
+        stopped
+    }
+
+    #[test]
+    fn test_call_timeout() -> Result<(), FlameError> {
+        let engine = wasm_cache::new_engine()?;
+        let stopped = start_ticker(&engine);
+
+        let res: anyhow::Result<()> = tokio_test::block_on(async {
+            let module = Module::new(&engine, LOOP_WAT)?;
+            let mut store = Store::new(&engine, Some(Instant::now() + Duration::from_millis(100)));
+            store.epoch_deadline_callback(|ctx| check_deadline(*ctx.data()));
+            store.set_epoch_deadline(1);
+
+            let instance = Instance::new_async(&mut store, &module, &[]).await?;
+            let run = instance.get_typed_func::<(), ()>(&mut store, "run")?;
+            run.call_async(&mut store, ()).await
+        });
+        stopped.store(true, Ordering::Relaxed);
+
+        let err = res.expect_err("the endless call should time out");
+        assert!(format!("{err:#}").contains("timed out"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_timeout() {
+        assert!(matches!(check_deadline(None), Ok(UpdateDeadline::Yield(1))));
+        let future = Instant::now() + Duration::from_secs(60);
+        assert!(matches!(
+            check_deadline(Some(future)),
+            Ok(UpdateDeadline::Yield(1))
+        ));
+        assert!(check_deadline(Some(Instant::now())).is_err());
+    }
+
+    #[test]
+    fn test_memory_trap() -> Result<(), FlameError> {
+        let engine = wasm_cache::new_engine()?;
+
+        let res: anyhow::Result<(i32, anyhow::Result<i32>)> = tokio_test::block_on(async {
+            let module = Module::new(&engine, GROW_WAT)?;
+            let mut store = Store::new(&engine, store_limits(2 * PAGE_SIZE));
+            store.limiter(|limits| limits);
+            // No timeout, but the engine is interrupted by the epoch.
+            store.set_epoch_deadline(u64::MAX);
+
+            let instance = Instance::new_async(&mut store, &module, &[]).await?;
+            let grow = instance.get_typed_func::<i32, i32>(&mut store, "grow")?;
+            // The memory grows within the limit, and traps beyond it.
+            let within = grow.call_async(&mut store, 1).await?;
+            let beyond = grow.call_async(&mut store, 1).await;
+
+            Ok((within, beyond))
+        });
+
+        let (within, beyond) = res.map_err(|e| FlameError::Internal(e.to_string()))?;
+        assert_eq!(within, 1);
+        assert!(beyond.is_err());
+
+        Ok(())
+    }
+
+    fn new_app() -> apis::ApplicationContext {
+        apis::ApplicationContext {
+            name: "test".to_string(),
+            image: None,
+            command: None,
+            arguments: vec![],
+            environments: HashMap::new(),
+            working_directory: None,
+            sandbox: None,
+            url: None,
+            checksum: None,
+            concurrency: 1,
+            package_dir: None,
+            shim: apis::Shim::Wasm,
+        }
+    }
+
+    #[test]
+    fn test_wasm_timeout() -> Result<(), FlameError> {
+        let mut app = new_app();
+        assert_eq!(wasm_timeout(&app)?, None);
+
+        app.environments
+            .insert(FLAME_WASM_TIMEOUT.to_string(), "3".to_string());
+        assert_eq!(wasm_timeout(&app)?, Some(Duration::from_secs(3)));
+
+        app.environments
+            .insert(FLAME_WASM_TIMEOUT.to_string(), "3s".to_string());
+        assert!(wasm_timeout(&app).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_budget() -> Result<(), FlameError> {
+        let mut app = new_app();
+        // A quarter of the memory is for the scratch by default.
+        assert_eq!(memory_budget(&app, 4096)?, (3072, 1024));
+        assert_eq!(memory_budget(&app, 0)?, (0, 0));
+
+        app.environments
+            .insert(FLAME_WASM_SCRATCH_SIZE.to_string(), "100".to_string());
+        assert_eq!(memory_budget(&app, 4096)?, (3996, 100));
+        assert_eq!(memory_budget(&app, 0)?, (0, 100));
+
+        // The scratch can not take all the memory.
+        app.environments
+            .insert(FLAME_WASM_SCRATCH_SIZE.to_string(), "4096".to_string());
+        assert!(memory_budget(&app, 4096).is_err());
+
+        app.environments
+            .insert(FLAME_WASM_SCRATCH_SIZE.to_string(), "1k".to_string());
+        assert!(memory_budget(&app, 4096).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scratch_size() -> Result<(), FlameError> {
+        let mut host = HostState::new(10);
+
+        host.set("a".to_string(), b"1234".to_vec())?;
+        assert_eq!(host.scratch_size, 5);
+
+        // The size of the replaced value is released.
+        host.set("a".to_string(), b"12".to_vec())?;
+        assert_eq!(host.scratch_size, 3);
+
+        host.set("b".to_string(), b"123".to_vec())?;
+        assert_eq!(host.scratch_size, 7);
+
+        host.delete("a");
+        assert_eq!(host.scratch_size, 4);
+        // Deleting a missing key is a no-op.
+        host.delete("a");
+        assert_eq!(host.scratch_size, 4);
+
+        host.leave();
+        assert_eq!(host.scratch_size, 0);
+        assert!(host.scratch.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scratch_limit() -> Result<(), FlameError> {
+        let mut host = HostState::new(10);
+
+        host.set("a".to_string(), b"12345678".to_vec())?;
+        // The value exceeding the limit is rejected, and the scratch is kept.
+        assert!(host.set("b".to_string(), b"12".to_vec()).is_err());
+        assert_eq!(host.scratch_size, 9);
+        assert!(!host.scratch.contains_key("b"));
+
+        // Replacing the value within the limit is allowed.
+        host.set("a".to_string(), b"123456789".to_vec())?;
+        assert_eq!(host.scratch_size, 10);
+
+        // No limit if 0.
+        let mut host = HostState::new(0);
+        host.set("a".to_string(), vec![0u8; 1024])?;
+        assert_eq!(host.scratch_size, 1025);
+
+        Ok(())
+    }
+}
@@ -11,18 +11,30 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use std::collections::HashSet;
+
 use async_trait::async_trait;
+use tokio::sync::mpsc;
+use tokio::task::{JoinHandle, JoinSet};
 
-use crate::client::BackendClient;
-use crate::executor::Executor;
+use crate::backoff::Backoff;
+use crate::client::{Assignment, BackendClient};
+use crate::executor::{Executor, TaskResult};
+use crate::shims::{ShimPtr, TaskUpdate};
 use crate::states::State;
-use common::apis::ExecutorState;
+use common::apis::{ExecutorState, TaskContext, TaskOutput};
 use common::{trace::TraceFn, trace_fn, FlameError};
 
+// The updates of the task buffered in the executor before reported to the server.
+const TASK_UPDATE_BUFFER_SIZE: usize = 128;
+
+type InvokeResult = (TaskContext, Result<Option<TaskOutput>, FlameError>, bool);
+
 #[derive(Clone)]
 pub struct BoundState {
     pub client: BackendClient,
     pub executor: Executor,
+    pub healthy: bool,
 }
 
 #[async_trait]
@@ -30,37 +42,362 @@ impl State for BoundState {
     async fn execute(&mut self) -> Result<Executor, FlameError> {
         trace_fn!("BoundState::execute");
 
-        let task = self.client.launch_task(&self.executor.clone()).await?;
-        self.executor.task = task.clone();
-
-        match task {
-            Some(task_ctx) => {
-                let shim_ptr = &mut self.executor.shim.clone().ok_or(FlameError::InvalidState(
-                    "no shim in bound state".to_string(),
-                ))?;
-                {
-                    let mut shim = shim_ptr.lock().await;
-                    let output = shim.on_task_invoke(&task_ctx).await?;
-                    if let Some(task_ctx) = &mut self.executor.task {
-                        task_ctx.output = output;
-                    }
-                };
+        let concurrency = self
+            .executor
+            .session
+            .as_ref()
+            .map(|ssn| ssn.application.concurrency.max(1) as usize)
+            .unwrap_or(1);
 
-                self.client.complete_task(&self.executor.clone()).await?;
+        // Run the tasks pushed by the server up to the concurrency of the application, the
+        // others are prefetched; and report the completed tasks while the others are running,
+        // so the shim does not wait on the network between tasks. The executor leaves the
+        // session after it's unbound by the server and all its tasks completed.
+        let client = self.client.clone();
+        let mut running = JoinSet::new();
+        let mut running_ids = HashSet::new();
+        let mut report: Option<JoinHandle<Vec<String>>> = None;
+        let mut unbinding = false;
 
-                let (ssn_id, task_id) = {
-                    let task = &self.executor.task.clone().unwrap();
-                    (task.session_id.clone(), task.task_id.clone())
+        loop {
+            while running.len() < concurrency {
+                let Some(task_ctx) = self.executor.tasks.pop_front() else {
+                    break;
                 };
-                log::debug!("Complete task <{ssn_id}/{task_id}>")
+                running_ids.insert(task_ctx.task_id.clone());
+                running.spawn(Self::invoke(
+                    self.client.clone(),
+                    self.executor.id.clone(),
+                    self.executor.shim.clone(),
+                    task_ctx,
+                ));
+            }
+            self.publish_held(&running_ids);
+
+            // The results are kept until reported, so they're not launched again.
+            if report.is_none() && !self.executor.results.is_empty() {
+                report = Some(tokio::spawn(Self::report(
+                    self.client.clone(),
+                    self.executor.clone(),
+                )));
+            }
+
+            if unbinding && running.is_empty() {
+                break;
             }
-            None => {
-                self.executor.state = ExecutorState::Unbinding;
+
+            tokio::select! {
+                Some(res) = running.join_next(), if !running.is_empty() => {
+                    let (task_ctx, res) = match res {
+                        Ok((task_ctx, res @ Ok(_), _)) | Ok((task_ctx, res, true)) => (task_ctx, res),
+                        // The shim is unhealthy, so the executor is unregistered; and the
+                        // tasks will be retried by the server.
+                        Ok((_, Err(e), false)) => {
+                            return Err(self.abort(&mut running, report, e).await);
+                        }
+                        Err(e) => {
+                            let e = FlameError::Internal(e.to_string());
+                            return Err(self.abort(&mut running, report, e).await);
+                        }
+                    };
+                    running_ids.remove(&task_ctx.task_id);
+
+                    let (output, error) = match res {
+                        Ok(output) => (output, None),
+                        // The task failed in the service, report it to the server.
+                        Err(e) => (None, Some(e.to_string())),
+                    };
+
+                    self.executor.results.push(TaskResult {
+                        task_id: task_ctx.task_id.clone(),
+                        output,
+                        error,
+                    });
+
+                    log::debug!(
+                        "Complete task <{}/{}>",
+                        task_ctx.session_id,
+                        task_ctx.task_id
+                    );
+                }
+                res = async { report.as_mut().unwrap().await }, if report.is_some() => {
+                    report = None;
+                    if let Ok(reported) = res {
+                        self.executor.results.retain(|r| !reported.contains(&r.task_id));
+                    }
+                }
+                assignment = client.receive(&self.executor) => match assignment? {
+                    Assignment::Launch(tasks) => self.merge(tasks, &running_ids),
+                    Assignment::Unbind => {
+                        log::debug!("Executor <{}> is unbinding.", self.executor.id);
+                        unbinding = true;
+                    }
+                    _ => log::debug!(
+                        "Executor <{}> is bound, skip the work of other states.",
+                        self.executor.id
+                    ),
+                },
+            }
+        }
+
+        if let Some(report) = report.take() {
+            if let Ok(reported) = report.await {
+                self.executor
+                    .results
+                    .retain(|r| !reported.contains(&r.task_id));
             }
         }
+        self.complete_tasks().await?;
+        self.publish_held(&running_ids);
 
-        self.executor.task = None;
+        self.executor.state = ExecutorState::Unbinding;
 
         Ok(self.executor.clone())
     }
+
+    fn is_healthy(&self) -> bool {
+        self.healthy
+    }
+}
+
+impl BoundState {
+    /// Invoke the task in the shim, and return the result with the health of the shim.
+    async fn invoke(
+        client: BackendClient,
+        executor_id: String,
+        shim_ptr: Option<ShimPtr>,
+        task_ctx: TaskContext,
+    ) -> InvokeResult {
+        let Some(shim_ptr) = shim_ptr else {
+            let err = FlameError::InvalidState("no shim in bound state".to_string());
+            return (task_ctx, Err(err), false);
+        };
+
+        // Relay the updates of the task to the server, until the task is done.
+        let (updater, mut updates) = mpsc::channel::<TaskUpdate>(TASK_UPDATE_BUFFER_SIZE);
+        let reporter = {
+            let (mut client, task_ctx) = (client, task_ctx.clone());
+            tokio::spawn(async move {
+                while let Some(update) = updates.recv().await {
+                    if let Err(e) = client.report_task(&executor_id, &task_ctx, update).await {
+                        log::warn!(
+                            "Failed to report task <{}/{}>: {e}",
+                            task_ctx.session_id,
+                            task_ctx.task_id
+                        );
+                    }
+                }
+            })
+        };
+
+        let (res, healthy) = {
+            let shim = shim_ptr.read().await;
+            let res = shim.on_task_invoke(&task_ctx, &updater).await;
+            (res, shim.is_healthy())
+        };
+
+        // Report all updates before completing the task, as they're rejected afterwards.
+        drop(updater);
+        if let Err(e) = reporter.await {
+            log::warn!("Failed to wait for the reporter of task: {e}");
+        }
+
+        (task_ctx, res, healthy)
+    }
+
+    /// Report the completed tasks of the executor, and retry with backoff if the server is
+    /// unavailable, e.g. restarting; the network errors should not break the running tasks.
+    /// Return the reported tasks, or the ones rejected by the server which are dropped.
+    async fn report(mut client: BackendClient, executor: Executor) -> Vec<String> {
+        let ids = executor.results.iter().map(|r| r.task_id.clone()).collect();
+        let mut backoff = Backoff::default();
+        loop {
+            match client.complete_task(&executor, &executor.results).await {
+                Ok(()) => return ids,
+                Err(FlameError::Network(e)) => {
+                    let delay = backoff.next_delay();
+                    log::warn!(
+                        "Failed to complete tasks of executor <{}>, retry in {delay:?}: {e}",
+                        executor.id
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => {
+                    log::error!(
+                        "Failed to complete tasks of executor <{}>, drop them: {e}",
+                        executor.id
+                    );
+                    return ids;
+                }
+            }
+        }
+    }
+
+    /// Stop the running tasks as the shim is unhealthy, and report the completed ones.
+    async fn abort(
+        &mut self,
+        running: &mut JoinSet<InvokeResult>,
+        report: Option<JoinHandle<Vec<String>>>,
+        err: FlameError,
+    ) -> FlameError {
+        self.healthy = false;
+        running.abort_all();
+        self.publish_held(&HashSet::new());
+        if let Some(report) = report {
+            report.abort();
+        }
+        if let Err(e) = self.complete_tasks().await {
+            log::warn!("Failed to complete tasks before unregistered: {e}");
+        }
+
+        err
+    }
+
+    /// Share the running, prefetched and unreported tasks with the manager, which reports
+    /// them to the server; so they're reclaimed by the server if it was restarted.
+    fn publish_held(&self, running_ids: &HashSet<String>) {
+        let held = running_ids
+            .iter()
+            .cloned()
+            .chain(self.executor.tasks.iter().map(|t| t.task_id.clone()))
+            .chain(self.executor.results.iter().map(|r| r.task_id.clone()))
+            .collect();
+        match self.executor.held.lock() {
+            Ok(mut tasks) => *tasks = held,
+            Err(_) => log::error!(
+                "Failed to lock the tasks of executor <{}>.",
+                self.executor.id
+            ),
+        }
+    }
+
+    /// Merge the tasks pushed by the server into the executor.
+    fn merge(&mut self, tasks: Vec<TaskContext>, running_ids: &HashSet<String>) {
+        for task in tasks {
+            // The server pushes the launched tasks again on a new stream, skip them.
+            let launched = running_ids.contains(&task.task_id)
+                || self
+                    .executor
+                    .tasks
+                    .iter()
+                    .any(|t| t.task_id == task.task_id)
+                || self
+                    .executor
+                    .results
+                    .iter()
+                    .any(|r| r.task_id == task.task_id);
+            if !launched {
+                self.executor.tasks.push_back(task);
+            }
+        }
+        self.publish_held(running_ids);
+    }
+
+    /// Report the results of the completed tasks to the server.
+    async fn complete_tasks(&mut self) -> Result<(), FlameError> {
+        if self.executor.results.is_empty() {
+            return Ok(());
+        }
+
+        self.client
+            .complete_task(&self.executor.clone(), &self.executor.results)
+            .await?;
+        self.executor.results.clear();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::client::tests::FakeBackend;
+    use common::ctx::FlameContext;
+
+    fn new_task(id: &str) -> TaskContext {
+        TaskContext {
+            task_id: id.to_string(),
+            session_id: "1".to_string(),
+            input: None,
+            output: None,
+        }
+    }
+
+    fn new_result(id: &str) -> TaskResult {
+        TaskResult {
+            task_id: id.to_string(),
+            output: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_merge() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            let ctx = FlameContext {
+                endpoint: "http://127.0.0.1:8080".to_string(),
+                ..FlameContext::default()
+            };
+            let mut state = BoundState {
+                client: BackendClient::new(&ctx)?,
+                executor: Executor {
+                    tasks: VecDeque::from([new_task("2")]),
+                    results: vec![new_result("1")],
+                    ..Executor::for_test("exe-1")
+                },
+                healthy: true,
+            };
+
+            // The server pushes the launched tasks again with the new one, e.g. by a new
+            // stream: "1" is completed, "2" is prefetched and "3" is running; only "4" is new.
+            let running_ids = HashSet::from(["3".to_string()]);
+            let tasks = ["1", "2", "3", "4"].map(new_task).to_vec();
+            state.merge(tasks, &running_ids);
+
+            let task_ids: Vec<&str> = state
+                .executor
+                .tasks
+                .iter()
+                .map(|t| t.task_id.as_str())
+                .collect();
+            assert_eq!(task_ids, vec!["2", "4"]);
+            let result_ids: Vec<&str> = state
+                .executor
+                .results
+                .iter()
+                .map(|r| r.task_id.as_str())
+                .collect();
+            assert_eq!(result_ids, vec!["1"]);
+
+            // All the tasks held by the state are reported to the server.
+            let mut held = state.executor.task_ids();
+            held.sort();
+            assert_eq!(held, vec!["1", "2", "3", "4"]);
+
+            Ok(())
+        })
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_shim() -> Result<(), FlameError> {
+        let (backend, client) = FakeBackend::serve().await;
+        // The shim of the executor is lost, e.g. crashed.
+        let mut state = BoundState {
+            client,
+            executor: Executor {
+                tasks: VecDeque::from([new_task("1")]),
+                ..Executor::for_test("exe-1")
+            },
+            healthy: true,
+        };
+
+        assert!(state.execute().await.is_err());
+        assert!(!state.is_healthy());
+        assert!(state.executor.task_ids().is_empty());
+        assert!(backend.requests().is_empty());
+
+        Ok(())
+    }
 }
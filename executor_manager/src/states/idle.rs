@@ -13,7 +13,7 @@ limitations under the License.
 
 use async_trait::async_trait;
 
-use crate::client::BackendClient;
+use crate::client::{Assignment, BackendClient};
 use crate::executor::Executor;
 use crate::shims;
 use crate::states::State;
@@ -24,6 +24,7 @@ use common::{trace::TraceFn, trace_fn, FlameError};
 pub struct IdleState {
     pub client: BackendClient,
     pub executor: Executor,
+    pub healthy: bool,
 }
 
 #[async_trait]
@@ -31,7 +32,22 @@ impl State for IdleState {
     async fn execute(&mut self) -> Result<Executor, FlameError> {
         trace_fn!("IdleState::execute");
 
-        let ssn = self.client.bind_executor(&self.executor.clone()).await?;
+        // Wait for the session to bind, which is pushed by the server.
+        let ssn = loop {
+            match self.client.receive(&self.executor).await? {
+                Assignment::Bind(ssn) => break *ssn,
+                Assignment::Release => {
+                    log::debug!("Executor <{}> is releasing.", &self.executor.id);
+                    self.executor.state = ExecutorState::Releasing;
+                    return Ok(self.executor.clone());
+                }
+                // The work of the previous session, e.g. pushed again by a new stream.
+                _ => log::debug!(
+                    "Executor <{}> is idle, skip the work of previous session.",
+                    &self.executor.id
+                ),
+            }
+        };
 
         log::debug!(
             "Try to bind Executor <{}> to <{}>.",
@@ -39,13 +55,16 @@ impl State for IdleState {
             &ssn.session_id.clone()
         );
 
-        let shim_ptr = shims::new(&ssn.application).await?;
+        // The shim failed to enter the session, e.g. its service did not start.
+        self.healthy = false;
+        let shim_ptr = shims::new(&self.executor, &ssn.application).await?;
         {
             // TODO(k82cn): if on_session_enter failed, add retry limits.
-            let mut shim = shim_ptr.lock().await;
+            let mut shim = shim_ptr.write().await;
             shim.on_session_enter(&ssn).await?;
             log::debug!("Shim on_session_enter completed.");
         };
+        self.healthy = true;
 
         self.client
             .bind_executor_completed(&self.executor.clone())
@@ -64,4 +83,8 @@ impl State for IdleState {
 
         Ok(self.executor.clone())
     }
+
+    fn is_healthy(&self) -> bool {
+        self.healthy
+    }
 }
@@ -21,6 +21,7 @@ use common::FlameError;
 
 mod bound;
 mod idle;
+mod releasing;
 mod unbinding;
 mod unknown;
 mod void;
@@ -36,14 +37,21 @@ pub fn from(client: BackendClient, e: Executor) -> Box<dyn State> {
         ExecutorState::Idle => Box::new(idle::IdleState {
             client,
             executor: e,
+            healthy: true,
         }),
         ExecutorState::Bound => Box::new(bound::BoundState {
             client,
             executor: e,
+            healthy: true,
         }),
         ExecutorState::Unbinding => Box::new(unbinding::UnbindingState {
             client,
             executor: e,
+            healthy: true,
+        }),
+        ExecutorState::Releasing => Box::new(releasing::ReleasingState {
+            client,
+            executor: e,
         }),
         _ => Box::new(unknown::UnknownState { executor: e }),
     }
@@ -52,4 +60,11 @@ pub fn from(client: BackendClient, e: Executor) -> Box<dyn State> {
 #[async_trait]
 pub trait State: Send + Sync {
     async fn execute(&mut self) -> Result<Executor, FlameError>;
+
+    /// Whether the executor is still healthy after the state failed: the state is retried
+    /// if so, e.g. the server was unavailable; otherwise its shim failed, and the executor
+    /// is unregistered so the server retries its tasks.
+    fn is_healthy(&self) -> bool {
+        true
+    }
 }
@@ -0,0 +1,120 @@
+/*
+Copyright 2023 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use async_trait::async_trait;
+
+use crate::client::BackendClient;
+use crate::executor::Executor;
+use crate::states::State;
+use common::apis::ExecutorState;
+use common::{trace::TraceFn, trace_fn, FlameError};
+
+#[derive(Clone)]
+pub struct ReleasingState {
+    pub client: BackendClient,
+    pub executor: Executor,
+}
+
+#[async_trait]
+impl State for ReleasingState {
+    async fn execute(&mut self) -> Result<Executor, FlameError> {
+        trace_fn!("ReleasingState::execute");
+
+        // Drop the shim to tear down the service process, if any.
+        self.executor.tasks.clear();
+        self.executor.results.clear();
+        self.executor.session = None;
+        self.executor.shim = None;
+
+        self.client
+            .release_executor_completed(&self.executor.clone())
+            .await?;
+
+        // The executor's resources on the node are freed now.
+        self.executor.state = ExecutorState::Released;
+
+        Ok(self.executor.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::client::tests::FakeBackend;
+    use crate::executor::TaskResult;
+    use ::rpc::flame::node_request::Request as NodeRequestKind;
+    use common::apis::TaskContext;
+    use common::ctx::FlameContext;
+
+    fn new_executor() -> Executor {
+        let mut executor = Executor::for_test("exe-1");
+        executor.state = ExecutorState::Releasing;
+        executor.tasks = VecDeque::from([TaskContext {
+            task_id: "1".to_string(),
+            session_id: "1".to_string(),
+            input: None,
+            output: None,
+        }]);
+        executor.results = vec![TaskResult {
+            task_id: "2".to_string(),
+            output: None,
+            error: None,
+        }];
+
+        executor
+    }
+
+    #[tokio::test]
+    async fn test_release_executor() -> Result<(), FlameError> {
+        let (backend, client) = FakeBackend::serve().await;
+        let mut state = ReleasingState {
+            client,
+            executor: new_executor(),
+        };
+
+        let executor = state.execute().await?;
+        assert_eq!(executor.state, ExecutorState::Released);
+        assert!(executor.tasks.is_empty());
+        assert!(executor.results.is_empty());
+        assert!(executor.session.is_none());
+
+        let requests = backend.requests();
+        assert!(matches!(
+            &requests[..],
+            [NodeRequestKind::ReleaseExecutorCompleted(req)] if req.executor_id == "exe-1"
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_release_executor_failed() -> Result<(), FlameError> {
+        // No server listens on the endpoint.
+        let ctx = FlameContext {
+            endpoint: "http://127.0.0.1:1".to_string(),
+            ..FlameContext::default()
+        };
+        let mut state = ReleasingState {
+            client: BackendClient::new(&ctx)?,
+            executor: new_executor(),
+        };
+
+        // The executor is kept releasing, so it's released again.
+        assert!(state.execute().await.is_err());
+        assert_eq!(state.executor.state, ExecutorState::Releasing);
+
+        Ok(())
+    }
+}
@@ -23,6 +23,7 @@ use common::{trace::TraceFn, trace_fn, FlameError};
 pub struct UnbindingState {
     pub client: BackendClient,
     pub executor: Executor,
+    pub healthy: bool,
 }
 
 #[async_trait]
@@ -30,21 +31,24 @@ impl State for UnbindingState {
     async fn execute(&mut self) -> Result<Executor, FlameError> {
         trace_fn!("UnbindingState::execute");
 
-        self.client.unbind_executor(&self.executor.clone()).await?;
+        // The shim failed to leave the session, e.g. its service crashed.
+        self.healthy = false;
         let shim_ptr = &mut self.executor.shim.clone().ok_or(FlameError::InvalidState(
             "no shim in bound state".to_string(),
         ))?;
 
         {
-            let mut shim = shim_ptr.lock().await;
+            let mut shim = shim_ptr.write().await;
             shim.on_session_leave().await?;
         }
+        self.healthy = true;
 
         self.client
             .unbind_executor_completed(&self.executor.clone())
             .await?;
 
-        self.executor.task = None;
+        self.executor.tasks.clear();
+        self.executor.results.clear();
         self.executor.session = None;
         self.executor.shim = None;
 
@@ -53,4 +57,8 @@ impl State for UnbindingState {
 
         Ok(self.executor.clone())
     }
+
+    fn is_healthy(&self) -> bool {
+        self.healthy
+    }
 }
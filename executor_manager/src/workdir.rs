@@ -0,0 +1,392 @@
+/*
+Copyright 2025 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::fs;
+use std::future::Future;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use common::apis::ApplicationContext;
+use common::ctx::FlameContext;
+use common::FlameError;
+
+static WORK_DIR_CONFIG: OnceLock<WorkDirConfig> = OnceLock::new();
+
+// The disk usage of the work directory is checked in this interval while the tasks run.
+const QUOTA_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+struct WorkDirConfig {
+    root: PathBuf,
+    quota: u64,
+    keep_on_failure: bool,
+}
+
+/// Load the configuration of the work directories, and create their root.
+pub fn init(ctx: &FlameContext) -> Result<(), FlameError> {
+    let root = PathBuf::from(&ctx.work_root);
+    fs::create_dir_all(&root).map_err(|e| io_error(&root, e))?;
+
+    let _ = WORK_DIR_CONFIG.set(WorkDirConfig {
+        root,
+        quota: ctx.work_quota,
+        keep_on_failure: ctx.keep_work_dir_on_failure,
+    });
+
+    Ok(())
+}
+
+/// The work directory of an executor, where its service runs. It's derived from the
+/// `working_directory` of the application under the root of the node, i.e.
+/// `<work root>/<working directory>/<executor id>`, so the executors never share
+/// one; it's removed when dropped, e.g. the executor was unbound or released.
+///
+/// If the root is configured with a disk quota, the usage of the directory is checked
+/// periodically in background, and the running tasks fail once it exceeds the quota;
+/// the directory is unhealthy after that, so the executor is released. If the executor failed and `keep_work_dir_on_failure` is configured, the directory
+/// is kept for debugging.
+pub struct WorkDir {
+    executor_id: String,
+    root: PathBuf,
+    path: PathBuf,
+    quota: u64,
+    keep_on_failure: bool,
+    // Whether the executor failed, which is set by its concurrent tasks.
+    failed: AtomicBool,
+    // Whether a task failed by the exceeded quota.
+    over_quota: AtomicBool,
+    // The error of the exceeded quota, which is updated by the monitor of the usage.
+    exceeded: watch::Receiver<Option<String>>,
+    monitor: Option<JoinHandle<()>>,
+}
+
+impl WorkDir {
+    pub fn new(executor_id: &str, app: &ApplicationContext) -> Result<Self, FlameError> {
+        let config = WORK_DIR_CONFIG
+            .get()
+            .ok_or(FlameError::Uninitialized("work directory".to_string()))?;
+
+        let mut path = config.root.clone();
+        if let Some(working_directory) = &app.working_directory {
+            for component in Path::new(working_directory).components() {
+                match component {
+                    Component::Normal(c) => path.push(c),
+                    Component::RootDir | Component::CurDir => {}
+                    _ => {
+                        return Err(FlameError::InvalidConfig(format!(
+                            "invalid working directory <{working_directory}> of application <{}>",
+                            app.name
+                        )))
+                    }
+                }
+            }
+        }
+        path.push(executor_id);
+
+        // Clean up the directory left by the previous executor with the same id, if any.
+        remove_dir(&path)?;
+        fs::create_dir_all(&path).map_err(|e| io_error(&path, e))?;
+
+        let (tx, exceeded) = watch::channel(None);
+        let monitor =
+            (config.quota > 0).then(|| tokio::spawn(monitor(path.clone(), config.quota, tx)));
+
+        Ok(Self {
+            executor_id: executor_id.to_string(),
+            root: config.root.clone(),
+            path,
+            quota: config.quota,
+            keep_on_failure: config.keep_on_failure,
+            failed: AtomicBool::new(false),
+            over_quota: AtomicBool::new(false),
+            exceeded,
+            monitor,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Run the future, e.g. a task, until it's done or the disk quota is exceeded.
+    pub async fn guard<F: Future>(&self, fut: F) -> Result<F::Output, FlameError> {
+        tokio::select! {
+            output = fut => Ok(output),
+            err = self.exceeded() => Err(err),
+        }
+    }
+
+    /// Wait until the usage of the directory exceeds the disk quota, and mark the
+    /// directory unhealthy; it never returns if there is no quota.
+    pub async fn exceeded(&self) -> FlameError {
+        let mut exceeded = self.exceeded.clone();
+        let err = exceeded
+            .wait_for(Option::is_some)
+            .await
+            .ok()
+            .and_then(|e| e.clone());
+        match err {
+            Some(err) => {
+                self.over_quota.store(true, Ordering::Relaxed);
+                FlameError::Internal(err)
+            }
+            // No monitor of the usage, e.g. no quota.
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Whether no task failed by the exceeded quota; the service may be broken by the
+    /// stopped task or the full disk, so the executor is released otherwise.
+    pub fn is_healthy(&self) -> bool {
+        !self.over_quota.load(Ordering::Relaxed)
+    }
+
+    /// Check the result of the executor, e.g. a task, and the disk quota of the
+    /// directory after it; the executor is failed if any of them is an error.
+    pub async fn check<T>(&self, res: Result<T, FlameError>) -> Result<T, FlameError> {
+        let res = match res {
+            Ok(v) => self.check_quota().await.map(|_| v),
+            Err(e) => Err(e),
+        };
+        if res.is_err() {
+            self.set_failed();
+        }
+
+        res
+    }
+
+    /// Mark the executor as failed, e.g. its service crashed.
+    pub fn set_failed(&self) {
+        self.failed.store(true, Ordering::Relaxed);
+    }
+
+    async fn check_quota(&self) -> Result<(), FlameError> {
+        if self.quota == 0 {
+            return Ok(());
+        }
+
+        match exceeded_quota(self.path.clone(), self.quota).await {
+            Some(err) => {
+                self.over_quota.store(true, Ordering::Relaxed);
+                Err(FlameError::Internal(err))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        if let Some(monitor) = self.monitor.take() {
+            monitor.abort();
+        }
+
+        if self.failed.load(Ordering::Relaxed) && self.keep_on_failure {
+            log::info!(
+                "Keep the work directory <{}> of the failed executor <{}>.",
+                self.path.display(),
+                self.executor_id
+            );
+            return;
+        }
+
+        if let Err(e) = remove_dir(&self.path) {
+            log::warn!(
+                "Failed to remove the work directory of executor <{}>: {e}",
+                self.executor_id
+            );
+        }
+    }
+}
+
+/// Check the disk usage of the directory periodically; the error is cleared after the
+/// usage is within the quota again, e.g. the files were removed.
+async fn monitor(path: PathBuf, quota: u64, exceeded: watch::Sender<Option<String>>) {
+    loop {
+        tokio::time::sleep(QUOTA_CHECK_INTERVAL).await;
+        let err = exceeded_quota(path.clone(), quota).await;
+        exceeded.send_if_modified(|current| {
+            if current.is_some() == err.is_some() {
+                return false;
+            }
+            if let Some(err) = &err {
+                log::warn!("{err}");
+            }
+            *current = err;
+            true
+        });
+    }
+}
+
+/// Return the error if the disk usage of the directory exceeded the quota; the
+/// directory is walked by the blocking thread.
+async fn exceeded_quota(path: PathBuf, quota: u64) -> Option<String> {
+    let usage = {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || disk_usage(&path))
+            .await
+            .unwrap_or_default()
+    };
+
+    (usage > quota).then(|| {
+        format!(
+            "the work directory <{}> used {usage} bytes, exceeded the quota {quota} bytes",
+            path.display()
+        )
+    })
+}
+
+/// The total size of the files in the directory; the symlinks are not followed.
+fn disk_usage(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => disk_usage(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn remove_dir(path: &Path) -> Result<(), FlameError> {
+    match fs::remove_dir_all(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(io_error(path, e)),
+        _ => Ok(()),
+    }
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> FlameError {
+    FlameError::Internal(format!("failed to access <{}>: {e}", path.display()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use common::apis::Shim;
+
+    const TEST_QUOTA: u64 = 1024;
+
+    /// Init the work directories, which are shared by the tests of the executor manager,
+    /// and return an application in the directory.
+    pub(crate) fn new_app(working_directory: Option<&str>) -> ApplicationContext {
+        init(&FlameContext {
+            work_root: "/tmp/flame_test_work".to_string(),
+            work_quota: TEST_QUOTA,
+            ..FlameContext::default()
+        })
+        .unwrap();
+
+        ApplicationContext {
+            name: "test".to_string(),
+            image: None,
+            command: None,
+            arguments: vec![],
+            environments: HashMap::new(),
+            working_directory: working_directory.map(String::from),
+            sandbox: None,
+            url: None,
+            checksum: None,
+            concurrency: 1,
+            package_dir: None,
+            shim: Shim::Shell,
+        }
+    }
+
+    #[test]
+    fn test_work_dir_path() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            let app = new_app(Some("/jobs/./demo"));
+            let work_dir = WorkDir::new("exe-path", &app)?;
+            assert_eq!(
+                work_dir.path(),
+                Path::new("/tmp/flame_test_work/jobs/demo/exe-path")
+            );
+            assert!(work_dir.path().is_dir());
+
+            // The directory is removed when dropped.
+            let path = work_dir.path().to_path_buf();
+            drop(work_dir);
+            assert!(!path.exists());
+
+            // The working directory can not escape the root.
+            let app = new_app(Some("../etc"));
+            assert!(WorkDir::new("exe-escape", &app).is_err());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_check_quota() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            let app = new_app(None);
+            let work_dir = WorkDir::new("exe-check", &app)?;
+            assert_eq!(work_dir.check(Ok(1)).await?, 1);
+
+            fs::write(
+                work_dir.path().join("data"),
+                vec![0u8; TEST_QUOTA as usize + 1],
+            )
+            .map_err(|e| FlameError::Internal(e.to_string()))?;
+            assert!(work_dir.check(Ok(1)).await.is_err());
+            assert!(work_dir.failed.load(Ordering::Relaxed));
+            assert!(!work_dir.is_healthy());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_guard_quota() -> Result<(), FlameError> {
+        tokio_test::block_on(async {
+            tokio::time::pause();
+
+            let app = new_app(None);
+            let work_dir = WorkDir::new("exe-guard", &app)?;
+            assert_eq!(work_dir.guard(async { 1 }).await?, 1);
+
+            // The running task is stopped once the usage exceeded the quota.
+            fs::write(
+                work_dir.path().join("data"),
+                vec![0u8; TEST_QUOTA as usize + 1],
+            )
+            .map_err(|e| FlameError::Internal(e.to_string()))?;
+            let res = work_dir.guard(std::future::pending::<()>()).await;
+            assert!(res.is_err());
+            assert!(!work_dir.is_healthy());
+
+            // The error is cleared after the files were removed, so the later tasks run.
+            fs::remove_file(work_dir.path().join("data"))
+                .map_err(|e| FlameError::Internal(e.to_string()))?;
+            let mut exceeded = work_dir.exceeded.clone();
+            exceeded
+                .wait_for(Option::is_none)
+                .await
+                .map_err(|e| FlameError::Internal(e.to_string()))?;
+            assert_eq!(work_dir.guard(async { 2 }).await?, 2);
+
+            Ok(())
+        })
+    }
+}
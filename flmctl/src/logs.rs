@@ -0,0 +1,43 @@
+/*
+Copyright 2025 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::error::Error;
+
+use futures::StreamExt;
+
+use flame_rs::apis::FlameContext;
+use flame_rs::client;
+
+pub async fn run(
+    ctx: &FlameContext,
+    session: &str,
+    task: &Option<String>,
+    follow: bool,
+) -> Result<(), Box<dyn Error>> {
+    let conn = client::connect(&ctx.endpoint).await?;
+    let mut logs = conn.read_logs(session, task.as_deref(), follow).await?;
+
+    while let Some(entry) = logs.next().await {
+        let entry = entry?;
+        println!(
+            "{} {} {} {}: {}",
+            entry.timestamp.format("%Y-%m-%dT%H:%M:%S%.3f"),
+            entry.executor_id,
+            entry.task_id.unwrap_or("-".to_string()),
+            entry.stream,
+            entry.message
+        );
+    }
+
+    Ok(())
+}
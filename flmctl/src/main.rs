@@ -19,7 +19,9 @@ use flame_rs::apis::FlameContext;
 mod create;
 mod helper;
 mod list;
+mod logs;
 mod migrate;
+mod node;
 mod register;
 mod unregister;
 mod view;
@@ -94,6 +96,23 @@ enum Commands {
         #[arg(short, long)]
         name: String,
     },
+    /// Print the logs of the session or task
+    Logs {
+        /// The id of session
+        #[arg(short, long)]
+        session: String,
+        /// The id of task
+        #[arg(short, long)]
+        task: Option<String>,
+        /// Keep streaming the new logs
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Manage the nodes of Flame
+    Node {
+        #[command(subcommand)]
+        command: node::NodeCommands,
+    },
 }
 
 #[tokio::main]
@@ -119,6 +138,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
         Some(Commands::Migrate { url, sql }) => migrate::run(&ctx, url, sql).await?,
         Some(Commands::Register { file }) => register::run(&ctx, file).await?,
         Some(Commands::Unregister { name }) => unregister::run(&ctx, name).await?,
+        Some(Commands::Logs {
+            session,
+            task,
+            follow,
+        }) => logs::run(&ctx, session, task, *follow).await?,
+        Some(Commands::Node { command }) => node::run(&ctx, command).await?,
         _ => helper::run().await?,
     };
 
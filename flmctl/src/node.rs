@@ -0,0 +1,64 @@
+/*
+Copyright 2023 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::error::Error;
+
+use chrono::Duration;
+use clap::Subcommand;
+
+use flame_rs as flame;
+use flame_rs::apis::FlameContext;
+
+#[derive(Subcommand)]
+pub enum NodeCommands {
+    /// Mark the node as unschedulable
+    Cordon {
+        /// The name of node
+        name: String,
+    },
+    /// Mark the node as schedulable
+    Uncordon {
+        /// The name of node
+        name: String,
+    },
+    /// Release the executors of the node after their running tasks completed
+    Drain {
+        /// The name of node
+        name: String,
+        /// The timeout (in seconds) to wait for the running tasks
+        #[arg(short, long)]
+        timeout: Option<i64>,
+    },
+}
+
+pub async fn run(ctx: &FlameContext, cmd: &NodeCommands) -> Result<(), Box<dyn Error>> {
+    let conn = flame::client::connect(&ctx.endpoint).await?;
+
+    match cmd {
+        NodeCommands::Cordon { name } => {
+            conn.cordon_node(name).await?;
+            println!("Node <{name}> was cordoned.");
+        }
+        NodeCommands::Uncordon { name } => {
+            conn.uncordon_node(name).await?;
+            println!("Node <{name}> was uncordoned.");
+        }
+        NodeCommands::Drain { name, timeout } => {
+            conn.drain_node(name, timeout.map(Duration::seconds))
+                .await?;
+            println!("Node <{name}> is draining.");
+        }
+    }
+
+    Ok(())
+}
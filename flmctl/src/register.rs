@@ -18,7 +18,7 @@ use flame_rs as flame;
 use flame_rs::apis::Shim;
 use flame_rs::{
     apis::{FlameContext, FlameError},
-    client::{ApplicationAttributes, ApplicationSchema},
+    client::{ApplicationAttributes, ApplicationSandbox, ApplicationSchema},
 };
 
 use serde_derive::{Deserialize, Serialize};
@@ -35,6 +35,13 @@ struct SchemaYaml {
     pub common_data: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SandboxYaml {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub allow_network: Option<bool>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct SpecYaml {
     pub shim: Option<String>,
@@ -48,6 +55,10 @@ struct SpecYaml {
     pub max_instances: Option<i32>,
     pub delay_release: Option<i64>,
     pub schema: Option<SchemaYaml>,
+    pub sandbox: Option<SandboxYaml>,
+    pub url: Option<String>,
+    pub checksum: Option<String>,
+    pub concurrency: Option<i32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,6 +99,10 @@ impl TryFrom<&ApplicationYaml> for ApplicationAttributes {
             .as_str()
         {
             "grpc" => Ok(Shim::Grpc),
+            "http" => Ok(Shim::Http),
+            "stdio" => Ok(Shim::Stdio),
+            "shell" => Ok(Shim::Shell),
+            "wasm" => Ok(Shim::Wasm),
             _ => Err(FlameError::InvalidConfig("unsupported shim".to_string())),
         }?;
 
@@ -103,6 +118,10 @@ impl TryFrom<&ApplicationYaml> for ApplicationAttributes {
             max_instances: yaml.spec.max_instances,
             delay_release: yaml.spec.delay_release.map(Duration::seconds),
             schema: yaml.spec.schema.clone().map(ApplicationSchema::from),
+            sandbox: yaml.spec.sandbox.clone().map(ApplicationSandbox::from),
+            url: yaml.spec.url.clone(),
+            checksum: yaml.spec.checksum.clone(),
+            concurrency: yaml.spec.concurrency,
         })
     }
 }
@@ -116,3 +135,13 @@ impl From<SchemaYaml> for ApplicationSchema {
         }
     }
 }
+
+impl From<SandboxYaml> for ApplicationSandbox {
+    fn from(sandbox: SandboxYaml) -> Self {
+        Self {
+            uid: sandbox.uid,
+            gid: sandbox.gid,
+            allow_network: sandbox.allow_network.unwrap_or(false),
+        }
+    }
+}
@@ -18,6 +18,9 @@ use serde_json::Value;
 use flame_rs::apis::{FlameContext, FlameError};
 use flame_rs::client;
 
+// The sandboxed service runs as nobody by default.
+const DEFAULT_SANDBOX_ID: u32 = 65534;
+
 pub async fn run(
     ctx: &FlameContext,
     session: &Option<String>,
@@ -58,6 +61,16 @@ async fn view_application(
     for label in application.attributes.labels {
         println!("\t{label}");
     }
+    println!(
+        "{:<15}{}",
+        "URL:",
+        application.attributes.url.unwrap_or_default()
+    );
+    println!(
+        "{:<15}{}",
+        "Checksum:",
+        application.attributes.checksum.unwrap_or_default()
+    );
     println!(
         "{:<15}{}",
         "Command:",
@@ -86,6 +99,18 @@ async fn view_application(
         "Delay Release:",
         application.attributes.delay_release.unwrap_or_default()
     );
+    println!(
+        "{:<15}{}",
+        "Concurrency:",
+        application.attributes.concurrency.unwrap_or(1)
+    );
+
+    if let Some(sandbox) = application.attributes.sandbox {
+        println!("{:<15}", "Sandbox:");
+        println!("  Uid: {}", sandbox.uid.unwrap_or(DEFAULT_SANDBOX_ID));
+        println!("  Gid: {}", sandbox.gid.unwrap_or(DEFAULT_SANDBOX_ID));
+        println!("  Allow Network: {}", sandbox.allow_network);
+    }
 
     println!("{:<15}", "Schema:");
 
@@ -14,3 +14,7 @@ limitations under the License.
 pub mod flame {
     tonic::include_proto!("flame");
 }
+
+pub mod health {
+    tonic::include_proto!("grpc.health.v1");
+}
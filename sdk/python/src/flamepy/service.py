@@ -14,6 +14,7 @@ limitations under the License.
 import asyncio
 import os
 import grpc
+from grpc_health.v1 import health, health_pb2, health_pb2_grpc
 from abc import ABC, abstractmethod
 from typing import Optional, Dict, Any, Union
 from dataclasses import dataclass
@@ -26,6 +27,9 @@ from .shim_pb2 import TaskOutput as TaskOutputProto
 
 logger = logging.getLogger(__name__)
 
+FLAME_SERVICE_SOCKET = "FLAME_SERVICE_SOCKET"
+DEFAULT_SERVICE_SOCKET = "/tmp/flame/shim/fsi.sock"
+
 @dataclass
 class ApplicationContext:
     """Context for an application."""
@@ -186,9 +190,17 @@ class GrpcShimServer:
             # Add servicer to server
             shim_servicer = GrpcShimServicer(self._service)
             add_GrpcShimServicer_to_server(shim_servicer, self._server)
+
+            # Add health servicer, which is probed by the executor manager
+            health_servicer = health.aio.HealthServicer()
+            health_pb2_grpc.add_HealthServicer_to_server(health_servicer, self._server)
+            await health_servicer.set("", health_pb2.HealthCheckResponse.SERVING)
             
-            # Listen on Unix socket
-            socket_path = f"/tmp/flame/shim/fsi.sock"
+            # Listen on Unix socket, each executor has its own socket.
+            socket_path = os.getenv(FLAME_SERVICE_SOCKET, DEFAULT_SERVICE_SOCKET)
+            # Remove the stale socket, e.g. the previous service crashed.
+            if os.path.exists(socket_path):
+                os.remove(socket_path)
             self._server.add_insecure_port(f"unix://{socket_path}")
             
             # Start server
@@ -60,6 +60,7 @@ class Shim(IntEnum):
     WASM = 2
     SHELL = 3
     GRPC = 4
+    HTTP = 5
 
 
 class FlameErrorCode(IntEnum):
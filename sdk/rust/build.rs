@@ -19,12 +19,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "flame.ExecutorState",
             "#[allow(clippy::enum_variant_names)]",
         )
+        // The messages of executors and nodes are only used by the backend.
+        .type_attribute("flame.ExecutorSpec", "#[allow(dead_code)]")
+        .type_attribute("flame.Executor", "#[allow(dead_code)]")
+        .type_attribute("flame.NodeSpec", "#[allow(dead_code)]")
+        .type_attribute("flame.Node", "#[allow(dead_code)]")
         .protoc_arg("--experimental_allow_proto3_optional")
         .compile_protos(
             &[
                 "protos/types.proto",
                 "protos/frontend.proto",
                 "protos/shim.proto",
+                "protos/health.proto",
             ],
             &["protos"],
         )?;
@@ -16,6 +16,11 @@ pub(crate) mod flame {
 }
 use flame as rpc;
 
+#[allow(dead_code)]
+pub(crate) mod health {
+    tonic::include_proto!("grpc.health.v1");
+}
+
 use std::fmt::{Display, Formatter};
 use std::fs;
 use std::path::Path;
@@ -97,6 +102,7 @@ pub enum Shim {
     Wasm = 2,
     Shell = 3,
     Grpc = 4,
+    Http = 5,
 }
 
 impl From<Status> for FlameError {
@@ -113,6 +119,7 @@ impl From<rpc::Shim> for Shim {
             rpc::Shim::Wasm => Shim::Wasm,
             rpc::Shim::Shell => Shim::Shell,
             rpc::Shim::Grpc => Shim::Grpc,
+            rpc::Shim::Http => Shim::Http,
         }
     }
 }
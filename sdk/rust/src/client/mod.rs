@@ -12,10 +12,11 @@ limitations under the License.
 */
 
 use std::collections::HashMap;
+use std::pin::Pin;
 use std::sync::{Arc, Mutex};
 
 use chrono::{DateTime, Duration, TimeZone, Utc};
-use futures::TryFutureExt;
+use futures::{Stream, TryFutureExt};
 use stdng::{logs::TraceFn, trace_fn};
 use tokio_stream::StreamExt;
 use tonic::transport::Channel;
@@ -24,9 +25,10 @@ use tonic::Request;
 
 use self::rpc::frontend_client::FrontendClient as FlameFrontendClient;
 use self::rpc::{
-    ApplicationSpec, CloseSessionRequest, CreateSessionRequest, CreateTaskRequest, Environment,
-    GetApplicationRequest, GetTaskRequest, ListApplicationRequest, ListSessionRequest,
-    RegisterApplicationRequest, SessionSpec, TaskSpec, WatchTaskRequest,
+    ApplicationSpec, CloseSessionRequest, CordonNodeRequest, CreateSessionRequest,
+    CreateTaskRequest, DrainNodeRequest, Environment, GetApplicationRequest, GetTaskRequest,
+    ListApplicationRequest, ListSessionRequest, ReadLogsRequest, RegisterApplicationRequest,
+    SessionSpec, TaskSpec, UncordonNodeRequest, WatchSessionRequest, WatchTaskRequest,
 };
 use crate::apis::flame as rpc;
 use crate::apis::Shim;
@@ -69,6 +71,14 @@ pub struct ApplicationSchema {
     pub common_data: Option<String>,
 }
 
+/// The sandbox of the application's service, see `ApplicationSandbox` in types.proto.
+#[derive(Clone)]
+pub struct ApplicationSandbox {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub allow_network: bool,
+}
+
 #[derive(Clone)]
 pub struct ApplicationAttributes {
     pub shim: Shim,
@@ -83,6 +93,10 @@ pub struct ApplicationAttributes {
     pub max_instances: Option<i32>,
     pub delay_release: Option<Duration>,
     pub schema: Option<ApplicationSchema>,
+    pub sandbox: Option<ApplicationSandbox>,
+    pub url: Option<String>,
+    pub checksum: Option<String>,
+    pub concurrency: Option<i32>,
 }
 
 #[derive(Clone)]
@@ -120,8 +134,34 @@ pub struct Task {
 
     pub input: Option<TaskInput>,
     pub output: Option<TaskOutput>,
+
+    /// The latest progress reported by the running task.
+    pub progress: Option<TaskProgress>,
+    /// The chunk of the output emitted by the running task, only set when watching.
+    pub partial_output: Option<TaskOutput>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TaskProgress {
+    /// The percentage of the task which was done, in [0, 100].
+    pub percentage: f32,
+    pub message: Option<String>,
+}
+
+/// The log captured from the service of an executor.
+#[derive(Clone)]
+pub struct LogEntry {
+    pub executor_id: String,
+    pub ssn_id: SessionID,
+    pub task_id: Option<TaskID>,
+    /// The stream of the service which wrote the log, e.g. stdout or stderr.
+    pub stream: String,
+    pub timestamp: DateTime<Utc>,
+    pub message: String,
 }
 
+pub type LogStream = Pin<Box<dyn Stream<Item = Result<LogEntry, FlameError>> + Send>>;
+
 pub type TaskInformerPtr = Arc<Mutex<dyn TaskInformer>>;
 
 pub trait TaskInformer: Send + Sync + 'static {
@@ -214,6 +254,67 @@ impl Connection {
             .await?;
         Ok(Application::from(&app.into_inner()))
     }
+
+    pub async fn cordon_node(&self, name: &str) -> Result<(), FlameError> {
+        let mut client = FlameClient::new(self.channel.clone());
+        client
+            .cordon_node(CordonNodeRequest {
+                node_name: name.to_string(),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn uncordon_node(&self, name: &str) -> Result<(), FlameError> {
+        let mut client = FlameClient::new(self.channel.clone());
+        client
+            .uncordon_node(UncordonNodeRequest {
+                node_name: name.to_string(),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn drain_node(
+        &self,
+        name: &str,
+        timeout: Option<Duration>,
+    ) -> Result<(), FlameError> {
+        let mut client = FlameClient::new(self.channel.clone());
+        client
+            .drain_node(DrainNodeRequest {
+                node_name: name.to_string(),
+                timeout: timeout.map(|t| t.num_seconds()),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Read the logs of the session, or of the task if specified; keep streaming
+    /// the new logs if `follow` is true.
+    pub async fn read_logs(
+        &self,
+        ssn_id: &str,
+        task_id: Option<&str>,
+        follow: bool,
+    ) -> Result<LogStream, FlameError> {
+        let mut client = FlameClient::new(self.channel.clone());
+        let stream = client
+            .read_logs(ReadLogsRequest {
+                session_id: ssn_id.to_string(),
+                task_id: task_id.map(|id| id.to_string()),
+                follow,
+            })
+            .await?
+            .into_inner();
+
+        Ok(Box::pin(stream.map(|entry| {
+            entry.map(|e| LogEntry::from(&e)).map_err(FlameError::from)
+        })))
+    }
 }
 
 impl Session {
@@ -298,6 +399,34 @@ impl Session {
         Ok(())
     }
 
+    /// Watch the updates of all tasks in the session, e.g. their states, progress and
+    /// partial outputs, until the session is closed and all its tasks are completed.
+    pub async fn watch_session(&self, informer_ptr: TaskInformerPtr) -> Result<(), FlameError> {
+        trace_fn!("Session::watch_session");
+        let mut client = self
+            .client
+            .clone()
+            .ok_or(FlameError::Internal("no flame client".to_string()))?;
+
+        let watch_ssn_req = WatchSessionRequest {
+            session_id: self.id.clone(),
+        };
+        let mut task_stream = client.watch_session(watch_ssn_req).await?.into_inner();
+        while let Some(task) = task_stream.next().await {
+            match task {
+                Ok(t) => {
+                    let mut informer = lock_ptr!(informer_ptr)?;
+                    informer.on_update(Task::from(&t));
+                }
+                Err(e) => {
+                    let mut informer = lock_ptr!(informer_ptr)?;
+                    informer.on_error(FlameError::from(e.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub async fn close(&self) -> Result<(), FlameError> {
         trace_fn!("Session::close");
         let mut client = self
@@ -319,13 +448,31 @@ impl From<&rpc::Task> for Task {
     fn from(task: &rpc::Task) -> Self {
         let metadata = task.metadata.clone().unwrap();
         let spec = task.spec.clone().unwrap();
-        let status = task.status.unwrap();
+        let status = task.status.clone().unwrap();
         Task {
             id: metadata.id,
             ssn_id: spec.session_id.clone(),
             input: spec.input.map(TaskInput::from),
             output: spec.output.map(TaskOutput::from),
             state: TaskState::try_from(status.state).unwrap_or(TaskState::default()),
+            progress: status.progress.map(|p| TaskProgress {
+                percentage: p.percentage,
+                message: p.message,
+            }),
+            partial_output: task.partial_output.clone().map(TaskOutput::from),
+        }
+    }
+}
+
+impl From<&rpc::LogEntry> for LogEntry {
+    fn from(entry: &rpc::LogEntry) -> Self {
+        Self {
+            executor_id: entry.executor_id.clone(),
+            ssn_id: entry.session_id.clone(),
+            task_id: entry.task_id.clone(),
+            stream: entry.stream.clone(),
+            timestamp: DateTime::from_timestamp_millis(entry.timestamp).unwrap_or_default(),
+            message: entry.message.clone(),
         }
     }
 }
@@ -393,6 +540,10 @@ impl From<ApplicationAttributes> for ApplicationSpec {
             max_instances: app.max_instances,
             delay_release: app.delay_release.map(|s| s.num_seconds()),
             schema: app.schema.clone().map(rpc::ApplicationSchema::from),
+            sandbox: app.sandbox.clone().map(rpc::ApplicationSandbox::from),
+            url: app.url.clone(),
+            checksum: app.checksum.clone(),
+            concurrency: app.concurrency,
         }
     }
 }
@@ -416,6 +567,30 @@ impl From<ApplicationSpec> for ApplicationAttributes {
             max_instances: app.max_instances,
             delay_release: app.delay_release.map(Duration::seconds),
             schema: app.schema.clone().map(ApplicationSchema::from),
+            sandbox: app.sandbox.map(ApplicationSandbox::from),
+            url: app.url.clone(),
+            checksum: app.checksum.clone(),
+            concurrency: app.concurrency,
+        }
+    }
+}
+
+impl From<ApplicationSandbox> for rpc::ApplicationSandbox {
+    fn from(sandbox: ApplicationSandbox) -> Self {
+        Self {
+            uid: sandbox.uid,
+            gid: sandbox.gid,
+            allow_network: sandbox.allow_network,
+        }
+    }
+}
+
+impl From<rpc::ApplicationSandbox> for ApplicationSandbox {
+    fn from(sandbox: rpc::ApplicationSandbox) -> Self {
+        Self {
+            uid: sandbox.uid,
+            gid: sandbox.gid,
+            allow_network: sandbox.allow_network,
         }
     }
 }
@@ -11,17 +11,29 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use std::env;
+use std::fs;
+use std::pin::Pin;
 use std::sync::Arc;
 
+use futures::Stream;
 use tokio::net::UnixListener;
-use tokio_stream::wrappers::UnixListenerStream;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, UnixListenerStream};
 use tonic::{transport::Server, Request, Response, Status};
 
+use self::health::health_server::{Health, HealthServer};
+use self::health::{health_check_response::ServingStatus, HealthCheckRequest, HealthCheckResponse};
 use self::rpc::grpc_shim_server::{GrpcShim, GrpcShimServer};
 use crate::apis::flame as rpc;
+use crate::apis::health;
 
 use crate::apis::{CommonData, FlameError, TaskInput, TaskOutput};
 
+const FLAME_SERVICE_SOCKET: &str = "FLAME_SERVICE_SOCKET";
+const DEFAULT_SERVICE_SOCKET: &str = "/tmp/flame/shim/fsi.sock";
+const TASK_UPDATE_BUFFER_SIZE: usize = 128;
+
 pub struct ApplicationContext {
     pub name: String,
     pub image: Option<String>,
@@ -38,6 +50,51 @@ pub struct TaskContext {
     pub task_id: String,
     pub session_id: String,
     pub input: Option<TaskInput>,
+    // The updates of the task are streamed to the executor manager, if it invoked the
+    // task by streaming.
+    updates: Option<mpsc::Sender<Result<rpc::TaskUpdate, Status>>>,
+}
+
+impl TaskContext {
+    /// Report the progress of the task, the percentage is in [0, 100].
+    pub async fn report_progress(
+        &self,
+        percentage: f32,
+        message: Option<String>,
+    ) -> Result<(), FlameError> {
+        self.send(rpc::TaskUpdate {
+            progress: Some(rpc::TaskProgress {
+                percentage,
+                message,
+            }),
+            partial_output: None,
+            output: None,
+        })
+        .await
+    }
+
+    /// Emit a chunk of the output before the task completes, e.g. the tokens generated
+    /// so far; the chunks are relayed to the clients watching the task.
+    pub async fn emit_output(&self, data: TaskOutput) -> Result<(), FlameError> {
+        self.send(rpc::TaskUpdate {
+            progress: None,
+            partial_output: Some(data.into()),
+            output: None,
+        })
+        .await
+    }
+
+    async fn send(&self, update: rpc::TaskUpdate) -> Result<(), FlameError> {
+        // The updates are dropped if the task was not invoked by streaming.
+        let Some(updates) = &self.updates else {
+            return Ok(());
+        };
+
+        updates
+            .send(Ok(update))
+            .await
+            .map_err(|e| FlameError::Network(format!("failed to send task update: {e}")))
+    }
 }
 
 #[tonic::async_trait]
@@ -49,12 +106,16 @@ pub trait FlameService: Send + Sync + 'static {
 
 pub type FlameServicePtr = Arc<dyn FlameService>;
 
+type TaskUpdateStream = Pin<Box<dyn Stream<Item = Result<rpc::TaskUpdate, Status>> + Send>>;
+
 struct ShimService {
     service: FlameServicePtr,
 }
 
 #[tonic::async_trait]
 impl GrpcShim for ShimService {
+    type OnTaskInvokeStreamStream = TaskUpdateStream;
+
     async fn on_session_enter(
         &self,
         req: Request<rpc::SessionContext>,
@@ -85,6 +146,40 @@ impl GrpcShim for ShimService {
         }))
     }
 
+    async fn on_task_invoke_stream(
+        &self,
+        req: Request<rpc::TaskContext>,
+    ) -> Result<Response<Self::OnTaskInvokeStreamStream>, Status> {
+        log::debug!("ShimService::on_task_invoke_stream");
+        let (tx, rx) = mpsc::channel(TASK_UPDATE_BUFFER_SIZE);
+
+        let mut ctx = TaskContext::from(req.into_inner());
+        ctx.updates = Some(tx.clone());
+
+        // The stream is closed after the output, as all senders are dropped.
+        let service = self.service.clone();
+        tokio::spawn(async move {
+            let update = service
+                .on_task_invoke(ctx)
+                .await
+                .map(|data| rpc::TaskUpdate {
+                    progress: None,
+                    partial_output: None,
+                    output: Some(rpc::TaskOutput {
+                        data: data.map(|d| d.into()),
+                    }),
+                })
+                .map_err(Status::from);
+            if tx.send(update).await.is_err() {
+                log::debug!("The executor manager stopped watching the task.");
+            }
+        });
+
+        Ok(Response::new(
+            Box::pin(ReceiverStream::new(rx)) as Self::OnTaskInvokeStreamStream
+        ))
+    }
+
     async fn on_session_leave(
         &self,
         _: Request<rpc::EmptyRequest>,
@@ -99,16 +194,39 @@ impl GrpcShim for ShimService {
     }
 }
 
+/// The health service is probed by the executor manager to check the service is serving.
+struct HealthService {}
+
+#[tonic::async_trait]
+impl Health for HealthService {
+    async fn check(
+        &self,
+        _: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        Ok(Response::new(HealthCheckResponse {
+            status: ServingStatus::Serving.into(),
+        }))
+    }
+}
+
 pub async fn run(service: impl FlameService) -> Result<(), Box<dyn std::error::Error>> {
     let shim_service = ShimService {
         service: Arc::new(service),
     };
 
-    let uds = UnixListener::bind("/tmp/flame/shim/fsi.sock")?;
+    // Each executor has its own socket, which is passed by the executor manager.
+    let socket = env::var(FLAME_SERVICE_SOCKET).unwrap_or(DEFAULT_SERVICE_SOCKET.to_string());
+    // Remove the stale socket, e.g. the previous service crashed.
+    if fs::exists(&socket)? {
+        fs::remove_file(&socket)?;
+    }
+
+    let uds = UnixListener::bind(&socket)?;
     let uds_stream = UnixListenerStream::new(uds);
 
     Server::builder()
         .add_service(GrpcShimServer::new(shim_service))
+        .add_service(HealthServer::new(HealthService {}))
         .serve_with_incoming(uds_stream)
         .await?;
 
@@ -147,6 +265,7 @@ impl From<rpc::TaskContext> for TaskContext {
             task_id: ctx.task_id.clone(),
             session_id: ctx.session_id.clone(),
             input: ctx.input.map(|data| data.into()),
+            updates: None,
         }
     }
 }
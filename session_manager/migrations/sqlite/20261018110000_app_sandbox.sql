@@ -0,0 +1 @@
+ALTER TABLE applications ADD COLUMN sandbox TEXT;
@@ -0,0 +1 @@
+ALTER TABLE applications ADD COLUMN checksum TEXT;
@@ -0,0 +1 @@
+ALTER TABLE applications ADD COLUMN concurrency INTEGER NOT NULL DEFAULT 1;
@@ -11,78 +11,252 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use std::pin::Pin;
+
 use async_trait::async_trait;
-use chrono::Utc;
-use tonic::{Request, Response, Status};
+use chrono::{Duration, Utc};
+use futures::Stream;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
 
 use self::rpc::backend_server::Backend;
+use self::rpc::node_request::Request as NodeRequestKind;
+use self::rpc::node_response::Response as NodeResponseKind;
 use self::rpc::{
-    BindExecutorCompletedRequest, BindExecutorRequest, BindExecutorResponse, CompleteTaskRequest,
-    LaunchTaskRequest, LaunchTaskResponse, RegisterExecutorRequest, RegisterNodeRequest,
-    ReleaseNodeRequest, SyncNodeRequest, SyncNodeResponse, UnbindExecutorCompletedRequest,
-    UnbindExecutorRequest, UnregisterExecutorRequest,
+    CompleteTaskRequest, NodeRequest, NodeResponse, RegisterExecutorRequest, ReportTaskRequest,
+    SyncNodeRequest, SyncNodeResponse,
 };
 use ::rpc::flame as rpc;
 
 use crate::apiserver::Flame;
-use crate::model::{
-    Executor, ExecutorInfo, ExecutorPtr, NodeInfo, NodeInfoPtr, SessionInfo, SessionInfoPtr,
-    SnapShot, SnapShotPtr,
+use crate::controller::Assigner;
+use crate::model::{Executor, TaskEvent};
+use common::apis::{
+    self, ExecutorState, Node, TaskGID, TaskOutput, TaskProgress, TaskState, DEFAULT_DRAIN_TIMEOUT,
 };
-use common::apis::{Application, ExecutorState, Node, Session, TaskOutput};
 use common::{trace::TraceFn, trace_fn, FlameError};
 
+// The responses buffered in the node stream, e.g. the tasks launched to the executors of the node.
+const NODE_STREAM_BUFFER_SIZE: usize = 128;
+// The node is kept alive while its stream is connected, which is refreshed in this interval;
+// it's shorter than the grace period of the node.
+const HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+type NodeSender = mpsc::Sender<Result<NodeResponse, Status>>;
+
 #[async_trait]
 impl Backend for Flame {
-    async fn register_node(
+    type ConnectNodeStream = Pin<Box<dyn Stream<Item = Result<NodeResponse, Status>> + Send>>;
+
+    async fn connect_node(
         &self,
-        req: Request<RegisterNodeRequest>,
-    ) -> Result<Response<rpc::Result>, Status> {
-        trace_fn!("Backend::register_node");
-        let req = req.into_inner();
-        let node = Node::from(
-            req.node
-                .ok_or(FlameError::InvalidConfig("node is required".to_string()))?,
-        );
-        self.controller.register_node(&node).await?;
-        Ok(Response::new(rpc::Result::default()))
+        req: Request<Streaming<NodeRequest>>,
+    ) -> Result<Response<Self::ConnectNodeStream>, Status> {
+        trace_fn!("Backend::connect_node");
+        let inbound = req.into_inner();
+        let (tx, rx) = mpsc::channel(NODE_STREAM_BUFFER_SIZE);
+
+        let flame = Flame {
+            controller: self.controller.clone(),
+        };
+        tokio::spawn(async move {
+            if let Err(e) = flame.serve_node(inbound, tx).await {
+                log::debug!("The node stream is closed: {e}");
+            }
+        });
+
+        Ok(Response::new(
+            Box::pin(ReceiverStream::new(rx)) as Self::ConnectNodeStream
+        ))
+    }
+}
+
+impl Flame {
+    /// Serve the stream of the node: handle the requests of the node in order, and push the
+    /// work assigned to its executors after the executors or tasks were changed, e.g. the
+    /// executor was bound to a session or the task was created.
+    async fn serve_node(
+        &self,
+        mut inbound: Streaming<NodeRequest>,
+        tx: NodeSender,
+    ) -> Result<(), FlameError> {
+        let mut assigner: Option<Assigner> = None;
+        let mut executors = self.controller.watch_executors();
+        let mut tasks = self.controller.watch_tasks();
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+
+        loop {
+            let delay = assigner
+                .as_ref()
+                .and_then(Assigner::deadline)
+                .map(|d| (d - Utc::now()).to_std().unwrap_or_default());
+
+            tokio::select! {
+                req = inbound.message() => {
+                    let req = req.map_err(|e| FlameError::Network(e.to_string()))?;
+                    let Some(req) = req else {
+                        return Ok(());
+                    };
+                    if let (None, Some(name)) = (&assigner, node_name(&req)) {
+                        assigner = Some(Assigner::new(name));
+                    }
+                    let resp = self.handle_node_request(req).await;
+                    send(&tx, resp).await?;
+                }
+                res = executors.changed() => {
+                    res.map_err(|e| FlameError::Internal(e.to_string()))?;
+                }
+                event = tasks.recv() => match event {
+                    // The progress of the running tasks does not change the work.
+                    Ok(TaskEvent::Reported(..)) => continue,
+                    Ok(TaskEvent::StateChanged(_)) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                _ = tokio::time::sleep(delay.unwrap_or_default()), if delay.is_some() => {}
+                _ = heartbeat.tick() => {
+                    if let Some(assigner) = &assigner {
+                        self.controller.touch_node(assigner.node_name())?;
+                    }
+                    continue;
+                }
+            }
+
+            let Some(assigner) = assigner.as_mut() else {
+                continue;
+            };
+            let assignments = match self.controller.assign(assigner).await {
+                Ok(assignments) => assignments,
+                Err(e) => {
+                    log::error!(
+                        "Failed to assign work to node <{}>: {e}",
+                        assigner.node_name()
+                    );
+                    continue;
+                }
+            };
+            for assignment in assignments {
+                let resp = NodeResponse {
+                    id: 0,
+                    response: Some(assignment.into()),
+                };
+                send(&tx, resp).await?;
+            }
+        }
     }
-    async fn sync_node(
+
+    /// Handle the request of the node stream, and return its response with the same id.
+    async fn handle_node_request(&self, req: NodeRequest) -> NodeResponse {
+        let response = match req.request {
+            Some(request) => self
+                .dispatch_node_request(request)
+                .await
+                .unwrap_or_else(|e| {
+                    let status = Status::from(e);
+                    NodeResponseKind::Result(rpc::Result {
+                        return_code: -1,
+                        message: Some(format!("{}: {}", status.code(), status.message())),
+                    })
+                }),
+            None => NodeResponseKind::Result(rpc::Result {
+                return_code: -1,
+                message: Some("empty node request".to_string()),
+            }),
+        };
+
+        NodeResponse {
+            id: req.id,
+            response: Some(response),
+        }
+    }
+
+    async fn dispatch_node_request(
         &self,
-        req: Request<SyncNodeRequest>,
-    ) -> Result<Response<SyncNodeResponse>, Status> {
-        trace_fn!("Backend::sync_node");
-        let req = req.into_inner();
+        request: NodeRequestKind,
+    ) -> Result<NodeResponseKind, FlameError> {
+        let ok = NodeResponseKind::Result(rpc::Result::default());
+
+        match request {
+            NodeRequestKind::RegisterNode(req) => {
+                trace_fn!("Backend::register_node");
+                let node = Node::from(
+                    req.node
+                        .ok_or(FlameError::InvalidConfig("node is required".to_string()))?,
+                );
+                self.controller.register_node(&node).await?;
+            }
+            NodeRequestKind::SyncNode(req) => {
+                trace_fn!("Backend::sync_node");
+                return self.sync_node(req).await.map(NodeResponseKind::SyncNode);
+            }
+            NodeRequestKind::ReleaseNode(req) => {
+                trace_fn!("Backend::release_node");
+                self.controller.release_node(&req.node_name).await?;
+            }
+            NodeRequestKind::DrainNode(req) => {
+                trace_fn!("Backend::drain_node");
+                let timeout = req
+                    .timeout
+                    .map(Duration::seconds)
+                    .unwrap_or(DEFAULT_DRAIN_TIMEOUT);
+                self.controller.drain_node(&req.node_name, timeout)?;
+            }
+            NodeRequestKind::RegisterExecutor(req) => {
+                trace_fn!("Backend::register_executor");
+                self.register_executor(req).await?;
+            }
+            NodeRequestKind::UnregisterExecutor(req) => {
+                trace_fn!("Backend::unregister_executor");
+                self.controller.unregister_executor(req.executor_id).await?;
+            }
+            NodeRequestKind::BindExecutorCompleted(req) => {
+                trace_fn!("Backend::bind_executor_completed");
+                self.controller
+                    .bind_session_completed(req.executor_id)
+                    .await?;
+            }
+            NodeRequestKind::UnbindExecutorCompleted(req) => {
+                trace_fn!("Backend::unbind_executor_completed");
+                self.controller
+                    .unbind_executor_completed(req.executor_id)
+                    .await?;
+            }
+            NodeRequestKind::ReleaseExecutorCompleted(req) => {
+                trace_fn!("Backend::release_executor_completed");
+                self.controller
+                    .release_executor_completed(req.executor_id)
+                    .await?;
+            }
+            NodeRequestKind::CompleteTask(req) => {
+                trace_fn!("Backend::complete_task");
+                self.complete_task(req).await?;
+            }
+            NodeRequestKind::ReportTask(req) => {
+                trace_fn!("Backend::report_task");
+                self.report_task(req)?;
+            }
+        }
+
+        Ok(ok)
+    }
+
+    async fn sync_node(&self, req: SyncNodeRequest) -> Result<SyncNodeResponse, FlameError> {
         let node = Node::from(
             req.node
                 .ok_or(FlameError::InvalidConfig("node is required".to_string()))?,
         );
         let executors: Vec<Executor> = req.executors.into_iter().map(rpc::Executor::into).collect();
 
-        let executors = self.controller.sync_node(&node, &executors).await?;
+        let (node, executors) = self.controller.sync_node(&node, &executors).await?;
 
-        Ok(Response::new(SyncNodeResponse {
+        Ok(SyncNodeResponse {
             node: Some(node.into()),
             executors: executors.into_iter().map(rpc::Executor::from).collect(),
-        }))
+        })
     }
 
-    async fn release_node(
-        &self,
-        req: Request<ReleaseNodeRequest>,
-    ) -> Result<Response<rpc::Result>, Status> {
-        trace_fn!("Backend::release_node");
-        let req = req.into_inner();
-        self.controller.release_node(&req.node_name).await?;
-        Ok(Response::new(rpc::Result::default()))
-    }
-
-    async fn register_executor(
-        &self,
-        req: Request<RegisterExecutorRequest>,
-    ) -> Result<Response<rpc::Result>, Status> {
-        trace_fn!("Backend::register_executor");
-        let req = req.into_inner();
+    async fn register_executor(&self, req: RegisterExecutorRequest) -> Result<(), FlameError> {
         let spec = req
             .executor_spec
             .ok_or(FlameError::InvalidConfig("no executor spec".to_string()))?;
@@ -91,119 +265,273 @@ impl Backend for Flame {
             id: req.executor_id,
             node: spec.node,
             resreq: spec.resreq.unwrap_or_default().into(),
-            task_id: None,
+            task_ids: vec![],
             ssn_id: None,
             creation_time: Utc::now(),
+            idle_since: None,
             state: ExecutorState::Idle,
         };
 
-        self.controller
-            .register_executor(&e)
-            .await
-            .map_err(Status::from)?;
-
-        Ok(Response::new(rpc::Result::default()))
+        self.controller.register_executor(&e).await
     }
-    async fn unregister_executor(
-        &self,
-        _: Request<UnregisterExecutorRequest>,
-    ) -> Result<Response<rpc::Result>, Status> {
-        todo!()
+
+    async fn complete_task(&self, req: CompleteTaskRequest) -> Result<(), FlameError> {
+        for res in req.results {
+            let task_id = res
+                .task_id
+                .parse::<apis::TaskID>()
+                .map_err(|_| FlameError::InvalidConfig("invalid task id".to_string()))?;
+
+            // If the task failed, record the error message as its output.
+            let (task_output, task_state) = match res.error {
+                Some(error) => (Some(TaskOutput::from(error)), TaskState::Failed),
+                None => (res.task_output.map(TaskOutput::from), TaskState::Succeed),
+            };
+
+            self.controller
+                .complete_task(req.executor_id.clone(), task_id, task_output, task_state)
+                .await?;
+        }
+
+        Ok(())
     }
 
-    async fn bind_executor(
-        &self,
-        req: Request<BindExecutorRequest>,
-    ) -> Result<Response<BindExecutorResponse>, Status> {
-        trace_fn!("Backend::bind_executor");
-        let req = req.into_inner();
-
-        let ssn = self
-            .controller
-            .wait_for_session(req.executor_id.to_string())
-            .await?;
-        let session = Some(rpc::Session::from(&ssn));
-
-        let app = self.controller.get_application(ssn.application).await?;
-        let application = Some(rpc::Application::from(&app));
-
-        log::debug!(
-            "Bind executor <{}> to Session <{}:{}>",
-            req.executor_id.to_string(),
-            app.name,
-            ssn.id
-        );
+    fn report_task(&self, req: ReportTaskRequest) -> Result<(), FlameError> {
+        let gid = TaskGID {
+            ssn_id: req
+                .session_id
+                .parse::<apis::SessionID>()
+                .map_err(|_| FlameError::InvalidConfig("invalid session id".to_string()))?,
+            task_id: req
+                .task_id
+                .parse::<apis::TaskID>()
+                .map_err(|_| FlameError::InvalidConfig("invalid task id".to_string()))?,
+        };
 
-        Ok(Response::new(BindExecutorResponse {
-            application,
-            session,
-        }))
+        self.controller.report_task(
+            req.executor_id,
+            gid,
+            req.progress.map(TaskProgress::from),
+            req.partial_output.map(TaskOutput::from),
+        )
     }
+}
 
-    async fn bind_executor_completed(
-        &self,
-        req: Request<BindExecutorCompletedRequest>,
-    ) -> Result<Response<rpc::Result>, Status> {
-        trace_fn!("Backend::bind_executor_completed");
-        let req = req.into_inner();
+/// The node of the stream, which is known after the node was registered or synced.
+fn node_name(req: &NodeRequest) -> Option<&str> {
+    let node = match &req.request {
+        Some(NodeRequestKind::RegisterNode(r)) => r.node.as_ref(),
+        Some(NodeRequestKind::SyncNode(r)) => r.node.as_ref(),
+        _ => None,
+    };
 
-        self.controller
-            .bind_session_completed(req.executor_id)
-            .await?;
+    node.and_then(|n| n.metadata.as_ref())
+        .map(|m| m.name.as_str())
+}
 
-        Ok(Response::new(rpc::Result::default()))
-    }
+async fn send(tx: &NodeSender, resp: NodeResponse) -> Result<(), FlameError> {
+    tx.send(Ok(resp))
+        .await
+        .map_err(|_| FlameError::Network("node stream closed".to_string()))
+}
 
-    async fn unbind_executor(
-        &self,
-        req: Request<UnbindExecutorRequest>,
-    ) -> Result<Response<rpc::Result>, Status> {
-        let req = req.into_inner();
-        self.controller.unbind_executor(req.executor_id).await?;
+#[cfg(test)]
+mod tests {
+    use std::time::Duration as StdDuration;
 
-        Ok(Response::new(rpc::Result::default()))
-    }
+    use tokio::net::TcpListener;
+    use tonic::transport::Server;
 
-    async fn unbind_executor_completed(
-        &self,
-        req: Request<UnbindExecutorCompletedRequest>,
-    ) -> Result<Response<rpc::Result>, Status> {
-        let req = req.into_inner();
-        self.controller
-            .unbind_executor_completed(req.executor_id)
-            .await?;
-
-        Ok(Response::new(rpc::Result::default()))
+    use self::rpc::backend_client::BackendClient;
+    use self::rpc::backend_server::BackendServer;
+    use super::*;
+    use crate::controller::{self, ControllerPtr};
+    use crate::storage::tests::new_storage;
+
+    struct NodeConn {
+        requests: mpsc::Sender<NodeRequest>,
+        responses: Streaming<NodeResponse>,
     }
 
-    async fn launch_task(
-        &self,
-        req: Request<LaunchTaskRequest>,
-    ) -> Result<Response<LaunchTaskResponse>, Status> {
-        let req = req.into_inner();
-        let task = self.controller.launch_task(req.executor_id).await?;
-        if let Some(task) = task {
-            return Ok(Response::new(LaunchTaskResponse {
-                task: Some(rpc::Task::from(&task)),
-            }));
+    impl NodeConn {
+        /// Serve the backend by a local port, and connect the stream of node to it.
+        async fn connect(controller: ControllerPtr) -> Result<Self, FlameError> {
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .map_err(|e| FlameError::Network(e.to_string()))?;
+            let addr = listener
+                .local_addr()
+                .map_err(|e| FlameError::Network(e.to_string()))?;
+            let incoming = futures::stream::unfold(listener, |l| async move {
+                Some((l.accept().await.map(|(s, _)| s), l))
+            });
+            tokio::spawn(
+                Server::builder()
+                    .add_service(BackendServer::new(Flame { controller }))
+                    .serve_with_incoming(incoming),
+            );
+
+            let mut client = BackendClient::connect(format!("http://{addr}"))
+                .await
+                .map_err(|e| FlameError::Network(e.to_string()))?;
+            let (requests, rx) = mpsc::channel(NODE_STREAM_BUFFER_SIZE);
+            let responses = client
+                .connect_node(ReceiverStream::new(rx))
+                .await?
+                .into_inner();
+
+            Ok(Self {
+                requests,
+                responses,
+            })
+        }
+
+        async fn send(&self, id: u64, request: NodeRequestKind) -> Result<(), FlameError> {
+            let req = NodeRequest {
+                id,
+                request: Some(request),
+            };
+            self.requests
+                .send(req)
+                .await
+                .map_err(|e| FlameError::Network(e.to_string()))
+        }
+
+        async fn recv(&mut self) -> Result<NodeResponse, FlameError> {
+            let resp = tokio::time::timeout(StdDuration::from_secs(5), self.responses.message())
+                .await
+                .map_err(|_| FlameError::Network("no response in time".to_string()))??;
+
+            resp.ok_or(FlameError::Network("node stream closed".to_string()))
+        }
+
+        /// Receive the response of the request, which succeeded or failed.
+        async fn result(&mut self, id: u64) -> Result<bool, FlameError> {
+            let resp = self.recv().await?;
+            assert_eq!(resp.id, id);
+            match resp.response {
+                Some(NodeResponseKind::Result(res)) => Ok(res.return_code == 0),
+                resp => panic!("unexpected response: {resp:?}"),
+            }
         }
 
-        Ok(Response::new(LaunchTaskResponse { task: None }))
+        /// Receive the work pushed by the server.
+        async fn pushed(&mut self) -> Result<NodeResponseKind, FlameError> {
+            let resp = self.recv().await?;
+            assert_eq!(resp.id, 0);
+            resp.response
+                .ok_or(FlameError::Internal("empty node response".to_string()))
+        }
     }
 
-    async fn complete_task(
-        &self,
-        req: Request<CompleteTaskRequest>,
-    ) -> Result<Response<rpc::Result>, Status> {
-        let req = req.into_inner();
-
-        self.controller
-            .complete_task(
-                req.executor_id.clone(),
-                req.task_output.map(TaskOutput::from),
-            )
-            .await?;
-
-        Ok(Response::new(rpc::Result::default()))
+    fn new_node(name: &str) -> rpc::Node {
+        Node {
+            name: name.to_string(),
+            ..Node::default()
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_node_stream() -> Result<(), FlameError> {
+        let storage = new_storage("node_stream")?;
+        let controller = controller::new_ptr(storage);
+
+        tokio_test::block_on(async {
+            let mut conn = NodeConn::connect(controller.clone()).await?;
+
+            // The node registers itself, and the server pushes its executors.
+            let req = rpc::RegisterNodeRequest {
+                node: Some(new_node("node-1")),
+            };
+            conn.send(1, NodeRequestKind::RegisterNode(req)).await?;
+            assert!(conn.result(1).await?);
+            assert!(matches!(
+                conn.pushed().await?,
+                NodeResponseKind::SyncNode(resp) if resp.executors.is_empty()
+            ));
+
+            // The executor created in the node is pushed, which registers itself.
+            let ssn = controller
+                .create_session("flmexec".to_string(), 1, None)
+                .await?;
+            let exe = controller
+                .create_executor("node-1".to_string(), ssn.id)
+                .await?;
+            assert!(matches!(
+                conn.pushed().await?,
+                NodeResponseKind::SyncNode(resp) if resp.executors.len() == 1
+            ));
+            let req = RegisterExecutorRequest {
+                executor_id: exe.id.clone(),
+                executor_spec: Some(rpc::ExecutorSpec {
+                    resreq: None,
+                    node: "node-1".to_string(),
+                }),
+            };
+            conn.send(2, NodeRequestKind::RegisterExecutor(req)).await?;
+            assert!(conn.result(2).await?);
+
+            // The session bound to the executor is pushed.
+            controller.bind_session(exe.id.clone(), ssn.id).await?;
+            match conn.pushed().await? {
+                NodeResponseKind::BindExecutor(resp) => {
+                    assert_eq!(resp.executor_id, exe.id);
+                    let ssn_id = resp.session.and_then(|s| s.metadata).map(|m| m.id);
+                    assert_eq!(ssn_id, Some(ssn.id.to_string()));
+                }
+                resp => panic!("unexpected response: {resp:?}"),
+            }
+
+            // The task of the session is pushed after the executor was bound.
+            let task = controller.create_task(ssn.id, None).await?;
+            let req = rpc::BindExecutorCompletedRequest {
+                executor_id: exe.id.clone(),
+            };
+            conn.send(3, NodeRequestKind::BindExecutorCompleted(req))
+                .await?;
+            assert!(conn.result(3).await?);
+            match conn.pushed().await? {
+                NodeResponseKind::LaunchTask(resp) => {
+                    assert_eq!(resp.executor_id, exe.id);
+                    let ids: Vec<String> = resp
+                        .tasks
+                        .into_iter()
+                        .filter_map(|t| t.metadata.map(|m| m.id))
+                        .collect();
+                    assert_eq!(ids, vec![task.id.to_string()]);
+                }
+                resp => panic!("unexpected response: {resp:?}"),
+            }
+
+            // The node completes the task; the unknown executor is rejected.
+            let complete = |executor_id: &str| {
+                NodeRequestKind::CompleteTask(CompleteTaskRequest {
+                    executor_id: executor_id.to_string(),
+                    results: vec![rpc::TaskResult {
+                        task_id: task.id.to_string(),
+                        task_output: None,
+                        error: None,
+                    }],
+                })
+            };
+            conn.send(4, complete(&exe.id)).await?;
+            assert!(conn.result(4).await?);
+            assert_eq!(
+                controller.get_task(ssn.id, task.id)?.state,
+                TaskState::Succeed
+            );
+            conn.send(5, complete("unknown")).await?;
+            assert!(!conn.result(5).await?);
+
+            // The executor is unbound by the server, e.g. the node is draining.
+            controller.unbind_executor(exe.id.clone()).await?;
+            assert!(matches!(
+                conn.pushed().await?,
+                NodeResponseKind::UnbindExecutor(resp) if resp.executor_id == exe.id
+            ));
+
+            Ok(())
+        })
     }
 }
@@ -13,8 +13,10 @@ limitations under the License.
 use std::pin::Pin;
 
 use async_trait::async_trait;
+use chrono::Duration;
 use common::apis::ApplicationAttributes;
 use futures::Stream;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
@@ -24,11 +26,13 @@ use self::rpc::frontend_server::Frontend;
 use self::rpc::{
     CloseSessionRequest, CreateSessionRequest, CreateTaskRequest, DeleteSessionRequest,
     DeleteTaskRequest, GetSessionRequest, GetTaskRequest, ListSessionRequest, OpenSessionRequest,
-    Session, SessionList, Task, WatchTaskRequest,
+    Session, SessionList, Task, WatchSessionRequest, WatchTaskRequest,
 };
+use ::rpc::flame::executor_manager_client::ExecutorManagerClient;
 use ::rpc::flame::{
-    ApplicationList, GetApplicationRequest, ListApplicationRequest, RegisterApplicationRequest,
-    UnregisterApplicationRequest, UpdateApplicationRequest,
+    ApplicationList, CordonNodeRequest, DrainNodeRequest, GetApplicationRequest,
+    ListApplicationRequest, LogEntry, ReadLogsRequest, RegisterApplicationRequest,
+    UncordonNodeRequest, UnregisterApplicationRequest, UpdateApplicationRequest,
 };
 use rpc::flame as rpc;
 
@@ -36,10 +40,14 @@ use common::{apis, FlameError};
 use common::{trace::TraceFn, trace_fn};
 
 use crate::apiserver::Flame;
+use crate::controller::ControllerPtr;
+use crate::model::TaskEvent;
 
 #[async_trait]
 impl Frontend for Flame {
     type WatchTaskStream = Pin<Box<dyn Stream<Item = Result<Task, Status>> + Send>>;
+    type WatchSessionStream = Pin<Box<dyn Stream<Item = Result<Task, Status>> + Send>>;
+    type ReadLogsStream = Pin<Box<dyn Stream<Item = Result<LogEntry, Status>> + Send>>;
 
     async fn register_application(
         &self,
@@ -79,6 +87,14 @@ impl Frontend for Flame {
             }
         }
 
+        if let Some(ref url) = spec.url {
+            apis::parse_package_checksum(url, spec.checksum.as_deref())?;
+        }
+
+        if spec.concurrency.is_some_and(|c| c < 1) {
+            return Err(Status::invalid_argument("concurrency must be at least 1"));
+        }
+
         let res = self
             .controller
             .register_application(req.name, ApplicationAttributes::from(spec))
@@ -288,24 +304,50 @@ impl Frontend for Flame {
         let (tx, rx) = mpsc::channel(128);
 
         let controller = self.controller.clone();
+        let mut events = controller.watch_tasks();
         tokio::spawn(async move {
+            // Keep the state watcher across the iterations, so the events of the task don't
+            // restart it; it's re-created only after the state was sent.
+            let mut state = Box::pin(controller.watch_task(gid));
             loop {
-                match controller.watch_task(gid).await {
-                    Ok(task) => {
-                        log::debug!("Task <{}> state is <{}>", task.id, task.state as i32);
-                        if let Err(e) = tx.send(Result::<_, Status>::Ok(Task::from(&task))).await {
-                            log::debug!("Failed to send Task <{gid}>: {e}");
+                tokio::select! {
+                    res = &mut state => match res {
+                        Ok(task) => {
+                            log::debug!("Task <{}> state is <{}>", task.id, task.state as i32);
+                            let res = tx.send(Result::<_, Status>::Ok(Task::from(&task))).await;
+                            if let Err(e) = res {
+                                log::debug!("Failed to send Task <{gid}>: {e}");
+                                break;
+                            }
+                            if task.is_completed() {
+                                log::debug!("Task <{}> is completed, exit.", task.id);
+                                break;
+                            }
+                            state.set(controller.watch_task(gid));
+                        }
+                        Err(e) => {
+                            log::debug!("Failed to watch Task <{gid}>: {e}");
                             break;
                         }
-                        if task.is_completed() {
-                            log::debug!("Task <{}> is completed, exit.", task.id);
+                    },
+                    // The progress and partial outputs of the task; its state is watched above.
+                    event = events.recv() => match event {
+                        Ok(event @ TaskEvent::Reported(..)) if event.task().gid() == gid => {
+                            if let Err(e) = tx.send(Ok(Task::from(&event))).await {
+                                log::debug!("Failed to send Task <{gid}>: {e}");
+                                break;
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(n)) => {
+                            log::warn!("The watcher of Task <{gid}> lost {n} events.");
+                            let status =
+                                Status::data_loss(format!("lost {n} events of Task <{gid}>"));
+                            let _ = tx.send(Err(status)).await;
                             break;
                         }
-                    }
-                    Err(e) => {
-                        log::debug!("Failed to watch Task <{gid}>: {e}");
-                        break;
-                    }
+                        Err(RecvError::Closed) => break,
+                    },
                 }
             }
         });
@@ -316,6 +358,84 @@ impl Frontend for Flame {
         ))
     }
 
+    async fn watch_session(
+        &self,
+        req: Request<WatchSessionRequest>,
+    ) -> Result<Response<Self::WatchSessionStream>, Status> {
+        let req = req.into_inner();
+        let ssn_id = req
+            .session_id
+            .parse::<apis::SessionID>()
+            .map_err(|_| Status::invalid_argument("invalid session id"))?;
+
+        // Subscribe the events before listing the tasks, so no update is lost.
+        let mut events = self.controller.watch_tasks();
+        let mut sessions = self.controller.watch_sessions();
+        let tasks = self.controller.list_task(ssn_id)?;
+
+        let (tx, rx) = mpsc::channel(128);
+
+        let controller = self.controller.clone();
+        tokio::spawn(async move {
+            for task in tasks {
+                if let Err(e) = tx.send(Ok(Task::from(&task))).await {
+                    log::debug!("Failed to send Task <{}>: {e}", task.gid());
+                    return;
+                }
+            }
+
+            // The watcher exits once the session is closed and all its tasks are completed,
+            // which is checked when the session or the state of its tasks was changed.
+            let mut changed = true;
+            loop {
+                if changed {
+                    match is_session_completed(&controller, ssn_id) {
+                        Ok(false) => {}
+                        Ok(true) => {
+                            log::debug!("Session <{ssn_id}> is completed, exit.");
+                            break;
+                        }
+                        Err(e) => {
+                            log::debug!("Failed to watch Session <{ssn_id}>: {e}");
+                            break;
+                        }
+                    }
+                }
+
+                changed = tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(event) if event.task().ssn_id == ssn_id => {
+                            if let Err(e) = tx.send(Ok(Task::from(&event))).await {
+                                log::debug!("Failed to send Task <{}>: {e}", event.task().gid());
+                                break;
+                            }
+                            matches!(event, TaskEvent::StateChanged(_))
+                        }
+                        Ok(_) => false,
+                        Err(RecvError::Lagged(n)) => {
+                            log::warn!("The watcher of Session <{ssn_id}> lost {n} events.");
+                            let status =
+                                Status::data_loss(format!("lost {n} events of Session <{ssn_id}>"));
+                            let _ = tx.send(Err(status)).await;
+                            break;
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    res = sessions.changed() => match res {
+                        Ok(_) => true,
+                        Err(_) => break,
+                    },
+                    _ = tx.closed() => break,
+                };
+            }
+        });
+
+        let output_stream = ReceiverStream::new(rx);
+        Ok(Response::new(
+            Box::pin(output_stream) as Self::WatchSessionStream
+        ))
+    }
+
     async fn get_task(&self, req: Request<GetTaskRequest>) -> Result<Response<Task>, Status> {
         let req = req.into_inner();
         let ssn_id = req
@@ -336,4 +456,276 @@ impl Frontend for Flame {
 
         Ok(Response::new(task))
     }
+
+    async fn cordon_node(
+        &self,
+        req: Request<CordonNodeRequest>,
+    ) -> Result<Response<rpc::Result>, Status> {
+        trace_fn!("Frontend::cordon_node");
+        let req = req.into_inner();
+        self.controller.cordon_node(&req.node_name)?;
+
+        Ok(Response::new(rpc::Result::default()))
+    }
+
+    async fn uncordon_node(
+        &self,
+        req: Request<UncordonNodeRequest>,
+    ) -> Result<Response<rpc::Result>, Status> {
+        trace_fn!("Frontend::uncordon_node");
+        let req = req.into_inner();
+        self.controller.uncordon_node(&req.node_name)?;
+
+        Ok(Response::new(rpc::Result::default()))
+    }
+
+    async fn drain_node(
+        &self,
+        req: Request<DrainNodeRequest>,
+    ) -> Result<Response<rpc::Result>, Status> {
+        trace_fn!("Frontend::drain_node");
+        let req = req.into_inner();
+        let timeout = req
+            .timeout
+            .map(Duration::seconds)
+            .unwrap_or(apis::DEFAULT_DRAIN_TIMEOUT);
+        self.controller.drain_node(&req.node_name, timeout)?;
+
+        Ok(Response::new(rpc::Result::default()))
+    }
+
+    async fn read_logs(
+        &self,
+        req: Request<ReadLogsRequest>,
+    ) -> Result<Response<Self::ReadLogsStream>, Status> {
+        trace_fn!("Frontend::read_logs");
+        let req = req.into_inner();
+        let ssn_id = req
+            .session_id
+            .parse::<apis::SessionID>()
+            .map_err(|_| Status::invalid_argument("invalid session id"))?;
+        self.controller.get_session(ssn_id)?;
+
+        // The logs are kept by the executor managers, read them from all nodes.
+        let nodes = self.controller.list_nodes()?;
+
+        let (tx, rx) = mpsc::channel(128);
+        tokio::spawn(async move {
+            let (node_tx, mut node_rx) = mpsc::channel(128);
+            for node in nodes {
+                tokio::spawn(read_node_logs(node, req.clone(), node_tx.clone()));
+            }
+            drop(node_tx);
+
+            if req.follow {
+                loop {
+                    let entry = tokio::select! {
+                        entry = node_rx.recv() => entry,
+                        _ = tx.closed() => break,
+                    };
+                    let Some(entry) = entry else {
+                        break;
+                    };
+                    if tx.send(Ok(entry)).await.is_err() {
+                        break;
+                    }
+                }
+            } else {
+                // Merge the logs of all nodes in time order.
+                let mut entries = vec![];
+                while let Some(entry) = node_rx.recv().await {
+                    entries.push(entry);
+                }
+                entries.sort_by_key(|e| e.timestamp);
+
+                for entry in entries {
+                    if tx.send(Ok(entry)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        let output_stream = ReceiverStream::new(rx);
+        Ok(Response::new(
+            Box::pin(output_stream) as Self::ReadLogsStream
+        ))
+    }
+}
+
+/// The session is completed when it's closed and all its tasks are completed.
+fn is_session_completed(
+    controller: &ControllerPtr,
+    ssn_id: apis::SessionID,
+) -> Result<bool, FlameError> {
+    let ssn = controller.get_session(ssn_id)?;
+    if !ssn.is_closed() {
+        return Ok(false);
+    }
+    let tasks = controller.list_task(ssn_id)?;
+    Ok(tasks.iter().all(|t| t.is_completed()))
+}
+
+/// Read the logs from the executor manager of the node; the node is skipped if it's not reachable.
+async fn read_node_logs(node: apis::Node, req: ReadLogsRequest, tx: mpsc::Sender<LogEntry>) {
+    let Some(endpoint) = node.endpoint else {
+        return;
+    };
+
+    let mut client = match ExecutorManagerClient::connect(endpoint.clone()).await {
+        Ok(client) => client,
+        Err(e) => {
+            log::warn!(
+                "Failed to connect to node <{}> at <{endpoint}>: {e}",
+                node.name
+            );
+            return;
+        }
+    };
+    let mut stream = match client.read_logs(req).await {
+        Ok(resp) => resp.into_inner(),
+        Err(e) => {
+            log::warn!("Failed to read logs from node <{}>: {e}", node.name);
+            return;
+        }
+    };
+
+    loop {
+        let entry = tokio::select! {
+            entry = stream.message() => entry,
+            _ = tx.closed() => break,
+        };
+        match entry {
+            Ok(Some(entry)) => {
+                if tx.send(entry).await.is_err() {
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                log::warn!("Failed to read logs from node <{}>: {e}", node.name);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration as StdDuration;
+
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::controller;
+    use crate::storage::tests::new_storage;
+    use crate::storage::StoragePtr;
+
+    /// Create a session with a running task, and return the frontend, its storage and the task.
+    fn new_running_task(name: &str) -> Result<(Flame, StoragePtr, apis::Task), FlameError> {
+        let storage = new_storage(name)?;
+        let controller = controller::new_ptr(storage.clone());
+
+        let ssn = tokio_test::block_on(controller.create_session("flmexec".to_string(), 1, None))?;
+        let task = tokio_test::block_on(controller.create_task(ssn.id, None))?;
+        let ssn_ptr = storage.get_session_ptr(ssn.id)?;
+        let task_ptr = storage.get_task_ptr(task.gid())?;
+        tokio_test::block_on(controller.update_task(
+            ssn_ptr,
+            task_ptr,
+            apis::TaskState::Running,
+            None,
+        ))?;
+        let task = controller.get_task(ssn.id, task.id)?;
+
+        Ok((Flame { controller }, storage, task))
+    }
+
+    async fn next<S>(stream: &mut S) -> Option<Result<Task, Status>>
+    where
+        S: Stream<Item = Result<Task, Status>> + Unpin,
+    {
+        tokio::time::timeout(StdDuration::from_secs(5), stream.next())
+            .await
+            .unwrap_or_else(|_| panic!("no task in time"))
+    }
+
+    #[test]
+    fn test_watch_task_reported() -> Result<(), FlameError> {
+        let (flame, storage, task) = new_running_task("watch_task_reported")?;
+        tokio_test::block_on(async {
+            let req = WatchTaskRequest {
+                task_id: task.id.to_string(),
+                session_id: task.ssn_id.to_string(),
+            };
+            let mut stream = flame.watch_task(Request::new(req)).await?.into_inner();
+
+            let progress = apis::TaskProgress {
+                percentage: 10.0,
+                message: None,
+            };
+            storage.report_task(
+                task.gid(),
+                Some(progress),
+                Some(apis::TaskOutput::from("chunk")),
+            )?;
+            let reported = next(&mut stream).await.transpose()?;
+            let reported = reported.ok_or(FlameError::Internal("stream closed".to_string()))?;
+            assert_eq!(reported.partial_output, Some(b"chunk".to_vec()));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_watch_session_lagged() -> Result<(), FlameError> {
+        let (flame, storage, task) = new_running_task("watch_session_lagged")?;
+        tokio_test::block_on(async {
+            let req = WatchSessionRequest {
+                session_id: task.ssn_id.to_string(),
+            };
+            let mut stream = flame.watch_session(Request::new(req)).await?.into_inner();
+
+            // The watcher does not run until the stream is read, so it lags behind.
+            for _ in 0..2048 {
+                storage.report_task(task.gid(), None, None)?;
+            }
+
+            // The listed task is sent first, then the watcher fails as events were lost.
+            assert!(matches!(next(&mut stream).await, Some(Ok(_))));
+            match next(&mut stream).await {
+                Some(Err(status)) => assert_eq!(status.code(), tonic::Code::DataLoss),
+                res => panic!("unexpected result: {res:?}"),
+            }
+            assert!(next(&mut stream).await.is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_watch_session_completed() -> Result<(), FlameError> {
+        let (flame, storage, task) = new_running_task("watch_session_completed")?;
+        tokio_test::block_on(async {
+            let req = WatchSessionRequest {
+                session_id: task.ssn_id.to_string(),
+            };
+            let mut stream = flame.watch_session(Request::new(req)).await?.into_inner();
+            assert!(matches!(next(&mut stream).await, Some(Ok(_))));
+
+            // The watcher keeps running until the session is closed.
+            let ssn_ptr = storage.get_session_ptr(task.ssn_id)?;
+            let task_ptr = storage.get_task_ptr(task.gid())?;
+            flame
+                .controller
+                .update_task(ssn_ptr, task_ptr, apis::TaskState::Succeed, None)
+                .await?;
+            assert!(matches!(next(&mut stream).await, Some(Ok(_))));
+
+            flame.controller.close_session(task.ssn_id).await?;
+            assert!(next(&mut stream).await.is_none());
+
+            Ok(())
+        })
+    }
 }
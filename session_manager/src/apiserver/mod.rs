@@ -65,6 +65,9 @@ impl FlameThread for ApiserverRunner {
 
         Server::builder()
             .tcp_keepalive(Some(Duration::from_secs(1)))
+            // Detect the broken streams of the nodes, so the lost nodes are not kept alive.
+            .http2_keepalive_interval(Some(Duration::from_secs(10)))
+            .http2_keepalive_timeout(Some(Duration::from_secs(20)))
             .add_service(FrontendServer::new(frontend_service))
             .add_service(BackendServer::new(backend_service))
             .serve(address)
@@ -0,0 +1,225 @@
+/*
+Copyright 2023 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+
+use common::apis::{Application, ExecutorID, ExecutorState, TaskID};
+use common::{lock_ptr, trace::TraceFn, trace_fn, FlameError};
+
+use crate::controller::{states, Controller};
+use crate::model::{Assignment, Executor};
+
+// The max number of tasks prefetched by each executor, besides the running ones; so the
+// executor does not wait on the network between tasks.
+const TASK_PREFETCH_SIZE: usize = 3;
+
+/// The work assigned to the executors of a node and pushed by its stream; the work is
+/// pushed again on a new stream, e.g. the node reconnected.
+#[derive(Debug, Default)]
+pub struct Assigner {
+    node_name: String,
+    /// The draining of the node and its executors, which were pushed.
+    executors: Option<(bool, BTreeSet<ExecutorID>)>,
+    /// The binding executors whose sessions were pushed.
+    bound: HashSet<ExecutorID>,
+    /// The unbinding executors which were pushed.
+    unbound: HashSet<ExecutorID>,
+    /// The releasing executors which were pushed.
+    released: HashSet<ExecutorID>,
+    /// The tasks pushed to the bound executors, which are not completed yet.
+    launched: HashMap<ExecutorID, HashSet<TaskID>>,
+    /// The time to unbind the bound executors which have nothing to do.
+    idle: HashMap<ExecutorID, DateTime<Utc>>,
+}
+
+impl Assigner {
+    pub fn new(node_name: &str) -> Self {
+        Self {
+            node_name: node_name.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn node_name(&self) -> &str {
+        &self.node_name
+    }
+
+    /// The time to assign the work again without any event, e.g. unbind the idle executors.
+    pub fn deadline(&self) -> Option<DateTime<Utc>> {
+        self.idle.values().min().copied()
+    }
+
+    /// Forget the work of the executors which left the states, e.g. the tasks of the
+    /// unbound executor; so it's pushed again if the executor enters the state again.
+    fn retain(&mut self, executors: &[Executor]) {
+        let states: HashMap<&ExecutorID, ExecutorState> =
+            executors.iter().map(|e| (&e.id, e.state)).collect();
+        let is = |id: &ExecutorID, state| states.get(id) == Some(&state);
+
+        self.bound.retain(|id| is(id, ExecutorState::Binding));
+        self.unbound.retain(|id| is(id, ExecutorState::Unbinding));
+        self.released.retain(|id| is(id, ExecutorState::Releasing));
+        self.launched.retain(|id, _| is(id, ExecutorState::Bound));
+        self.idle.retain(|id, _| is(id, ExecutorState::Bound));
+    }
+}
+
+impl Controller {
+    /// Assign the work to the executors of the node which was not pushed by the assigner,
+    /// e.g. the session to bind, the tasks to launch and the executors to release; the
+    /// bound executors are unbound if they have nothing to do after the delay release of
+    /// the application.
+    pub async fn assign(&self, assigner: &mut Assigner) -> Result<Vec<Assignment>, FlameError> {
+        trace_fn!("Controller::assign");
+
+        let node = self.storage.get_node(&assigner.node_name)?;
+        let executors: Vec<Executor> = self
+            .storage
+            .list_executors(&assigner.node_name)?
+            .into_iter()
+            .filter(|e| e.state != ExecutorState::Released)
+            .collect();
+        assigner.retain(&executors);
+
+        let mut res = vec![];
+
+        let ids = executors.iter().map(|e| e.id.clone()).collect();
+        let pushed = Some((node.draining, ids));
+        if assigner.executors != pushed {
+            assigner.executors = pushed;
+            res.push(Assignment::Executors(node, executors.clone()));
+        }
+
+        // The applications are loaded once for all executors.
+        let mut apps = HashMap::new();
+        for exe in &executors {
+            match self.assign_executor(assigner, &mut apps, exe).await {
+                Ok(Some(assignment)) => res.push(assignment),
+                Ok(None) => {}
+                Err(e) => log::error!("Failed to assign work to executor <{}>: {e}", exe.id),
+            }
+        }
+
+        Ok(res)
+    }
+
+    async fn assign_executor(
+        &self,
+        assigner: &mut Assigner,
+        apps: &mut HashMap<String, Application>,
+        exe: &Executor,
+    ) -> Result<Option<Assignment>, FlameError> {
+        match exe.state {
+            ExecutorState::Binding if !assigner.bound.contains(&exe.id) => {
+                let ssn_id = exe.ssn_id.ok_or(FlameError::InvalidState(
+                    "no session in binding executor".to_string(),
+                ))?;
+                let ssn = self.storage.get_session(ssn_id)?;
+                let app = self.load_application(apps, &ssn.application).await?;
+
+                assigner.bound.insert(exe.id.clone());
+                Ok(Some(Assignment::Bind(exe.id.clone(), Box::new(app), ssn)))
+            }
+            ExecutorState::Bound => self.assign_tasks(assigner, apps, exe).await,
+            ExecutorState::Unbinding if assigner.unbound.insert(exe.id.clone()) => {
+                Ok(Some(Assignment::Unbind(exe.id.clone())))
+            }
+            ExecutorState::Releasing if assigner.released.insert(exe.id.clone()) => {
+                Ok(Some(Assignment::Release(exe.id.clone())))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Launch the pending tasks of the session to the bound executor, up to the concurrency
+    /// of the application plus the prefetched ones; and push the launched tasks which were
+    /// not pushed yet, e.g. the node reconnected.
+    async fn assign_tasks(
+        &self,
+        assigner: &mut Assigner,
+        apps: &mut HashMap<String, Application>,
+        exe: &Executor,
+    ) -> Result<Option<Assignment>, FlameError> {
+        let ssn_id = exe.ssn_id.ok_or(FlameError::InvalidState(
+            "no session in bound executor".to_string(),
+        ))?;
+        let ssn_ptr = self.storage.get_session_ptr(ssn_id)?;
+        let app_name = lock_ptr!(ssn_ptr)?.application.clone();
+        let app = self.load_application(apps, &app_name).await?;
+
+        let launched = assigner.launched.entry(exe.id.clone()).or_default();
+        launched.retain(|id| exe.task_ids.contains(id));
+
+        let mut tasks = vec![];
+        for task_id in &exe.task_ids {
+            if !launched.contains(task_id) {
+                tasks.push(self.storage.get_task(ssn_id, *task_id)?);
+            }
+        }
+
+        let max_tasks = app.concurrency.max(1) as usize + TASK_PREFETCH_SIZE;
+        if exe.task_ids.len() < max_tasks {
+            let exe_ptr = self.storage.get_executor_ptr(exe.id.clone())?;
+            let state = states::from(self.storage.clone(), exe_ptr)?;
+            let max_tasks = max_tasks - exe.task_ids.len();
+            tasks.extend(state.launch_task(ssn_ptr, max_tasks).await?);
+        }
+
+        if !tasks.is_empty() {
+            assigner.idle.remove(&exe.id);
+            launched.extend(tasks.iter().map(|t| t.id));
+            return Ok(Some(Assignment::Launch(exe.id.clone(), tasks)));
+        }
+
+        if !exe.task_ids.is_empty() {
+            assigner.idle.remove(&exe.id);
+            return Ok(None);
+        }
+
+        // The executor has nothing to do, unbind it after the delay release.
+        let deadline = *assigner
+            .idle
+            .entry(exe.id.clone())
+            .or_insert_with(|| Utc::now() + app.delay_release);
+        if Utc::now() < deadline {
+            return Ok(None);
+        }
+
+        log::debug!(
+            "Executor <{}> has no task of session <{ssn_id}>, unbind it.",
+            exe.id
+        );
+        assigner.idle.remove(&exe.id);
+        self.unbind_executor(exe.id.clone()).await?;
+        assigner.unbound.insert(exe.id.clone());
+
+        Ok(Some(Assignment::Unbind(exe.id.clone())))
+    }
+
+    async fn load_application(
+        &self,
+        apps: &mut HashMap<String, Application>,
+        name: &str,
+    ) -> Result<Application, FlameError> {
+        if let Some(app) = apps.get(name) {
+            return Ok(app.clone());
+        }
+
+        let app = self.storage.get_application(name.to_string()).await?;
+        apps.insert(name.to_string(), app.clone());
+
+        Ok(app)
+    }
+}
@@ -16,19 +16,26 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use chrono::{Duration, Utc};
+
 use common::apis::{
-    Application, ApplicationAttributes, ApplicationID, CommonData, ExecutorID, Node, NodeState,
-    Session, SessionID, SessionPtr, Task, TaskGID, TaskID, TaskInput, TaskOutput, TaskPtr,
-    TaskState,
+    Application, ApplicationAttributes, ApplicationID, CommonData, ExecutorID, ExecutorState, Node,
+    NodeState, Session, SessionID, SessionPtr, Task, TaskGID, TaskID, TaskInput, TaskOutput,
+    TaskProgress, TaskPtr, TaskState,
 };
 
 use common::{lock_ptr, trace::TraceFn, trace_fn, FlameError};
 
-use crate::model::{Executor, ExecutorPtr, NodeInfoPtr, SessionInfoPtr, SnapShotPtr};
+use tokio::sync::{broadcast, watch};
+
+use crate::model::{Executor, ExecutorPtr, NodeInfoPtr, SessionInfoPtr, SnapShotPtr, TaskEvent};
 use crate::storage::StoragePtr;
 
+mod assign;
 mod states;
 
+pub use assign::Assigner;
+
 pub struct Controller {
     storage: StoragePtr,
 }
@@ -47,8 +54,28 @@ impl Controller {
     pub async fn sync_node(
         &self,
         node: &Node,
-        executors: &Vec<Executor>,
-    ) -> Result<Vec<Executor>, FlameError> {
+        executors: &[Executor],
+    ) -> Result<(Node, Vec<Executor>), FlameError> {
+        let lost = self.storage.release_lost_executors(&node.name, executors)?;
+        if !lost.is_empty() {
+            log::warn!(
+                "There are {} executors lost in node <{}>, release them.",
+                lost.len(),
+                node.name
+            );
+            self.retry_tasks(lost).await;
+        }
+
+        let adopted = self.storage.adopt_executors(&node.name, executors).await?;
+        for exe in adopted {
+            log::info!(
+                "Executor <{}> in node <{}> was adopted as <{}>.",
+                exe.id,
+                node.name,
+                exe.state
+            );
+        }
+
         self.storage.sync_node(node, executors).await
     }
 
@@ -56,6 +83,104 @@ impl Controller {
         self.storage.release_node(node_name).await
     }
 
+    pub fn list_nodes(&self) -> Result<Vec<Node>, FlameError> {
+        self.storage.list_nodes()
+    }
+
+    pub fn cordon_node(&self, node_name: &str) -> Result<(), FlameError> {
+        self.storage.cordon_node(node_name, true)
+    }
+
+    pub fn uncordon_node(&self, node_name: &str) -> Result<(), FlameError> {
+        self.storage.cordon_node(node_name, false)
+    }
+
+    pub fn drain_node(&self, node_name: &str, timeout: Duration) -> Result<(), FlameError> {
+        self.storage.drain_node(node_name, timeout)
+    }
+
+    /// Release the executors of the draining nodes: the idle executors are released
+    /// directly, the bound executors are unbound after their running task completed.
+    /// After the timeout, all executors are released and their tasks are re-queued.
+    pub async fn drain_nodes(&self) -> Result<(), FlameError> {
+        trace_fn!("Controller::drain_nodes");
+
+        for (node_name, deadline) in self.storage.draining_nodes()? {
+            if Utc::now() > deadline {
+                let count = self.release_executors(&node_name).await?;
+                if count > 0 {
+                    log::warn!("Drain node <{node_name}> timeout, released its {count} executors.");
+                }
+                continue;
+            }
+
+            for exe in self.storage.list_executors(&node_name)? {
+                let res = match exe.state {
+                    ExecutorState::Void | ExecutorState::Idle => {
+                        log::info!(
+                            "Release executor <{}> of draining node <{node_name}>.",
+                            exe.id
+                        );
+                        self.release_executor(exe.id.clone()).await
+                    }
+                    ExecutorState::Bound => self.unbind_executor(exe.id.clone()).await,
+                    _ => Ok(()),
+                };
+                if let Err(e) = res {
+                    log::error!("Failed to drain executor <{}>: {e}", exe.id);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Mark the nodes without heartbeat as NotReady, release their executors
+    /// and re-queue the tasks running on them.
+    pub async fn check_nodes(&self) -> Result<(), FlameError> {
+        trace_fn!("Controller::check_nodes");
+
+        for node_name in self.storage.expire_nodes()? {
+            let count = self.release_executors(&node_name).await?;
+            log::warn!("Node <{node_name}> is NotReady, released its {count} executors.");
+        }
+
+        Ok(())
+    }
+
+    /// Unregister all executors of the node by their states, which re-queue their
+    /// running tasks; return the number of the released executors.
+    async fn release_executors(&self, node_name: &str) -> Result<usize, FlameError> {
+        let executors = self.storage.list_executors(node_name)?;
+        for exe in &executors {
+            if let Err(e) = self.unregister_executor(exe.id.clone()).await {
+                log::error!("Failed to release executor <{}>: {e}", exe.id);
+            }
+        }
+
+        Ok(executors.len())
+    }
+
+    /// Re-queue the running tasks of the released executors.
+    async fn retry_tasks(&self, executors: Vec<Executor>) {
+        for exe in executors {
+            let Some(ssn_id) = exe.ssn_id else {
+                continue;
+            };
+            for task_id in exe.task_ids {
+                let gid = TaskGID { ssn_id, task_id };
+                match self.storage.retry_task(gid).await {
+                    Ok(task) => log::info!(
+                        "Task <{gid}> of executor <{}> is {} after executor lost.",
+                        exe.id,
+                        task.state
+                    ),
+                    Err(e) => log::error!("Failed to retry task <{gid}>: {e}"),
+                }
+            }
+        }
+    }
+
     pub async fn create_session(
         &self,
         app: String,
@@ -93,6 +218,30 @@ impl Controller {
         self.storage.get_task(ssn_id, id)
     }
 
+    pub fn list_task(&self, ssn_id: SessionID) -> Result<Vec<Task>, FlameError> {
+        self.storage.list_task(ssn_id)
+    }
+
+    /// Subscribe the changes of the executors, e.g. bound to a session or released.
+    pub fn watch_executors(&self) -> watch::Receiver<()> {
+        self.storage.watch_executors()
+    }
+
+    /// Keep the node alive while its stream is connected.
+    pub fn touch_node(&self, node_name: &str) -> Result<(), FlameError> {
+        self.storage.touch_node(node_name)
+    }
+
+    /// Subscribe the changes of the sessions, e.g. closed or deleted.
+    pub fn watch_sessions(&self) -> watch::Receiver<()> {
+        self.storage.watch_sessions()
+    }
+
+    /// Subscribe the events of all tasks, e.g. their states, progress and partial outputs.
+    pub fn watch_tasks(&self) -> broadcast::Receiver<TaskEvent> {
+        self.storage.watch_tasks()
+    }
+
     pub async fn update_task(
         &self,
         ssn: SessionPtr,
@@ -108,7 +257,10 @@ impl Controller {
         node_name: String,
         ssn_id: SessionID,
     ) -> Result<Executor, FlameError> {
-        self.storage.create_executor(node_name, ssn_id).await
+        let exe = self.storage.create_executor(node_name, ssn_id).await?;
+        self.storage.notify_executors();
+
+        Ok(exe)
     }
 
     pub async fn register_executor(&self, e: &Executor) -> Result<(), FlameError> {
@@ -117,6 +269,7 @@ impl Controller {
         let exe_ptr = self.storage.get_executor_ptr(e.id.clone())?;
         let state = states::from(self.storage.clone(), exe_ptr.clone())?;
         state.register_executor(exe_ptr).await?;
+        self.storage.notify_executors();
 
         Ok(())
     }
@@ -151,17 +304,6 @@ impl Controller {
         Ok((*task).clone())
     }
 
-    pub async fn wait_for_session(&self, id: ExecutorID) -> Result<Session, FlameError> {
-        trace_fn!("Controller::wait_for_session");
-        let exe_ptr = self.storage.get_executor_ptr(id)?;
-        let ssn_id = WaitForSsnFuture::new(&exe_ptr).await?;
-
-        let ssn_ptr = self.storage.get_session_ptr(ssn_id)?;
-        let ssn = lock_ptr!(ssn_ptr)?;
-
-        Ok((*ssn).clone())
-    }
-
     pub async fn bind_session(&self, id: ExecutorID, ssn_id: SessionID) -> Result<(), FlameError> {
         trace_fn!("Controller::bind_session");
 
@@ -170,6 +312,7 @@ impl Controller {
 
         let ssn_ptr = self.storage.get_session_ptr(ssn_id)?;
         state.bind_session(ssn_ptr).await?;
+        self.storage.notify_executors();
 
         Ok(())
     }
@@ -181,70 +324,76 @@ impl Controller {
         let state = states::from(self.storage.clone(), exe_ptr)?;
 
         state.bind_session_completed().await?;
+        self.storage.notify_executors();
 
         Ok(())
     }
 
-    pub async fn launch_task(&self, id: ExecutorID) -> Result<Option<Task>, FlameError> {
-        trace_fn!("Controller::launch_task");
-        let exe_ptr = self.storage.get_executor_ptr(id)?;
-        let state = states::from(self.storage.clone(), exe_ptr.clone())?;
-        let (ssn_id, task_id) = {
-            let exec = lock_ptr!(exe_ptr)?;
-            (exec.ssn_id, exec.task_id)
-        };
-        let ssn_id = ssn_id.ok_or(FlameError::InvalidState(
-            "no session in bound executor".to_string(),
-        ))?;
-
-        //
-        if let Some(task_id) = task_id {
-            log::warn!(
-                "Re-launch the task <{}/{}>",
-                ssn_id.clone(),
-                task_id.clone()
-            );
-            let task_ptr = self.storage.get_task_ptr(TaskGID { ssn_id, task_id })?;
-
-            let task = lock_ptr!(task_ptr)?;
-            return Ok(Some((*task).clone()));
-        }
-
-        let ssn_ptr = self.storage.get_session_ptr(ssn_id)?;
-        state.launch_task(ssn_ptr).await
-    }
-
+    /// Complete the task launched to the executor; it's skipped if the task was completed,
+    /// e.g. the executor retried the completion.
     pub async fn complete_task(
         &self,
         id: ExecutorID,
+        task_id: TaskID,
         task_output: Option<TaskOutput>,
+        task_state: TaskState,
     ) -> Result<(), FlameError> {
         trace_fn!("Storage::complete_task");
-        let exe_ptr = self.storage.get_executor_ptr(id)?;
-        let (ssn_id, task_id) = {
+        let exe_ptr = self.storage.get_executor_ptr(id.clone())?;
+        let (ssn_id, launched) = {
             let exe = lock_ptr!(exe_ptr)?;
             (
                 exe.ssn_id.ok_or(FlameError::InvalidState(
                     "no session in executor".to_string(),
                 ))?,
-                exe.task_id
-                    .ok_or(FlameError::InvalidState("no task in executor".to_string()))?,
+                exe.task_ids.contains(&task_id),
             )
         };
 
-        let task_ptr = self.storage.get_task_ptr(TaskGID { ssn_id, task_id })?;
+        let gid = TaskGID { ssn_id, task_id };
+        if !launched {
+            log::warn!("Task <{gid}> is not running in executor <{id}>, skip to complete it.");
+            return Ok(());
+        }
+
+        let task_ptr = self.storage.get_task_ptr(gid)?;
         let ssn_ptr = self.storage.get_session_ptr(ssn_id)?;
 
         let state = states::from(self.storage.clone(), exe_ptr)?;
-        state.complete_task(ssn_ptr, task_ptr, task_output).await?;
+        state
+            .complete_task(ssn_ptr, task_ptr, task_output, task_state)
+            .await?;
 
         Ok(())
     }
 
+    /// Relay the progress and partial output of the task running in the executor.
+    pub fn report_task(
+        &self,
+        id: ExecutorID,
+        gid: TaskGID,
+        progress: Option<TaskProgress>,
+        partial_output: Option<TaskOutput>,
+    ) -> Result<(), FlameError> {
+        trace_fn!("Controller::report_task");
+        let exe_ptr = self.storage.get_executor_ptr(id.clone())?;
+        {
+            let exe = lock_ptr!(exe_ptr)?;
+            if exe.ssn_id != Some(gid.ssn_id) || !exe.task_ids.contains(&gid.task_id) {
+                return Err(FlameError::InvalidState(format!(
+                    "task <{gid}> is not running in executor <{id}>"
+                )));
+            }
+        }
+
+        self.storage.report_task(gid, progress, partial_output)
+    }
+
     pub async fn unbind_executor(&self, id: ExecutorID) -> Result<(), FlameError> {
         let exe_ptr = self.storage.get_executor_ptr(id)?;
         let state = states::from(self.storage.clone(), exe_ptr)?;
         state.unbind_executor().await?;
+        self.storage.notify_executors();
 
         Ok(())
     }
@@ -254,6 +403,41 @@ impl Controller {
         let state = states::from(self.storage.clone(), exe_ptr)?;
 
         state.unbind_executor_completed().await?;
+        self.storage.notify_executors();
+
+        Ok(())
+    }
+
+    /// Unregister the executor, e.g. it crashed or its node is shutting down.
+    pub async fn unregister_executor(&self, id: ExecutorID) -> Result<(), FlameError> {
+        trace_fn!("Controller::unregister_executor");
+        let exe_ptr = self.storage.get_executor_ptr(id)?;
+        let state = states::from(self.storage.clone(), exe_ptr)?;
+
+        state.unregister_executor().await?;
+        self.storage.notify_executors();
+
+        Ok(())
+    }
+
+    pub async fn release_executor(&self, id: ExecutorID) -> Result<(), FlameError> {
+        trace_fn!("Controller::release_executor");
+        let exe_ptr = self.storage.get_executor_ptr(id)?;
+        let state = states::from(self.storage.clone(), exe_ptr)?;
+
+        state.release_executor().await?;
+        self.storage.notify_executors();
+
+        Ok(())
+    }
+
+    pub async fn release_executor_completed(&self, id: ExecutorID) -> Result<(), FlameError> {
+        trace_fn!("Controller::release_executor_completed");
+        let exe_ptr = self.storage.get_executor_ptr(id)?;
+        let state = states::from(self.storage.clone(), exe_ptr)?;
+
+        state.release_executor_completed().await?;
+        self.storage.notify_executors();
 
         Ok(())
     }
@@ -298,31 +482,327 @@ impl Future for WatchTaskFuture {
     }
 }
 
-struct WaitForSsnFuture {
-    executor: ExecutorPtr,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Assignment;
+    use crate::storage::tests::new_storage;
 
-impl WaitForSsnFuture {
-    pub fn new(exe_ptr: &ExecutorPtr) -> Self {
-        Self {
-            executor: exe_ptr.clone(),
+    /// Create an executor in the node with the state, and bind it to the session if any.
+    fn new_executor(
+        storage: &StoragePtr,
+        node: &str,
+        ssn_id: SessionID,
+        state: ExecutorState,
+    ) -> Result<ExecutorID, FlameError> {
+        let exe = tokio_test::block_on(storage.create_executor(node.to_string(), ssn_id))?;
+        let exe_ptr = storage.get_executor_ptr(exe.id.clone())?;
+        let mut exe = lock_ptr!(exe_ptr)?;
+        exe.state = state;
+        if state == ExecutorState::Bound {
+            exe.ssn_id = Some(ssn_id);
         }
+
+        Ok(exe.id.clone())
     }
-}
 
-impl Future for WaitForSsnFuture {
-    type Output = Result<SessionID, FlameError>;
+    /// The tasks launched by the assignments.
+    fn launched(assignments: &[Assignment]) -> Vec<TaskID> {
+        assignments
+            .iter()
+            .flat_map(|a| match a {
+                Assignment::Launch(_, tasks) => tasks.iter().map(|t| t.id).collect(),
+                _ => vec![],
+            })
+            .collect()
+    }
 
-    fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
-        let exe = lock_ptr!(self.executor)?;
+    fn register_node(controller: &ControllerPtr, name: &str) -> Result<(), FlameError> {
+        let node = Node {
+            name: name.to_string(),
+            ..Node::default()
+        };
+        tokio_test::block_on(controller.register_node(&node))
+    }
+
+    #[test]
+    fn test_launch_task_idempotent() -> Result<(), FlameError> {
+        let storage = new_storage("launch_task")?;
+        let controller = new_ptr(storage.clone());
+        register_node(&controller, "node-1")?;
 
-        match exe.ssn_id {
-            None => {
-                // No bound session, trigger waker.
-                ctx.waker().wake_by_ref();
-                Poll::Pending
+        let ssn = tokio_test::block_on(controller.create_session("flmexec".to_string(), 1, None))?;
+        for _ in 0..5 {
+            tokio_test::block_on(controller.create_task(ssn.id, None))?;
+        }
+        let exe = new_executor(&storage, "node-1", ssn.id, ExecutorState::Bound)?;
+
+        // The executor runs one task and prefetches three ones.
+        let mut assigner = Assigner::new("node-1");
+        let assignments = tokio_test::block_on(controller.assign(&mut assigner))?;
+        assert!(matches!(&assignments[0], Assignment::Executors(_, e) if e.len() == 1));
+        let launched = launched(&assignments);
+        assert_eq!(launched.len(), 4);
+
+        // Nothing is pushed again by the same stream.
+        let assignments = tokio_test::block_on(controller.assign(&mut assigner))?;
+        assert!(assignments.is_empty());
+
+        // The node reconnected, e.g. the stream was broken; the same tasks are pushed again.
+        let mut assigner = Assigner::new("node-1");
+        let assignments = tokio_test::block_on(controller.assign(&mut assigner))?;
+        assert_eq!(self::launched(&assignments), launched);
+
+        // The completion is retried by the executor; the second one is skipped.
+        for _ in 0..2 {
+            let completed =
+                controller.complete_task(exe.clone(), launched[0], None, TaskState::Succeed);
+            tokio_test::block_on(completed)?;
+        }
+        assert_eq!(
+            controller.get_task(ssn.id, launched[0])?.state,
+            TaskState::Succeed
+        );
+
+        // Only the new task is pushed to top up the executor.
+        let assignments = tokio_test::block_on(controller.assign(&mut assigner))?;
+        let topped = self::launched(&assignments);
+        assert_eq!(topped.len(), 1);
+        assert!(!launched.contains(&topped[0]));
+
+        let tasks = controller.list_task(ssn.id)?;
+        let running = tasks
+            .iter()
+            .filter(|t| t.state == TaskState::Running)
+            .count();
+        assert_eq!(running, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_assign_executors() -> Result<(), FlameError> {
+        let storage = new_storage("assign_executors")?;
+        let controller = new_ptr(storage.clone());
+        register_node(&controller, "node-1")?;
+
+        let attr = ApplicationAttributes {
+            delay_release: Duration::seconds(0),
+            ..ApplicationAttributes::default()
+        };
+        tokio_test::block_on(controller.register_application("nodelay".to_string(), attr))?;
+        let ssn = tokio_test::block_on(controller.create_session("nodelay".to_string(), 1, None))?;
+
+        let idle = new_executor(&storage, "node-1", ssn.id, ExecutorState::Idle)?;
+        let bound = new_executor(&storage, "node-1", ssn.id, ExecutorState::Bound)?;
+        let releasing = new_executor(&storage, "node-1", ssn.id, ExecutorState::Idle)?;
+        tokio_test::block_on(controller.bind_session(idle.clone(), ssn.id))?;
+        tokio_test::block_on(controller.release_executor(releasing.clone()))?;
+
+        // The session of the binding executor and the releasing executor are pushed; the
+        // bound executor has nothing to do, so it's unbound at once.
+        let mut assigner = Assigner::new("node-1");
+        let assignments = tokio_test::block_on(controller.assign(&mut assigner))?;
+        assert_eq!(assignments.len(), 4);
+        assert!(assignments.iter().any(
+            |a| matches!(a, Assignment::Bind(id, app, s) if *id == idle && app.name == "nodelay" && s.id == ssn.id)
+        ));
+        assert!(assignments
+            .iter()
+            .any(|a| matches!(a, Assignment::Release(id) if *id == releasing)));
+        assert!(assignments
+            .iter()
+            .any(|a| matches!(a, Assignment::Unbind(id) if *id == bound)));
+        let exe_ptr = storage.get_executor_ptr(bound.clone())?;
+        assert_eq!(lock_ptr!(exe_ptr)?.state, ExecutorState::Unbinding);
+
+        // Nothing is pushed again until the executors are changed.
+        let assignments = tokio_test::block_on(controller.assign(&mut assigner))?;
+        assert!(assignments.is_empty());
+
+        // The released executor is removed from the node.
+        tokio_test::block_on(controller.release_executor_completed(releasing))?;
+        let assignments = tokio_test::block_on(controller.assign(&mut assigner))?;
+        assert!(matches!(&assignments[..], [Assignment::Executors(_, e)] if e.len() == 2));
+
+        // The bound executor gets the new task of its session.
+        tokio_test::block_on(controller.bind_session_completed(idle.clone()))?;
+        let task = tokio_test::block_on(controller.create_task(ssn.id, None))?;
+        let assignments = tokio_test::block_on(controller.assign(&mut assigner))?;
+        assert!(matches!(
+            &assignments[..],
+            [Assignment::Launch(id, tasks)] if *id == idle && tasks[0].id == task.id
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_drain_nodes() -> Result<(), FlameError> {
+        let storage = new_storage("drain_nodes")?;
+        let controller = new_ptr(storage.clone());
+
+        let ssn = tokio_test::block_on(controller.create_session("flmexec".to_string(), 1, None))?;
+        let task = tokio_test::block_on(controller.create_task(ssn.id, None))?;
+        for name in ["node-1", "node-2"] {
+            register_node(&controller, name)?;
+        }
+
+        // The idle executor is released, and the bound one leaves its session.
+        let idle = new_executor(&storage, "node-1", ssn.id, ExecutorState::Idle)?;
+        let bound = new_executor(&storage, "node-1", ssn.id, ExecutorState::Bound)?;
+        controller.drain_node("node-1", Duration::seconds(60))?;
+        tokio_test::block_on(controller.drain_nodes())?;
+
+        let state = |id: &ExecutorID| -> Result<ExecutorState, FlameError> {
+            let exe_ptr = storage.get_executor_ptr(id.clone())?;
+            let exe = lock_ptr!(exe_ptr)?;
+            Ok(exe.state)
+        };
+        assert_eq!(state(&idle)?, ExecutorState::Releasing);
+        assert_eq!(state(&bound)?, ExecutorState::Unbinding);
+
+        // After the timeout, the executors are released and their tasks are re-queued.
+        new_executor(&storage, "node-2", ssn.id, ExecutorState::Bound)?;
+        let assignments = tokio_test::block_on(controller.assign(&mut Assigner::new("node-2")))?;
+        assert_eq!(launched(&assignments), vec![task.id]);
+        controller.drain_node("node-2", Duration::seconds(-1))?;
+        tokio_test::block_on(controller.drain_nodes())?;
+
+        assert!(storage.list_executors("node-2")?.is_empty());
+        let task = controller.get_task(ssn.id, task.id)?;
+        assert_eq!(task.state, TaskState::Pending);
+        assert_eq!(task.retry_count, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_executor() -> Result<(), FlameError> {
+        let storage = new_storage("release_executor")?;
+        let controller = new_ptr(storage.clone());
+        let ssn = tokio_test::block_on(controller.create_session("flmexec".to_string(), 1, None))?;
+
+        let id = new_executor(&storage, "node-1", ssn.id, ExecutorState::Idle)?;
+        tokio_test::block_on(controller.release_executor(id.clone()))?;
+        let exe_ptr = storage.get_executor_ptr(id.clone())?;
+        assert_eq!(lock_ptr!(exe_ptr)?.state, ExecutorState::Releasing);
+
+        // The releasing executor is not bound to any session; and releasing it again is a no-op.
+        let res = tokio_test::block_on(controller.bind_session(id.clone(), ssn.id));
+        assert!(matches!(res, Err(FlameError::InvalidState(_))));
+        tokio_test::block_on(controller.release_executor(id.clone()))?;
+        assert_eq!(lock_ptr!(exe_ptr)?.state, ExecutorState::Releasing);
+
+        // The node stopped the executor, which is removed to free the resources of node.
+        tokio_test::block_on(controller.release_executor_completed(id.clone()))?;
+        assert_eq!(lock_ptr!(exe_ptr)?.state, ExecutorState::Released);
+        assert!(matches!(
+            storage.get_executor_ptr(id.clone()),
+            Err(FlameError::NotFound(_))
+        ));
+        let res = tokio_test::block_on(controller.release_executor_completed(id));
+        assert!(matches!(res, Err(FlameError::NotFound(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_bound_executor() -> Result<(), FlameError> {
+        let storage = new_storage("release_bound_executor")?;
+        let controller = new_ptr(storage.clone());
+        let ssn = tokio_test::block_on(controller.create_session("flmexec".to_string(), 1, None))?;
+
+        // The bound executor is unbound before released.
+        let id = new_executor(&storage, "node-1", ssn.id, ExecutorState::Bound)?;
+        let res = tokio_test::block_on(controller.release_executor(id.clone()));
+        assert!(matches!(res, Err(FlameError::InvalidState(_))));
+        let res = tokio_test::block_on(controller.release_executor_completed(id.clone()));
+        assert!(matches!(res, Err(FlameError::InvalidState(_))));
+        assert_eq!(storage.list_executors("node-1")?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unregister_bound_executor() -> Result<(), FlameError> {
+        let storage = new_storage("unregister_bound_executor")?;
+        let controller = new_ptr(storage.clone());
+        register_node(&controller, "node-1")?;
+
+        let ssn = tokio_test::block_on(controller.create_session("flmexec".to_string(), 1, None))?;
+        tokio_test::block_on(controller.create_task(ssn.id, None))?;
+        let id = new_executor(&storage, "node-1", ssn.id, ExecutorState::Bound)?;
+        let assignments = tokio_test::block_on(controller.assign(&mut Assigner::new("node-1")))?;
+        let launched = launched(&assignments);
+        assert_eq!(launched.len(), 1);
+
+        // The shim of the executor failed, its task is retried by another executor.
+        tokio_test::block_on(controller.unregister_executor(id.clone()))?;
+        assert!(storage.list_executors("node-1")?.is_empty());
+        let task = controller.get_task(ssn.id, launched[0])?;
+        assert_eq!(task.state, TaskState::Pending);
+
+        let other = new_executor(&storage, "node-1", ssn.id, ExecutorState::Bound)?;
+        let assignments = tokio_test::block_on(controller.assign(&mut Assigner::new("node-1")))?;
+        assert!(assignments.iter().any(
+            |a| matches!(a, Assignment::Launch(id, tasks) if *id == other && tasks[0].id == task.id)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_task() -> Result<(), FlameError> {
+        let storage = new_storage("report_task")?;
+        let controller = new_ptr(storage.clone());
+        register_node(&controller, "node-1")?;
+
+        let ssn = tokio_test::block_on(controller.create_session("flmexec".to_string(), 1, None))?;
+        tokio_test::block_on(controller.create_task(ssn.id, None))?;
+        let id = new_executor(&storage, "node-1", ssn.id, ExecutorState::Bound)?;
+        let assignments = tokio_test::block_on(controller.assign(&mut Assigner::new("node-1")))?;
+        let gid = TaskGID {
+            ssn_id: ssn.id,
+            task_id: launched(&assignments)[0],
+        };
+
+        // The progress and partial output are relayed to the watchers.
+        let mut events = controller.watch_tasks();
+        let progress = TaskProgress {
+            percentage: 50.0,
+            message: Some("half".to_string()),
+        };
+        let output = TaskOutput::from("chunk");
+        controller.report_task(id.clone(), gid, Some(progress), Some(output.clone()))?;
+        match events.try_recv() {
+            Ok(TaskEvent::Reported(task, partial_output)) => {
+                assert_eq!(task.gid(), gid);
+                assert_eq!(task.progress.map(|p| p.percentage), Some(50.0));
+                assert_eq!(partial_output, Some(output));
             }
-            Some(ssn_id) => Poll::Ready(Ok(ssn_id)),
+            event => panic!("unexpected event: {event:?}"),
         }
+        // The progress is kept by the task.
+        let task = controller.get_task(ssn.id, gid.task_id)?;
+        assert_eq!(
+            task.progress.and_then(|p| p.message),
+            Some("half".to_string())
+        );
+
+        // Only the executor running the task can report it.
+        let other = new_executor(&storage, "node-1", ssn.id, ExecutorState::Bound)?;
+        let res = controller.report_task(other, gid, None, None);
+        assert!(matches!(res, Err(FlameError::InvalidState(_))));
+
+        // The completed task can not be reported.
+        let completed = controller.complete_task(id.clone(), gid.task_id, None, TaskState::Succeed);
+        tokio_test::block_on(completed)?;
+        let res = controller.report_task(id, gid, None, None);
+        assert!(matches!(res, Err(FlameError::InvalidState(_))));
+        assert!(matches!(events.try_recv(), Ok(TaskEvent::StateChanged(_))));
+        assert!(events.try_recv().is_err());
+
+        Ok(())
     }
 }
@@ -11,10 +11,10 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-use crate::controller::states::States;
+use crate::controller::states::{self, States};
 use crate::model::ExecutorPtr;
 use crate::storage::StoragePtr;
-use common::apis::{ExecutorState, SessionPtr, Task, TaskOutput, TaskPtr};
+use common::apis::{ExecutorState, SessionPtr, Task, TaskOutput, TaskPtr, TaskState};
 use common::{lock_ptr, trace::TraceFn, trace_fn, FlameError};
 
 pub struct BindingState {
@@ -66,7 +66,11 @@ impl States for BindingState {
         Err(FlameError::InvalidState("Executor is binding".to_string()))
     }
 
-    async fn launch_task(&self, _ssn: SessionPtr) -> Result<Option<Task>, FlameError> {
+    async fn launch_task(
+        &self,
+        _ssn: SessionPtr,
+        _max_tasks: usize,
+    ) -> Result<Vec<Task>, FlameError> {
         trace_fn!("BindingState::launch_task");
 
         Err(FlameError::InvalidState("Executor is binding".to_string()))
@@ -77,9 +81,28 @@ impl States for BindingState {
         _ssn: SessionPtr,
         _task: TaskPtr,
         _: Option<TaskOutput>,
+        _: TaskState,
     ) -> Result<(), FlameError> {
         trace_fn!("BindingState::complete_task");
 
         Err(FlameError::InvalidState("Executor is binding".to_string()))
     }
+
+    async fn release_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("BindingState::release_executor");
+
+        Err(FlameError::InvalidState("Executor is binding".to_string()))
+    }
+
+    async fn release_executor_completed(&self) -> Result<(), FlameError> {
+        trace_fn!("BindingState::release_executor_completed");
+
+        Err(FlameError::InvalidState("Executor is binding".to_string()))
+    }
+
+    async fn unregister_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("BindingState::unregister_executor");
+
+        states::unregister(&self.storage, &self.executor).await
+    }
 }
@@ -11,16 +11,11 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-use chrono::{DateTime, Duration, Utc};
-use std::future::Future;
-use std::pin::Pin;
-use std::task::{Context, Poll};
-
 use crate::model::ExecutorPtr;
 use common::apis::{ExecutorState, SessionPtr, Task, TaskOutput, TaskPtr, TaskState};
 use common::{lock_ptr, trace::TraceFn, trace_fn, FlameError};
 
-use crate::controller::states::States;
+use crate::controller::states::{self, States};
 use crate::storage::StoragePtr;
 
 pub struct BoundState {
@@ -63,53 +58,49 @@ impl States for BoundState {
         Err(FlameError::InvalidState("Executor is bound".to_string()))
     }
 
-    async fn launch_task(&self, ssn_ptr: SessionPtr) -> Result<Option<Task>, FlameError> {
+    async fn launch_task(
+        &self,
+        ssn_ptr: SessionPtr,
+        max_tasks: usize,
+    ) -> Result<Vec<Task>, FlameError> {
         trace_fn!("BoundState::launch_task");
 
-        let app_name = {
-            let ssn = lock_ptr!(ssn_ptr)?;
-            ssn.application.clone()
-        };
-
-        let app_ptr = self.storage.get_application(app_name).await?;
-
-        let task_ptr = WaitForTaskFuture::new(&ssn_ptr, app_ptr.delay_release).await?;
+        let mut task_ptrs = vec![];
+        while task_ptrs.len() < max_tasks {
+            let task_ptr = {
+                let mut ssn = lock_ptr!(ssn_ptr)?;
+                ssn.pop_pending_task()
+            };
 
-        let task_ptr = {
             match task_ptr {
-                Some(task_ptr) => {
-                    self.storage
-                        .update_task(ssn_ptr.clone(), task_ptr.clone(), TaskState::Running, None)
-                        .await?;
-                    Some(task_ptr)
-                }
-                None => None,
+                Some(task_ptr) => task_ptrs.push(task_ptr),
+                None => break,
             }
-        };
-
-        // No pending task, return.
-        if task_ptr.is_none() {
-            return Ok(None);
         }
 
-        // let task_ptr = task_ptr.unwrap();
-        let (ssn_id, task_id) = {
-            let task_ptr = task_ptr.clone().unwrap();
-            let task = lock_ptr!(task_ptr)?;
-            (task.ssn_id, task.id)
-        };
+        let mut tasks = vec![];
+        for task_ptr in task_ptrs {
+            self.storage
+                .update_task(ssn_ptr.clone(), task_ptr.clone(), TaskState::Running, None)
+                .await?;
 
-        log::debug!("Launching task <{}/{}>", ssn_id.clone(), task_id.clone());
+            let task = {
+                let task = lock_ptr!(task_ptr)?;
+                (*task).clone()
+            };
 
-        {
-            let mut e = lock_ptr!(self.executor)?;
-            e.task_id = Some(task_id);
-            e.ssn_id = Some(ssn_id);
-        };
+            log::debug!("Launching task <{}>", task.gid());
+
+            {
+                let mut e = lock_ptr!(self.executor)?;
+                e.task_ids.push(task.id);
+                e.ssn_id = Some(task.ssn_id);
+            };
+
+            tasks.push(task);
+        }
 
-        let task_ptr = task_ptr.unwrap();
-        let task = lock_ptr!(task_ptr)?;
-        Ok(Some((*task).clone()))
+        Ok(tasks)
     }
 
     async fn complete_task(
@@ -117,57 +108,42 @@ impl States for BoundState {
         ssn_ptr: SessionPtr,
         task_ptr: TaskPtr,
         task_output: Option<TaskOutput>,
+        task_state: TaskState,
     ) -> Result<(), FlameError> {
         trace_fn!("BoundState::complete_task");
 
+        let task_id = {
+            let task = lock_ptr!(task_ptr)?;
+            task.id
+        };
+
         self.storage
-            .update_task(ssn_ptr, task_ptr, TaskState::Succeed, task_output)
+            .update_task(ssn_ptr, task_ptr, task_state, task_output)
             .await?;
 
         {
             let mut e = lock_ptr!(self.executor)?;
-            e.task_id = None;
+            e.task_ids.retain(|id| *id != task_id);
         };
 
         Ok(())
     }
-}
 
-struct WaitForTaskFuture {
-    ssn: SessionPtr,
-    delay_release: Duration,
-    start_time: DateTime<Utc>,
-}
+    async fn release_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("BoundState::release_executor");
 
-impl WaitForTaskFuture {
-    pub fn new(ssn: &SessionPtr, delay_release: Duration) -> Self {
-        Self {
-            ssn: ssn.clone(),
-            delay_release,
-            start_time: Utc::now(),
-        }
+        Err(FlameError::InvalidState("Executor is bound".to_string()))
     }
-}
 
-impl Future for WaitForTaskFuture {
-    type Output = Result<Option<TaskPtr>, FlameError>;
-
-    fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
-        let mut ssn = lock_ptr!(self.ssn)?;
-
-        match ssn.pop_pending_task() {
-            None => {
-                let now = Utc::now();
-                if now - self.start_time > self.delay_release {
-                    // If the delay release is reached, return None.
-                    Poll::Ready(Ok(None))
-                } else {
-                    // If the delay release is not reached, wait for the next poll.
-                    ctx.waker().wake_by_ref();
-                    Poll::Pending
-                }
-            }
-            Some(task_ptr) => Poll::Ready(Ok(Some(task_ptr))),
-        }
+    async fn release_executor_completed(&self) -> Result<(), FlameError> {
+        trace_fn!("BoundState::release_executor_completed");
+
+        Err(FlameError::InvalidState("Executor is bound".to_string()))
+    }
+
+    async fn unregister_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("BoundState::unregister_executor");
+
+        states::unregister(&self.storage, &self.executor).await
     }
 }
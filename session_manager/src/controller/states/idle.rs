@@ -11,11 +11,11 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-use crate::controller::states::States;
+use crate::controller::states::{self, States};
 use crate::model::ExecutorPtr;
 use crate::storage::StoragePtr;
 
-use common::apis::{ExecutorState, SessionPtr, Task, TaskOutput, TaskPtr};
+use common::apis::{ExecutorState, SessionPtr, Task, TaskOutput, TaskPtr, TaskState};
 use common::{lock_ptr, trace::TraceFn, trace_fn, FlameError};
 
 pub struct IdleState {
@@ -42,6 +42,7 @@ impl States for IdleState {
         let mut e = lock_ptr!(self.executor)?;
         e.ssn_id = Some(ssn_id);
         e.state = ExecutorState::Binding;
+        e.idle_since = None;
 
         Ok(())
     }
@@ -64,7 +65,11 @@ impl States for IdleState {
         Err(FlameError::InvalidState("Executor is idle".to_string()))
     }
 
-    async fn launch_task(&self, _ssn: SessionPtr) -> Result<Option<Task>, FlameError> {
+    async fn launch_task(
+        &self,
+        _ssn: SessionPtr,
+        _max_tasks: usize,
+    ) -> Result<Vec<Task>, FlameError> {
         trace_fn!("IdleState::launch_task");
 
         Err(FlameError::InvalidState("Executor is idle".to_string()))
@@ -75,9 +80,32 @@ impl States for IdleState {
         _ssn: SessionPtr,
         _task: TaskPtr,
         _: Option<TaskOutput>,
+        _: TaskState,
     ) -> Result<(), FlameError> {
         trace_fn!("IdleState::complete_task");
 
         Err(FlameError::InvalidState("Executor is idle".to_string()))
     }
+
+    async fn release_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("IdleState::release_executor");
+
+        let mut e = lock_ptr!(self.executor)?;
+        e.state = ExecutorState::Releasing;
+        e.idle_since = None;
+
+        Ok(())
+    }
+
+    async fn release_executor_completed(&self) -> Result<(), FlameError> {
+        trace_fn!("IdleState::release_executor_completed");
+
+        Err(FlameError::InvalidState("Executor is idle".to_string()))
+    }
+
+    async fn unregister_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("IdleState::unregister_executor");
+
+        states::unregister(&self.storage, &self.executor).await
+    }
 }
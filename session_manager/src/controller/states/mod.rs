@@ -14,18 +14,20 @@ limitations under the License.
 use std::sync::Arc;
 
 use crate::controller::states::{
-    binding::BindingState, bound::BoundState, idle::IdleState, unbinding::UnbindingState,
-    void::VoidState,
+    binding::BindingState, bound::BoundState, idle::IdleState, released::ReleasedState,
+    releasing::ReleasingState, unbinding::UnbindingState, void::VoidState,
 };
 use crate::storage::StoragePtr;
 
 use crate::model::ExecutorPtr;
-use common::apis::{ExecutorState, SessionPtr, Task, TaskOutput, TaskPtr};
+use common::apis::{ExecutorState, SessionPtr, Task, TaskGID, TaskOutput, TaskPtr, TaskState};
 use common::{lock_ptr, FlameError};
 
 mod binding;
 mod bound;
 mod idle;
+mod released;
+mod releasing;
 mod unbinding;
 mod void;
 
@@ -55,12 +57,14 @@ pub fn from(storage: StoragePtr, exe_ptr: ExecutorPtr) -> Result<Arc<dyn States>
             executor: exe_ptr.clone(),
         })),
         ExecutorState::Unknown => Err(FlameError::InvalidState("Executor is unknown".to_string())),
-        ExecutorState::Releasing => Err(FlameError::InvalidState(
-            "Executor is releasing".to_string(),
-        )),
-        ExecutorState::Released => {
-            Err(FlameError::InvalidState("Executor is released".to_string()))
-        }
+        ExecutorState::Releasing => Ok(Arc::new(ReleasingState {
+            storage,
+            executor: exe_ptr.clone(),
+        })),
+        ExecutorState::Released => Ok(Arc::new(ReleasedState {
+            storage,
+            executor: exe_ptr.clone(),
+        })),
     }
 }
 
@@ -74,11 +78,47 @@ pub trait States: Send + Sync + 'static {
     async fn unbind_executor(&self) -> Result<(), FlameError>;
     async fn unbind_executor_completed(&self) -> Result<(), FlameError>;
 
-    async fn launch_task(&self, ssn: SessionPtr) -> Result<Option<Task>, FlameError>;
+    async fn release_executor(&self) -> Result<(), FlameError>;
+    async fn release_executor_completed(&self) -> Result<(), FlameError>;
+
+    async fn unregister_executor(&self) -> Result<(), FlameError>;
+
+    async fn launch_task(&self, ssn: SessionPtr, max_tasks: usize)
+        -> Result<Vec<Task>, FlameError>;
     async fn complete_task(
         &self,
         ssn: SessionPtr,
         task: TaskPtr,
         task_output: Option<TaskOutput>,
+        task_state: TaskState,
     ) -> Result<(), FlameError>;
 }
+
+/// Remove the executor from storage, and re-queue or fail its in-flight tasks if any.
+async fn unregister(storage: &StoragePtr, exe_ptr: &ExecutorPtr) -> Result<(), FlameError> {
+    let (id, gids) = {
+        let mut e = lock_ptr!(exe_ptr)?;
+        e.state = ExecutorState::Released;
+        let gids: Vec<TaskGID> = match e.ssn_id {
+            Some(ssn_id) => e
+                .task_ids
+                .drain(..)
+                .map(|task_id| TaskGID { ssn_id, task_id })
+                .collect(),
+            None => vec![],
+        };
+        (e.id.clone(), gids)
+    };
+
+    storage.release_executor(&id)?;
+
+    for gid in gids {
+        let task = storage.retry_task(gid).await?;
+        log::info!(
+            "Task <{gid}> of executor <{id}> is {} after executor unregistered.",
+            task.state
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+/*
+Copyright 2023 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use crate::controller::states::States;
+use crate::model::ExecutorPtr;
+use crate::storage::StoragePtr;
+use common::apis::{SessionPtr, Task, TaskOutput, TaskPtr, TaskState};
+use common::{trace::TraceFn, trace_fn, FlameError};
+
+pub struct ReleasedState {
+    pub storage: StoragePtr,
+    pub executor: ExecutorPtr,
+}
+
+#[async_trait::async_trait]
+impl States for ReleasedState {
+    async fn register_executor(&self, _exe: ExecutorPtr) -> Result<(), FlameError> {
+        trace_fn!("ReleasedState::register_executor");
+
+        Err(FlameError::InvalidState("Executor is released".to_string()))
+    }
+
+    async fn bind_session(&self, _ssn_ptr: SessionPtr) -> Result<(), FlameError> {
+        trace_fn!("ReleasedState::bind_session");
+
+        Err(FlameError::InvalidState("Executor is released".to_string()))
+    }
+
+    async fn bind_session_completed(&self) -> Result<(), FlameError> {
+        trace_fn!("ReleasedState::bind_session_completed");
+
+        Err(FlameError::InvalidState("Executor is released".to_string()))
+    }
+
+    async fn unbind_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("ReleasedState::unbind_executor");
+
+        Err(FlameError::InvalidState("Executor is released".to_string()))
+    }
+
+    async fn unbind_executor_completed(&self) -> Result<(), FlameError> {
+        trace_fn!("ReleasedState::unbind_executor_completed");
+
+        Err(FlameError::InvalidState("Executor is released".to_string()))
+    }
+
+    async fn launch_task(
+        &self,
+        _ssn: SessionPtr,
+        _max_tasks: usize,
+    ) -> Result<Vec<Task>, FlameError> {
+        trace_fn!("ReleasedState::launch_task");
+
+        Err(FlameError::InvalidState("Executor is released".to_string()))
+    }
+
+    async fn complete_task(
+        &self,
+        _ssn: SessionPtr,
+        _task: TaskPtr,
+        _: Option<TaskOutput>,
+        _: TaskState,
+    ) -> Result<(), FlameError> {
+        trace_fn!("ReleasedState::complete_task");
+
+        Err(FlameError::InvalidState("Executor is released".to_string()))
+    }
+
+    async fn release_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("ReleasedState::release_executor");
+
+        Ok(())
+    }
+
+    async fn release_executor_completed(&self) -> Result<(), FlameError> {
+        trace_fn!("ReleasedState::release_executor_completed");
+
+        Ok(())
+    }
+
+    async fn unregister_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("ReleasedState::unregister_executor");
+
+        Ok(())
+    }
+}
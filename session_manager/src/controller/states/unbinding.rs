@@ -11,7 +11,9 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-use crate::controller::states::States;
+use chrono::Utc;
+
+use crate::controller::states::{self, States};
 use crate::storage::StoragePtr;
 
 use crate::model::ExecutorPtr;
@@ -64,17 +66,21 @@ impl States for UnbindingState {
         let mut e = lock_ptr!(self.executor)?;
         e.state = ExecutorState::Idle;
         e.ssn_id = None;
-        e.task_id = None;
+        e.task_ids.clear();
+        e.idle_since = Some(Utc::now());
 
         Ok(())
     }
 
-    async fn launch_task(&self, _ssn: SessionPtr) -> Result<Option<Task>, FlameError> {
+    async fn launch_task(
+        &self,
+        _ssn: SessionPtr,
+        _max_tasks: usize,
+    ) -> Result<Vec<Task>, FlameError> {
         trace_fn!("UnbindingState::launch_task");
 
-        Err(FlameError::InvalidState(
-            "Executor is unbinding".to_string(),
-        ))
+        // No more task for the unbinding executor, so it'll start to unbind.
+        Ok(vec![])
     }
 
     async fn complete_task(
@@ -82,18 +88,46 @@ impl States for UnbindingState {
         ssn_ptr: SessionPtr,
         task_ptr: TaskPtr,
         task_output: Option<TaskOutput>,
+        task_state: TaskState,
     ) -> Result<(), FlameError> {
         trace_fn!("UnbindingState::complete_task");
 
+        let task_id = {
+            let task = lock_ptr!(task_ptr)?;
+            task.id
+        };
+
         self.storage
-            .update_task(ssn_ptr, task_ptr, TaskState::Succeed, task_output.clone())
+            .update_task(ssn_ptr, task_ptr, task_state, task_output.clone())
             .await?;
 
         {
             let mut e = lock_ptr!(self.executor)?;
-            e.task_id = None;
+            e.task_ids.retain(|id| *id != task_id);
         };
 
         Ok(())
     }
+
+    async fn release_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("UnbindingState::release_executor");
+
+        Err(FlameError::InvalidState(
+            "Executor is unbinding".to_string(),
+        ))
+    }
+
+    async fn release_executor_completed(&self) -> Result<(), FlameError> {
+        trace_fn!("UnbindingState::release_executor_completed");
+
+        Err(FlameError::InvalidState(
+            "Executor is unbinding".to_string(),
+        ))
+    }
+
+    async fn unregister_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("UnbindingState::unregister_executor");
+
+        states::unregister(&self.storage, &self.executor).await
+    }
 }
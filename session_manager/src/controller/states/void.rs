@@ -11,7 +11,9 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-use crate::controller::states::States;
+use chrono::Utc;
+
+use crate::controller::states::{self, States};
 use crate::storage::StoragePtr;
 
 use crate::model::ExecutorPtr;
@@ -36,6 +38,7 @@ impl States for VoidState {
             return Err(FlameError::InvalidState("Executor ID mismatch".to_string()));
         }
         e.state = ExecutorState::Idle;
+        e.idle_since = Some(Utc::now());
 
         Ok(())
     }
@@ -64,7 +67,11 @@ impl States for VoidState {
         Err(FlameError::InvalidState("Executor is void".to_string()))
     }
 
-    async fn launch_task(&self, _ssn: SessionPtr) -> Result<Option<Task>, FlameError> {
+    async fn launch_task(
+        &self,
+        _ssn: SessionPtr,
+        _max_tasks: usize,
+    ) -> Result<Vec<Task>, FlameError> {
         trace_fn!("VoidState::launch_task");
 
         Err(FlameError::InvalidState("Executor is void".to_string()))
@@ -75,9 +82,36 @@ impl States for VoidState {
         ssn_ptr: SessionPtr,
         task_ptr: TaskPtr,
         task_output: Option<TaskOutput>,
+        _: TaskState,
     ) -> Result<(), FlameError> {
         trace_fn!("VoidState::complete_task");
 
         Err(FlameError::InvalidState("Executor is void".to_string()))
     }
+
+    async fn release_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("VoidState::release_executor");
+
+        // The executor is not started by the node yet, release it directly.
+        let id = {
+            let mut e = lock_ptr!(self.executor)?;
+            e.state = ExecutorState::Released;
+            e.id.clone()
+        };
+        self.storage.release_executor(&id)?;
+
+        Ok(())
+    }
+
+    async fn release_executor_completed(&self) -> Result<(), FlameError> {
+        trace_fn!("VoidState::release_executor_completed");
+
+        Err(FlameError::InvalidState("Executor is void".to_string()))
+    }
+
+    async fn unregister_executor(&self) -> Result<(), FlameError> {
+        trace_fn!("VoidState::unregister_executor");
+
+        states::unregister(&self.storage, &self.executor).await
+    }
 }
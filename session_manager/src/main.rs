@@ -28,6 +28,7 @@ use common::FlameError;
 mod apiserver;
 mod controller;
 mod model;
+mod monitor;
 mod scheduler;
 mod storage;
 
@@ -81,6 +82,17 @@ async fn main() -> Result<(), FlameError> {
         handlers.push(handler);
     }
 
+    // Start node monitor thread.
+    {
+        let controller = controller.clone();
+        let ctx = ctx.clone();
+        let handler = tokio::spawn(async move {
+            let monitor = monitor::new(controller);
+            monitor.run(ctx).await
+        });
+        handlers.push(handler);
+    }
+
     log::info!("flame-session-manager started.");
 
     // Register default applications.
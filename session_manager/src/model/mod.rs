@@ -20,7 +20,7 @@ use rpc::flame as rpc;
 
 use common::apis::{
     Application, ExecutorID, ExecutorState, Node, NodeState, ResourceRequirement, Session,
-    SessionID, SessionState, Task, TaskID, TaskState,
+    SessionID, SessionState, Task, TaskID, TaskOutput, TaskState,
 };
 use common::ptr::MutexPtr;
 use common::{lock_ptr, FlameError};
@@ -107,10 +107,11 @@ pub struct ExecutorInfo {
     pub id: ExecutorID,
     pub node: String,
     pub resreq: ResourceRequirement,
-    pub task_id: Option<TaskID>,
+    pub task_ids: Vec<TaskID>,
     pub ssn_id: Option<SessionID>,
 
     pub creation_time: DateTime<Utc>,
+    pub idle_since: Option<DateTime<Utc>>,
     pub state: ExecutorState,
 }
 
@@ -119,6 +120,8 @@ pub struct NodeInfo {
     pub name: String,
     pub allocatable: ResourceRequirement,
     pub state: NodeState,
+    pub unschedulable: bool,
+    pub draining: bool,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -126,6 +129,23 @@ pub struct AppInfo {
     pub name: String,
     pub max_instances: i32,
     pub delay_release: Duration,
+    pub concurrency: i32,
+}
+
+impl AppInfo {
+    /// The slots desired by the session: each executor runs up to `concurrency` tasks of
+    /// the session at a time, and the application runs up to `max_instances` executors.
+    pub fn desired_slots(&self, ssn: &SessionInfo) -> f64 {
+        let mut tasks = 0.0;
+        for state in [TaskState::Pending, TaskState::Running] {
+            if let Some(d) = ssn.tasks_status.get(&state) {
+                tasks += *d as f64;
+            }
+        }
+
+        let executors = (tasks / self.concurrency.max(1) as f64).ceil();
+        (executors * ssn.slots as f64).min((self.max_instances * ssn.slots) as f64)
+    }
 }
 
 impl From<Application> for AppInfo {
@@ -140,6 +160,8 @@ impl From<&Node> for NodeInfo {
             name: node.name.clone(),
             allocatable: node.allocatable.clone(),
             state: node.state,
+            unschedulable: node.unschedulable,
+            draining: node.draining,
         }
     }
 }
@@ -150,6 +172,7 @@ impl From<&Application> for AppInfo {
             name: app.name.to_string(),
             max_instances: app.max_instances,
             delay_release: app.delay_release,
+            concurrency: app.concurrency,
         }
     }
 }
@@ -160,9 +183,10 @@ impl From<&Executor> for ExecutorInfo {
             id: exec.id.clone(),
             node: exec.node.clone(),
             resreq: exec.resreq.clone(),
-            task_id: exec.task_id,
+            task_ids: exec.task_ids.clone(),
             ssn_id: exec.ssn_id,
             creation_time: exec.creation_time,
+            idle_since: exec.idle_since,
             state: exec.state,
         }
     }
@@ -223,6 +247,11 @@ pub struct NodeFilter {
 
 pub const ALL_NODE: Option<NodeFilter> = None;
 
+pub const READY_NODE: Option<NodeFilter> = Some(NodeFilter {
+    state: Some(NodeState::Ready),
+    names: vec![],
+});
+
 pub const IDLE_EXECUTOR: Option<ExecutorFilter> = Some(ExecutorFilter {
     state: Some(ExecutorState::Idle),
     ids: vec![],
@@ -268,6 +297,14 @@ impl SnapShot {
                     log::warn!("Node <{name}> not found.");
                 }
             }
+
+            if let Some(state) = filter.state {
+                for node in nodes_list.values() {
+                    if node.state == state {
+                        nodes.insert(node.name.clone(), node.clone());
+                    }
+                }
+            }
         }
 
         Ok(nodes)
@@ -287,6 +324,11 @@ impl SnapShot {
         Ok(nodes)
     }
 
+    pub fn is_node_draining(&self, name: &str) -> Result<bool, FlameError> {
+        let nodes = lock_ptr!(self.nodes)?;
+        Ok(nodes.get(name).map(|n| n.draining).unwrap_or(false))
+    }
+
     pub fn find_applications(
         &self,
         filter: Option<AppFilter>,
@@ -547,9 +589,10 @@ impl SnapShot {
             id: exec.id.clone(),
             node: exec.node.clone(),
             resreq: exec.resreq.clone(),
-            task_id: exec.task_id,
+            task_ids: exec.task_ids.clone(),
             ssn_id: exec.ssn_id,
             creation_time: exec.creation_time,
+            idle_since: exec.idle_since,
             state,
         });
 
@@ -565,10 +608,12 @@ pub struct Executor {
     pub id: ExecutorID,
     pub node: String,
     pub resreq: ResourceRequirement,
-    pub task_id: Option<TaskID>,
+    /// The tasks launched to the executor and not completed yet, in launch order.
+    pub task_ids: Vec<TaskID>,
     pub ssn_id: Option<SessionID>,
 
     pub creation_time: DateTime<Utc>,
+    pub idle_since: Option<DateTime<Utc>>,
     pub state: ExecutorState,
 }
 
@@ -583,7 +628,7 @@ impl From<rpc::Executor> for Executor {
 impl From<&rpc::Executor> for Executor {
     fn from(e: &rpc::Executor) -> Self {
         let spec = e.spec.clone().unwrap();
-        let status = e.status.unwrap();
+        let status = e.status.clone().unwrap();
         let metadata = e.metadata.clone().unwrap();
 
         let state = rpc::ExecutorState::try_from(status.state).unwrap().into();
@@ -592,14 +637,98 @@ impl From<&rpc::Executor> for Executor {
             id: metadata.id.clone(),
             node: spec.node.clone(),
             resreq: spec.resreq.unwrap().into(),
-            task_id: None,
-            ssn_id: None,
+            task_ids: status
+                .task_ids
+                .iter()
+                .filter_map(|id| id.parse::<TaskID>().ok())
+                .collect(),
+            ssn_id: status
+                .session_id
+                .and_then(|id| id.parse::<SessionID>().ok()),
             creation_time: Utc::now(),
+            idle_since: None,
             state,
         }
     }
 }
 
+/// The event of a task, which is broadcast to the clients watching the task or its session.
+#[derive(Clone, Debug)]
+pub enum TaskEvent {
+    /// The state of the task was changed, e.g. it was created, launched or completed.
+    StateChanged(Task),
+    /// The running task reported its progress, or emitted a chunk of its output.
+    Reported(Task, Option<TaskOutput>),
+}
+
+impl TaskEvent {
+    pub fn task(&self) -> &Task {
+        match self {
+            TaskEvent::StateChanged(task) => task,
+            TaskEvent::Reported(task, _) => task,
+        }
+    }
+}
+
+impl From<&TaskEvent> for rpc::Task {
+    fn from(event: &TaskEvent) -> Self {
+        match event {
+            TaskEvent::StateChanged(task) => rpc::Task::from(task),
+            TaskEvent::Reported(task, partial_output) => rpc::Task {
+                partial_output: partial_output.clone().map(TaskOutput::into),
+                ..rpc::Task::from(task)
+            },
+        }
+    }
+}
+
+/// The work assigned to the executors of a node, which is pushed to the node by its stream.
+#[derive(Clone, Debug)]
+pub enum Assignment {
+    /// The executors of the node were changed, e.g. created or released.
+    Executors(Node, Vec<Executor>),
+    /// Bind the executor to the session of the application.
+    Bind(ExecutorID, Box<Application>, Session),
+    /// Unbind the executor from its session, after its tasks completed.
+    Unbind(ExecutorID),
+    /// Release the idle executor.
+    Release(ExecutorID),
+    /// Launch the tasks to the bound executor.
+    Launch(ExecutorID, Vec<Task>),
+}
+
+impl From<Assignment> for rpc::node_response::Response {
+    fn from(assignment: Assignment) -> Self {
+        use rpc::node_response::Response;
+
+        match assignment {
+            Assignment::Executors(node, executors) => Response::SyncNode(rpc::SyncNodeResponse {
+                node: Some(node.into()),
+                executors: executors.into_iter().map(rpc::Executor::from).collect(),
+            }),
+            Assignment::Bind(executor_id, app, ssn) => {
+                Response::BindExecutor(rpc::BindExecutorResponse {
+                    application: Some(rpc::Application::from(app.as_ref())),
+                    session: Some(rpc::Session::from(&ssn)),
+                    executor_id,
+                })
+            }
+            Assignment::Unbind(executor_id) => {
+                Response::UnbindExecutor(rpc::UnbindExecutorResponse { executor_id })
+            }
+            Assignment::Release(executor_id) => {
+                Response::ReleaseExecutor(rpc::ReleaseExecutorResponse { executor_id })
+            }
+            Assignment::Launch(executor_id, tasks) => {
+                Response::LaunchTask(rpc::LaunchTaskResponse {
+                    tasks: tasks.iter().map(rpc::Task::from).collect(),
+                    executor_id,
+                })
+            }
+        }
+    }
+}
+
 impl From<Executor> for rpc::Executor {
     fn from(e: Executor) -> Self {
         rpc::Executor::from(&e)
@@ -621,6 +750,8 @@ impl From<&Executor> for rpc::Executor {
 
         let status = Some(rpc::ExecutorStatus {
             state: rpc::ExecutorState::from(e.state).into(),
+            session_id: e.ssn_id.map(|id| id.to_string()),
+            task_ids: e.task_ids.iter().map(|id| id.to_string()).collect(),
         });
 
         rpc::Executor {
@@ -630,3 +761,39 @@ impl From<&Executor> for rpc::Executor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_desired_slots() {
+        let app = |concurrency, max_instances| AppInfo {
+            name: "flmexec".to_string(),
+            max_instances,
+            delay_release: Duration::seconds(0),
+            concurrency,
+        };
+        let ssn = |slots, pending, running, succeed| SessionInfo {
+            slots,
+            tasks_status: HashMap::from([
+                (TaskState::Pending, pending),
+                (TaskState::Running, running),
+                (TaskState::Succeed, succeed),
+            ]),
+            ..SessionInfo::default()
+        };
+
+        // One executor for each task by default; the completed tasks are not counted.
+        assert_eq!(app(1, 100).desired_slots(&ssn(1, 3, 2, 10)), 5.0);
+        // The concurrency is at least one.
+        assert_eq!(app(0, 100).desired_slots(&ssn(1, 3, 2, 10)), 5.0);
+        // An executor runs up to `concurrency` tasks, rounding up.
+        assert_eq!(app(4, 100).desired_slots(&ssn(1, 3, 2, 0)), 2.0);
+        assert_eq!(app(4, 100).desired_slots(&ssn(2, 8, 0, 0)), 4.0);
+        // Up to `max_instances` executors.
+        assert_eq!(app(2, 3).desired_slots(&ssn(2, 20, 0, 0)), 6.0);
+        // No executor without tasks.
+        assert_eq!(app(4, 100).desired_slots(&ssn(1, 0, 0, 5)), 0.0);
+    }
+}
@@ -0,0 +1,51 @@
+/*
+Copyright 2023 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time;
+
+use crate::controller::ControllerPtr;
+
+use crate::FlameThread;
+use common::ctx::FlameContext;
+use common::FlameError;
+
+const MONITOR_INTERVAL: u64 = 1000;
+
+pub fn new(controller: ControllerPtr) -> Arc<dyn FlameThread> {
+    Arc::new(NodeMonitor { controller })
+}
+
+/// NodeMonitor checks the heartbeat of nodes periodically, and releases
+/// the executors of the nodes which are NotReady or draining.
+struct NodeMonitor {
+    controller: ControllerPtr,
+}
+
+#[async_trait]
+impl FlameThread for NodeMonitor {
+    async fn run(&self, _flame_ctx: FlameContext) -> Result<(), FlameError> {
+        loop {
+            if let Err(e) = self.controller.check_nodes().await {
+                log::error!("Failed to check nodes: {e}");
+            }
+
+            if let Err(e) = self.controller.drain_nodes().await {
+                log::error!("Failed to drain nodes: {e}");
+            }
+
+            tokio::time::sleep(time::Duration::from_millis(MONITOR_INTERVAL)).await;
+        }
+    }
+}
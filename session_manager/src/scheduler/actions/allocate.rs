@@ -14,7 +14,7 @@ limitations under the License.
 use std::sync::Arc;
 use stdng::collections::BinaryHeap;
 
-use crate::model::{ALL_NODE, IDLE_EXECUTOR, OPEN_SESSION};
+use crate::model::{IDLE_EXECUTOR, OPEN_SESSION, READY_NODE};
 use crate::scheduler::actions::{Action, ActionPtr};
 use crate::scheduler::allocator::node_order_fn;
 use crate::scheduler::allocator::ssn_order_fn;
@@ -47,8 +47,11 @@ impl Action for AllocateAction {
             open_ssns.push(ssn.clone());
         }
 
-        let node_list = ss.find_nodes(ALL_NODE)?;
+        let node_list = ss.find_nodes(READY_NODE)?;
         for node in node_list.values() {
+            if node.unschedulable {
+                continue;
+            }
             nodes.push(node.clone());
         }
 
@@ -96,3 +99,49 @@ impl Action for AllocateAction {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller;
+    use crate::storage::tests::new_storage;
+    use common::apis::{Node, NodeState, ResourceRequirement};
+    use common::ctx::FlameContext;
+
+    fn allocate(controller: &controller::ControllerPtr) -> Result<(), FlameError> {
+        let mut ctx = Context::new(controller.clone(), &FlameContext::default())?;
+        tokio_test::block_on(AllocateAction::new_ptr().execute(&mut ctx))
+    }
+
+    #[test]
+    fn test_allocate_cordoned_node() -> Result<(), FlameError> {
+        let storage = new_storage("allocate_cordoned")?;
+        let controller = controller::new_ptr(storage.clone());
+
+        let slot = FlameContext::default().slot;
+        let node = Node {
+            name: "node-1".to_string(),
+            allocatable: ResourceRequirement {
+                cpu: slot.cpu * 4,
+                memory: slot.memory * 4,
+            },
+            state: NodeState::Ready,
+            ..Node::default()
+        };
+        tokio_test::block_on(controller.register_node(&node))?;
+
+        let ssn = tokio_test::block_on(controller.create_session("flmexec".to_string(), 1, None))?;
+        tokio_test::block_on(controller.create_task(ssn.id, None))?;
+
+        // No executor is created in the cordoned node.
+        controller.cordon_node(&node.name)?;
+        allocate(&controller)?;
+        assert!(storage.list_executors(&node.name)?.is_empty());
+
+        controller.uncordon_node(&node.name)?;
+        allocate(&controller)?;
+        assert_eq!(storage.list_executors(&node.name)?.len(), 1);
+
+        Ok(())
+    }
+}
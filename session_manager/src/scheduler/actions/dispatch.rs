@@ -50,6 +50,10 @@ impl Action for DispatchAction {
 
         let execs = ss.find_executors(IDLE_EXECUTOR)?;
         for exec in execs.values() {
+            // The executors of draining node will be released.
+            if ss.is_node_draining(&exec.node)? {
+                continue;
+            }
             idle_execs.push(exec.clone());
         }
 
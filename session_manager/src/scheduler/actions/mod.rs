@@ -21,11 +21,13 @@ use crate::FlameError;
 pub use allocate::AllocateAction;
 pub use backfill::BackfillAction;
 pub use dispatch::DispatchAction;
+pub use release::ReleaseAction;
 pub use shuffle::ShuffleAction;
 
 mod allocate;
 mod backfill;
 mod dispatch;
+mod release;
 mod shuffle;
 
 pub type ActionPtr = Arc<dyn Action>;
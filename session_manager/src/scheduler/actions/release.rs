@@ -0,0 +1,139 @@
+/*
+Copyright 2023 The Flame Authors.
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::sync::Arc;
+
+use chrono::Utc;
+
+use crate::model::{IDLE_EXECUTOR, OPEN_SESSION};
+use crate::scheduler::actions::{Action, ActionPtr};
+use crate::scheduler::Context;
+use crate::FlameError;
+
+use common::{trace::TraceFn, trace_fn};
+
+pub struct ReleaseAction {}
+
+impl ReleaseAction {
+    pub fn new_ptr() -> ActionPtr {
+        Arc::new(ReleaseAction {})
+    }
+}
+
+#[async_trait::async_trait]
+impl Action for ReleaseAction {
+    async fn execute(&self, ctx: &mut Context) -> Result<(), FlameError> {
+        trace_fn!("ReleaseAction::execute");
+        let ss = ctx.snapshot.clone();
+
+        let open_ssns = ss.find_sessions(OPEN_SESSION)?;
+        let execs = ss.find_executors(IDLE_EXECUTOR)?;
+
+        let now = Utc::now();
+        for exec in execs.values() {
+            // The executors of draining node are released by the node monitor.
+            if ss.is_node_draining(&exec.node)? {
+                continue;
+            }
+
+            if !ctx.allocator.is_reclaimable(exec)? {
+                continue;
+            }
+
+            // Keep the executor if it's still needed by open sessions, until idle timeout.
+            let needed = open_ssns
+                .values()
+                .any(|ssn| ctx.dispatcher.filter_one(exec, ssn));
+            let expired = exec
+                .idle_since
+                .map(|t| now - t > ctx.idle_timeout)
+                .unwrap_or(false);
+            if needed && !expired {
+                continue;
+            }
+
+            log::info!(
+                "Release idle executor <{}> on node <{}>, needed: {needed}, expired: {expired}.",
+                exec.id,
+                exec.node
+            );
+            if let Err(e) = ctx.allocator.release_executor(exec.clone()).await {
+                log::error!("Failed to release executor <{}>: {e}", exec.id);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::controller::{self, ControllerPtr};
+    use crate::storage::tests::new_storage;
+    use crate::storage::StoragePtr;
+    use common::apis::{ExecutorID, ExecutorState, SessionID};
+    use common::ctx::FlameContext;
+    use common::lock_ptr;
+
+    fn release(controller: &ControllerPtr) -> Result<(), FlameError> {
+        let mut ctx = Context::new(controller.clone(), &FlameContext::default())?;
+        tokio_test::block_on(ReleaseAction::new_ptr().execute(&mut ctx))
+    }
+
+    /// Create an idle executor, which has been idle for the duration.
+    fn new_idle_executor(
+        storage: &StoragePtr,
+        ssn_id: SessionID,
+        idle: Duration,
+    ) -> Result<ExecutorID, FlameError> {
+        let exe = tokio_test::block_on(storage.create_executor("node-1".to_string(), ssn_id))?;
+        let exe_ptr = storage.get_executor_ptr(exe.id.clone())?;
+        let mut exe = lock_ptr!(exe_ptr)?;
+        exe.state = ExecutorState::Idle;
+        exe.idle_since = Some(Utc::now() - idle);
+
+        Ok(exe.id.clone())
+    }
+
+    fn state(storage: &StoragePtr, id: &ExecutorID) -> Result<ExecutorState, FlameError> {
+        let exe_ptr = storage.get_executor_ptr(id.clone())?;
+        let exe = lock_ptr!(exe_ptr)?;
+        Ok(exe.state)
+    }
+
+    #[test]
+    fn test_release_idle_executors() -> Result<(), FlameError> {
+        let storage = new_storage("release_action")?;
+        let controller = controller::new_ptr(storage.clone());
+        let idle_timeout = FlameContext::default().idle_timeout;
+
+        let ssn = tokio_test::block_on(controller.create_session("flmexec".to_string(), 1, None))?;
+
+        // The executor is kept for the open session until the idle timeout.
+        let kept = new_idle_executor(&storage, ssn.id, Duration::seconds(1))?;
+        let expired = new_idle_executor(&storage, ssn.id, idle_timeout + Duration::seconds(1))?;
+        release(&controller)?;
+        assert_eq!(state(&storage, &kept)?, ExecutorState::Idle);
+        assert_eq!(state(&storage, &expired)?, ExecutorState::Releasing);
+
+        // The executor is released directly if no open session needs it.
+        tokio_test::block_on(controller.close_session(ssn.id))?;
+        release(&controller)?;
+        assert_eq!(state(&storage, &kept)?, ExecutorState::Releasing);
+
+        Ok(())
+    }
+}
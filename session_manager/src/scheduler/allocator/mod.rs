@@ -18,11 +18,11 @@ use std::sync::Arc;
 use stdng::collections;
 
 use crate::controller::ControllerPtr;
-use crate::model::{NodeInfo, NodeInfoPtr, SessionInfoPtr, SnapShotPtr};
+use crate::model::{ExecutorInfoPtr, NodeInfo, NodeInfoPtr, SessionInfoPtr, SnapShotPtr};
 use crate::scheduler::allocator::plugins::PluginManager;
 use crate::scheduler::allocator::plugins::PluginManagerPtr;
 use crate::scheduler::Context;
-use common::apis::{Node, Session};
+use common::apis::{ExecutorState, Node, Session};
 use common::FlameError;
 
 pub struct Allocator {
@@ -54,6 +54,10 @@ impl Allocator {
         self.plugins.is_allocatable(node, ssn)
     }
 
+    pub fn is_reclaimable(&self, exec: &ExecutorInfoPtr) -> Result<bool, FlameError> {
+        self.plugins.is_reclaimable(exec)
+    }
+
     pub async fn create_executor(
         &self,
         node: NodeInfoPtr,
@@ -75,6 +79,16 @@ impl Allocator {
 
         Ok(())
     }
+
+    pub async fn release_executor(&self, exec: ExecutorInfoPtr) -> Result<(), FlameError> {
+        self.controller.release_executor(exec.id.clone()).await?;
+        self.snapshot
+            .update_executor_state(exec.clone(), ExecutorState::Releasing)?;
+
+        log::debug!("Released executor <{}> on node <{}>", exec.id, exec.node);
+
+        Ok(())
+    }
 }
 
 pub fn ssn_order_fn(ctx: &Context) -> impl collections::Cmp<SessionInfoPtr> {
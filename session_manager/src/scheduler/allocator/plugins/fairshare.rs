@@ -17,7 +17,7 @@ use std::collections::HashMap;
 
 use crate::model::{
     ExecutorInfoPtr, NodeInfo, NodeInfoPtr, SessionInfo, SessionInfoPtr, SnapShot, ALL_APPLICATION,
-    ALL_EXECUTOR, ALL_NODE, OPEN_SESSION,
+    ALL_EXECUTOR, OPEN_SESSION, READY_NODE,
 };
 use crate::scheduler::allocator::plugins::{Plugin, PluginPtr};
 use common::apis::{SessionID, TaskState};
@@ -121,15 +121,8 @@ impl Plugin for FairShare {
         );
 
         for ssn in open_ssns.values() {
-            let mut desired = 0.0;
-            for state in [TaskState::Pending, TaskState::Running] {
-                if let Some(d) = ssn.tasks_status.get(&state) {
-                    desired += *d as f64 * ssn.slots as f64;
-                }
-            }
-
             if let Some(app) = apps.get(&ssn.application) {
-                desired = desired.min((app.max_instances * ssn.slots) as f64);
+                let desired = app.desired_slots(ssn);
 
                 self.ssn_map.insert(
                     ssn.id,
@@ -151,7 +144,7 @@ impl Plugin for FairShare {
 
         let mut remaining_slots = 0.0;
 
-        let nodes = ss.find_nodes(ALL_NODE)?;
+        let nodes = ss.find_nodes(READY_NODE)?;
         for node in nodes.values() {
             let allocatable = node.allocatable.to_slots(&ss.unit) as i32;
             remaining_slots += allocatable as f64;
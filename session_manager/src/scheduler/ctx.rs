@@ -13,14 +13,17 @@ limitations under the License.
 
 use std::sync::Arc;
 
+use chrono::Duration;
+
 use crate::controller::ControllerPtr;
 use crate::model::{ExecutorInfoPtr, SessionInfoPtr, SnapShotPtr};
 use crate::scheduler::actions::{
-    ActionPtr, AllocateAction, BackfillAction, DispatchAction, ShuffleAction,
+    ActionPtr, AllocateAction, BackfillAction, DispatchAction, ReleaseAction, ShuffleAction,
 };
 use crate::scheduler::allocator::{Allocator, AllocatorPtr};
 use crate::scheduler::dispatcher::{Dispatcher, DispatcherPtr};
 
+use common::ctx::FlameContext;
 use common::FlameError;
 
 const DEFAULT_SCHEDULE_INTERVAL: u64 = 500;
@@ -33,10 +36,12 @@ pub struct Context {
     pub dispatcher: DispatcherPtr,
     pub allocator: AllocatorPtr,
     pub schedule_interval: u64,
+    // The idle executors will be released after this timeout.
+    pub idle_timeout: Duration,
 }
 
 impl Context {
-    pub fn new(controller: ControllerPtr) -> Result<Self, FlameError> {
+    pub fn new(controller: ControllerPtr, flame_ctx: &FlameContext) -> Result<Self, FlameError> {
         let snapshot = controller.snapshot()?;
         // let plugins = PluginManager::setup(&snapshot.clone())?;
         let dispatcher = Arc::new(Dispatcher::new(snapshot.clone(), controller.clone())?);
@@ -54,8 +59,10 @@ impl Context {
                 DispatchAction::new_ptr(),
                 ShuffleAction::new_ptr(),
                 BackfillAction::new_ptr(),
+                ReleaseAction::new_ptr(),
             ],
             schedule_interval: DEFAULT_SCHEDULE_INTERVAL,
+            idle_timeout: flame_ctx.idle_timeout,
         })
     }
 }
@@ -48,7 +48,7 @@ impl Dispatcher {
     }
 
     pub fn filter_one(&self, exec: &ExecutorInfoPtr, ssn: &SessionInfoPtr) -> bool {
-        !self.filter(&[exec.clone()], ssn).is_empty()
+        !self.filter(std::slice::from_ref(exec), ssn).is_empty()
     }
 
     pub fn is_underused(&self, ssn: &SessionInfoPtr) -> Result<bool, FlameError> {
@@ -85,15 +85,8 @@ impl Plugin for FairShare {
         );
 
         for ssn in open_ssns.values() {
-            let mut desired = 0.0;
-            for state in [TaskState::Pending, TaskState::Running] {
-                if let Some(d) = ssn.tasks_status.get(&state) {
-                    desired += *d as f64 * ssn.slots as f64;
-                }
-            }
-
             if let Some(app) = apps.get(&ssn.application) {
-                desired = desired.min((app.max_instances * ssn.slots) as f64);
+                let desired = app.desired_slots(ssn);
 
                 self.ssn_map.insert(
                     ssn.id,
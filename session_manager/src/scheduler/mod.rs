@@ -37,9 +37,9 @@ struct ScheduleRunner {
 
 #[async_trait]
 impl FlameThread for ScheduleRunner {
-    async fn run(&self, _flame_ctx: FlameContext) -> Result<(), FlameError> {
+    async fn run(&self, flame_ctx: FlameContext) -> Result<(), FlameError> {
         loop {
-            let mut ctx = Context::new(self.controller.clone())?;
+            let mut ctx = Context::new(self.controller.clone(), &flame_ctx)?;
 
             for action in ctx.actions.clone() {
                 if let Err(e) = action.execute(&mut ctx).await {
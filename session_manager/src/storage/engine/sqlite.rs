@@ -24,9 +24,10 @@ use sqlx::{migrate::MigrateDatabase, types::Json, FromRow, Sqlite, SqlitePool};
 use crate::FlameError;
 use common::{
     apis::{
-        Application, ApplicationAttributes, ApplicationID, ApplicationSchema, ApplicationState,
-        CommonData, Session, SessionID, SessionState, SessionStatus, Shim, Task, TaskGID, TaskID,
-        TaskInput, TaskOutput, TaskState, DEFAULT_DELAY_RELEASE, DEFAULT_MAX_INSTANCES,
+        Application, ApplicationAttributes, ApplicationID, ApplicationSandbox, ApplicationSchema,
+        ApplicationState, CommonData, Session, SessionID, SessionState, SessionStatus, Shim, Task,
+        TaskGID, TaskID, TaskInput, TaskOutput, TaskState, DEFAULT_DELAY_RELEASE,
+        DEFAULT_MAX_INSTANCES,
     },
     trace::TraceFn,
     trace_fn,
@@ -43,19 +44,31 @@ struct AppSchemaDao {
     pub common_data: Option<String>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct AppSandboxDao {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub allow_network: bool,
+}
+
 #[derive(Clone, FromRow, Debug)]
 struct ApplicationDao {
     pub name: ApplicationID,
     pub image: Option<String>,
+    pub url: Option<String>,
+    pub checksum: Option<String>,
     pub description: Option<String>,
     pub labels: Option<Json<Vec<String>>>,
     pub command: Option<String>,
     pub arguments: Option<Json<Vec<String>>>,
     pub environments: Option<Json<HashMap<String, String>>>,
+    pub working_directory: Option<String>,
 
     pub max_instances: i32,
     pub delay_release: i64,
+    pub concurrency: i32,
     pub schema: Option<Json<AppSchemaDao>>,
+    pub sandbox: Option<Json<AppSandboxDao>>,
 
     pub shim: i32,
     pub creation_time: i64,
@@ -86,6 +99,7 @@ struct TaskDao {
     pub creation_time: i64,
     pub completion_time: Option<i64>,
 
+    pub retry_count: i32,
     pub state: i32,
 }
 
@@ -133,7 +147,7 @@ impl Engine for SqliteEngine {
             .await
             .map_err(|e| FlameError::Storage(format!("failed to begin TX: {e}")))?;
 
-        let sql = "INSERT INTO applications (name, description, labels, shim, command, arguments, environments, max_instances, delay_release, schema, creation_time, state) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, strftime ('%s', 'now'), 0) RETURNING *";
+        let sql = "INSERT INTO applications (name, description, labels, shim, command, arguments, environments, working_directory, max_instances, delay_release, schema, sandbox, url, checksum, concurrency, creation_time, state) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, strftime ('%s', 'now'), 0) RETURNING *";
         let app: ApplicationDao = sqlx::query_as(sql)
             .bind(name)
             .bind(attr.description)
@@ -142,9 +156,14 @@ impl Engine for SqliteEngine {
             .bind(attr.command)
             .bind(Json(attr.arguments))
             .bind(Json(attr.environments))
+            .bind(attr.working_directory)
             .bind(attr.max_instances)
             .bind(attr.delay_release.num_seconds())
             .bind(Json(attr.schema.clone().map(AppSchemaDao::from)))
+            .bind(attr.sandbox.clone().map(|s| Json(AppSandboxDao::from(s))))
+            .bind(attr.url)
+            .bind(attr.checksum)
+            .bind(attr.concurrency)
             .fetch_one(&mut *tx)
             .await
             .map_err(|e| FlameError::Storage(format!("failed to execute SQL: {e}")))?;
@@ -415,7 +434,7 @@ impl Engine for SqliteEngine {
             .await
             .map_err(|e| FlameError::Storage(e.to_string()))?;
 
-        let sql = r#"UPDATE tasks SET state=? WHERE id=? AND ssn_id=? RETURNING *"#;
+        let sql = r#"UPDATE tasks SET state=?, retry_count=retry_count+1 WHERE id=? AND ssn_id=? RETURNING *"#;
         let task: TaskDao = sqlx::query_as(sql)
             .bind(TaskState::Pending as i32)
             .bind(gid.task_id)
@@ -547,7 +566,9 @@ impl TryFrom<&TaskDao> for Task {
                 })
                 .transpose()?,
 
+            retry_count: task.retry_count,
             state: task.state.try_into()?,
+            progress: None,
         })
     }
 }
@@ -587,11 +608,14 @@ impl TryFrom<&ApplicationDao> for Application {
                 .clone()
                 .map(|envs| envs.0)
                 .unwrap_or_default(),
-            // TODO: make application configurable for work_dir.
-            working_directory: String::from("/tmp"),
+            working_directory: app.working_directory.clone().unwrap_or_default(),
             max_instances: app.max_instances,
             delay_release: Duration::seconds(app.delay_release),
             schema: app.schema.clone().map(|arg| arg.0.into()),
+            sandbox: app.sandbox.clone().map(|arg| arg.0.into()),
+            url: app.url.clone(),
+            checksum: app.checksum.clone(),
+            concurrency: app.concurrency,
         })
     }
 }
@@ -624,6 +648,26 @@ impl From<AppSchemaDao> for ApplicationSchema {
     }
 }
 
+impl From<ApplicationSandbox> for AppSandboxDao {
+    fn from(sandbox: ApplicationSandbox) -> Self {
+        Self {
+            uid: sandbox.uid,
+            gid: sandbox.gid,
+            allow_network: sandbox.allow_network,
+        }
+    }
+}
+
+impl From<AppSandboxDao> for ApplicationSandbox {
+    fn from(sandbox: AppSandboxDao) -> Self {
+        Self {
+            uid: sandbox.uid,
+            gid: sandbox.gid,
+            allow_network: sandbox.allow_network,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use common::apis::ApplicationState;
@@ -643,6 +687,39 @@ mod tests {
 
         assert_eq!(app_1.name, "flmexec");
         assert_eq!(app_1.state, ApplicationState::Enabled);
+        assert_eq!(app_1.working_directory, "/tmp");
+        assert_eq!(app_1.sandbox, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_application_attributes() -> Result<(), FlameError> {
+        let url = format!(
+            "sqlite:///tmp/flame_test_app_attrs_{}.db",
+            Utc::now().timestamp()
+        );
+        let storage = tokio_test::block_on(SqliteEngine::new_ptr(&url))?;
+
+        let sandbox = ApplicationSandbox {
+            uid: Some(1000),
+            gid: None,
+            allow_network: true,
+        };
+        let attr = ApplicationAttributes {
+            sandbox: Some(sandbox.clone()),
+            url: Some("file:///opt/flame/app.tar.gz".to_string()),
+            checksum: Some("0123abcd".to_string()),
+            concurrency: 8,
+            ..ApplicationAttributes::default()
+        };
+        tokio_test::block_on(storage.register_application("sandboxed".to_string(), attr))?;
+
+        let app = tokio_test::block_on(storage.get_application("sandboxed".to_string()))?;
+        assert_eq!(app.sandbox, Some(sandbox));
+        assert_eq!(app.url.as_deref(), Some("file:///opt/flame/app.tar.gz"));
+        assert_eq!(app.checksum.as_deref(), Some("0123abcd"));
+        assert_eq!(app.concurrency, 8);
 
         Ok(())
     }
@@ -807,4 +884,31 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_retry_task() -> Result<(), FlameError> {
+        let url = format!(
+            "sqlite:///tmp/flame_test_retry_task_{}.db",
+            Utc::now().timestamp()
+        );
+
+        let storage = tokio_test::block_on(SqliteEngine::new_ptr(&url))?;
+        for (name, attr) in common::default_applications() {
+            tokio_test::block_on(storage.register_application(name.clone(), attr))?;
+        }
+        let ssn_1 = tokio_test::block_on(storage.create_session("flmexec".to_string(), 1, None))?;
+
+        let task_1_1 = tokio_test::block_on(storage.create_task(ssn_1.id, None))?;
+        assert_eq!(task_1_1.retry_count, 0);
+
+        let task_1_1 =
+            tokio_test::block_on(storage.update_task(task_1_1.gid(), TaskState::Running, None))?;
+        assert_eq!(task_1_1.state, TaskState::Running);
+
+        let task_1_1 = tokio_test::block_on(storage.retry_task(task_1_1.gid()))?;
+        assert_eq!(task_1_1.state, TaskState::Pending);
+        assert_eq!(task_1_1.retry_count, 1);
+
+        Ok(())
+    }
 }
@@ -11,23 +11,24 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::sync::Arc;
+use tokio::sync::{broadcast, watch};
 use uuid::Uuid;
 
 use common::apis::{
     Application, ApplicationAttributes, ApplicationID, ApplicationPtr, CommonData, ExecutorID,
-    ExecutorState, Node, NodePtr, ResourceRequirement, Session, SessionID, SessionPtr,
-    SessionState, Task, TaskGID, TaskID, TaskInput, TaskOutput, TaskPtr, TaskState,
+    ExecutorState, Node, NodePtr, NodeState, ResourceRequirement, Session, SessionID, SessionPtr,
+    SessionState, Task, TaskGID, TaskID, TaskInput, TaskOutput, TaskProgress, TaskPtr, TaskState,
 };
 use common::ptr::{self, MutexPtr};
 use common::{ctx::FlameContext, lock_ptr, FlameError};
 
 use crate::model::{
     AppInfo, Executor, ExecutorInfo, ExecutorPtr, NodeInfo, NodeInfoPtr, SessionInfo,
-    SessionInfoPtr, SnapShot, SnapShotPtr,
+    SessionInfoPtr, SnapShot, SnapShotPtr, TaskEvent,
 };
 use crate::storage::engine::EnginePtr;
 
@@ -35,6 +36,9 @@ mod engine;
 
 pub type StoragePtr = Arc<Storage>;
 
+// The capacity of the task events for the slow watchers, which lose the oldest events.
+const TASK_EVENT_CAPACITY: usize = 1024;
+
 #[derive(Clone)]
 pub struct Storage {
     context: FlameContext,
@@ -42,7 +46,12 @@ pub struct Storage {
     sessions: MutexPtr<HashMap<SessionID, SessionPtr>>,
     executors: MutexPtr<HashMap<ExecutorID, ExecutorPtr>>,
     nodes: MutexPtr<HashMap<String, NodePtr>>,
+    heartbeats: MutexPtr<HashMap<String, DateTime<Utc>>>,
+    drains: MutexPtr<HashMap<String, DateTime<Utc>>>,
     applications: MutexPtr<HashMap<String, ApplicationPtr>>,
+    task_events: broadcast::Sender<TaskEvent>,
+    executor_events: watch::Sender<()>,
+    session_events: watch::Sender<()>,
 }
 
 pub async fn new_ptr(config: &FlameContext) -> Result<StoragePtr, FlameError> {
@@ -52,7 +61,12 @@ pub async fn new_ptr(config: &FlameContext) -> Result<StoragePtr, FlameError> {
         sessions: ptr::new_ptr(HashMap::new()),
         executors: ptr::new_ptr(HashMap::new()),
         nodes: ptr::new_ptr(HashMap::new()),
+        heartbeats: ptr::new_ptr(HashMap::new()),
+        drains: ptr::new_ptr(HashMap::new()),
         applications: ptr::new_ptr(HashMap::new()),
+        task_events: broadcast::channel(TASK_EVENT_CAPACITY).0,
+        executor_events: watch::channel(()).0,
+        session_events: watch::channel(()).0,
     }))
 }
 
@@ -109,8 +123,14 @@ impl Storage {
             let task_list = self.engine.find_tasks(ssn.id).await?;
             let mut ssn = ssn.clone();
             for task in task_list {
+                // The running tasks were interrupted by the restart rather than failed,
+                // so they're re-queued without counting a retry.
                 let task = match task.state {
-                    TaskState::Running => self.engine.retry_task(task.gid()).await?,
+                    TaskState::Running => {
+                        self.engine
+                            .update_task(task.gid(), TaskState::Pending, None)
+                            .await?
+                    }
                     _ => task,
                 };
 
@@ -126,50 +146,307 @@ impl Storage {
 
     pub async fn register_node(&self, node: &Node) -> Result<(), FlameError> {
         let mut node_map = lock_ptr!(self.nodes)?;
+        let node = Self::merge_node(&node_map, node)?;
         node_map.insert(node.name.clone(), ptr::new_ptr(node.clone()));
+
+        let mut heartbeats = lock_ptr!(self.heartbeats)?;
+        heartbeats.insert(node.name.clone(), Utc::now());
+
         Ok(())
     }
 
+    /// Sync the node and return the executors that the node should run; the
+    /// executors reported by the node but unknown to the server, e.g. they were
+    /// not adopted, are returned as Released, so the node will stop them.
     pub async fn sync_node(
         &self,
         node: &Node,
-        _: &Vec<Executor>,
-    ) -> Result<Vec<Executor>, FlameError> {
+        executors: &[Executor],
+    ) -> Result<(Node, Vec<Executor>), FlameError> {
         // trace_fn!("Storage::sync_node");
 
         let mut node_map = lock_ptr!(self.nodes)?;
+        let node = Self::merge_node(&node_map, node)?;
         node_map.insert(node.name.clone(), ptr::new_ptr(node.clone()));
 
+        {
+            let mut heartbeats = lock_ptr!(self.heartbeats)?;
+            heartbeats.insert(node.name.clone(), Utc::now());
+        }
+
         let mut res = vec![];
 
-        let mut exe_map = lock_ptr!(self.executors)?;
-        let execs = exe_map.values();
-        for exec in execs {
+        let exe_map = lock_ptr!(self.executors)?;
+        for exec in exe_map.values() {
             let exec = lock_ptr!(exec)?;
             if exec.node == node.name {
+                res.push(exec.clone());
+            }
+        }
+
+        for exec in executors {
+            if !exe_map.contains_key(&exec.id) {
+                log::warn!(
+                    "Executor <{}> in node <{}> is unknown, release it.",
+                    exec.id,
+                    node.name
+                );
                 res.push(Executor {
-                    id: exec.id.clone(),
-                    node: exec.node.clone(),
-                    resreq: exec.resreq.clone(),
-                    task_id: exec.task_id,
-                    ssn_id: exec.ssn_id,
-                    creation_time: exec.creation_time,
-                    state: exec.state,
+                    state: ExecutorState::Released,
+                    ..exec.clone()
                 });
             }
         }
 
         log::debug!("There are {} executors in node {}", res.len(), node.name);
 
+        Ok((node, res))
+    }
+
+    /// The spec of node, e.g. cordon, is managed by the server; keep it when
+    /// the node is updated by the executor manager.
+    fn merge_node(node_map: &HashMap<String, NodePtr>, node: &Node) -> Result<Node, FlameError> {
+        let mut node = node.clone();
+        if let Some(old) = node_map.get(&node.name) {
+            let old = lock_ptr!(old)?;
+            node.unschedulable = old.unschedulable;
+            node.draining = old.draining;
+        }
+
+        Ok(node)
+    }
+
+    /// Adopt the executors reported by the node but unknown to the server, e.g. the server
+    /// was restarted, with their reported state, session and tasks; so they keep running
+    /// instead of being released. The tasks of the adopted executors, which were re-queued
+    /// when the server was restarted, are launched to them again; return the adopted ones.
+    pub async fn adopt_executors(
+        &self,
+        node_name: &str,
+        executors: &[Executor],
+    ) -> Result<Vec<Executor>, FlameError> {
+        let mut res = vec![];
+
+        for exe in executors {
+            {
+                let exe_map = lock_ptr!(self.executors)?;
+                if exe_map.contains_key(&exe.id) {
+                    continue;
+                }
+            }
+
+            let mut exe = Executor {
+                node: node_name.to_string(),
+                creation_time: Utc::now(),
+                idle_since: None,
+                ..exe.clone()
+            };
+
+            match exe.state {
+                ExecutorState::Idle => {
+                    exe.ssn_id = None;
+                    exe.task_ids.clear();
+                    exe.idle_since = Some(Utc::now());
+                }
+                ExecutorState::Bound | ExecutorState::Unbinding => {
+                    // The session was deleted, release the executor.
+                    let Some(ssn_ptr) = exe.ssn_id.and_then(|id| self.get_session_ptr(id).ok())
+                    else {
+                        continue;
+                    };
+                    let ssn_id = lock_ptr!(ssn_ptr)?.id;
+
+                    let mut task_ids = vec![];
+                    for task_id in exe.task_ids.drain(..) {
+                        let Ok(task_ptr) = self.get_task_ptr(TaskGID { ssn_id, task_id }) else {
+                            continue;
+                        };
+                        // Only reclaim the re-queued tasks; the others are completed, or
+                        // launched to other executors.
+                        if lock_ptr!(task_ptr)?.state != TaskState::Pending {
+                            continue;
+                        }
+                        self.update_task(ssn_ptr.clone(), task_ptr, TaskState::Running, None)
+                            .await?;
+                        task_ids.push(task_id);
+                    }
+                    exe.task_ids = task_ids;
+                }
+                // The executor is not registered yet, or is releasing; release it.
+                _ => continue,
+            }
+
+            let mut exe_map = lock_ptr!(self.executors)?;
+            exe_map.insert(exe.id.clone(), ExecutorPtr::new(exe.clone().into()));
+            res.push(exe);
+        }
+
+        Ok(res)
+    }
+
+    /// Remove the executors which were started in the node but are lost: they're not
+    /// reported by the node anymore, e.g. the executor manager was restarted, or they're
+    /// reported as Released, e.g. they exited in the node; return them.
+    pub fn release_lost_executors(
+        &self,
+        node_name: &str,
+        executors: &[Executor],
+    ) -> Result<Vec<Executor>, FlameError> {
+        let mut res = vec![];
+
+        let mut exe_map = lock_ptr!(self.executors)?;
+        for exe in exe_map.values() {
+            let exe = lock_ptr!(exe)?;
+            // The Void executors are not started by the node yet.
+            if exe.node != node_name || exe.state == ExecutorState::Void {
+                continue;
+            }
+            let lost = match executors.iter().find(|e| e.id == exe.id) {
+                Some(reported) => reported.state == ExecutorState::Released,
+                None => true,
+            };
+            if lost {
+                res.push(exe.clone());
+            }
+        }
+
+        for exe in &res {
+            exe_map.remove(&exe.id);
+        }
+
         Ok(res)
     }
 
     pub async fn release_node(&self, node_name: &str) -> Result<(), FlameError> {
         let mut node_map = lock_ptr!(self.nodes)?;
         node_map.remove(node_name);
+
+        let mut heartbeats = lock_ptr!(self.heartbeats)?;
+        heartbeats.remove(node_name);
+
+        let mut drains = lock_ptr!(self.drains)?;
+        drains.remove(node_name);
+
         Ok(())
     }
 
+    pub fn cordon_node(&self, node_name: &str, unschedulable: bool) -> Result<(), FlameError> {
+        let node_ptr = self.get_node_ptr(node_name)?;
+        let mut node = lock_ptr!(node_ptr)?;
+        node.unschedulable = unschedulable;
+        if !unschedulable {
+            node.draining = false;
+            let mut drains = lock_ptr!(self.drains)?;
+            drains.remove(node_name);
+        }
+
+        Ok(())
+    }
+
+    pub fn drain_node(&self, node_name: &str, timeout: Duration) -> Result<(), FlameError> {
+        let node_ptr = self.get_node_ptr(node_name)?;
+        let mut node = lock_ptr!(node_ptr)?;
+        node.unschedulable = true;
+        if !node.draining {
+            node.draining = true;
+            let mut drains = lock_ptr!(self.drains)?;
+            drains.insert(node_name.to_string(), Utc::now() + timeout);
+        }
+
+        Ok(())
+    }
+
+    /// Return the draining nodes with their deadline.
+    pub fn draining_nodes(&self) -> Result<Vec<(String, DateTime<Utc>)>, FlameError> {
+        let drains = lock_ptr!(self.drains)?;
+        Ok(drains.iter().map(|(n, d)| (n.clone(), *d)).collect())
+    }
+
+    pub fn list_nodes(&self) -> Result<Vec<Node>, FlameError> {
+        let mut res = vec![];
+
+        let node_map = lock_ptr!(self.nodes)?;
+        for node in node_map.values() {
+            let node = lock_ptr!(node)?;
+            res.push(node.clone());
+        }
+
+        Ok(res)
+    }
+
+    pub fn get_node(&self, node_name: &str) -> Result<Node, FlameError> {
+        let node_ptr = self.get_node_ptr(node_name)?;
+        let node = lock_ptr!(node_ptr)?;
+
+        Ok(node.clone())
+    }
+
+    /// Keep the node alive, e.g. its stream is connected.
+    pub fn touch_node(&self, node_name: &str) -> Result<(), FlameError> {
+        let mut heartbeats = lock_ptr!(self.heartbeats)?;
+        heartbeats.insert(node_name.to_string(), Utc::now());
+
+        Ok(())
+    }
+
+    fn get_node_ptr(&self, node_name: &str) -> Result<NodePtr, FlameError> {
+        let node_map = lock_ptr!(self.nodes)?;
+        let node = node_map
+            .get(node_name)
+            .ok_or(FlameError::NotFound(node_name.to_string()))?;
+
+        Ok(node.clone())
+    }
+
+    pub fn list_executors(&self, node_name: &str) -> Result<Vec<Executor>, FlameError> {
+        let mut res = vec![];
+
+        let exe_map = lock_ptr!(self.executors)?;
+        for exe in exe_map.values() {
+            let exe = lock_ptr!(exe)?;
+            if exe.node == node_name {
+                res.push(exe.clone());
+            }
+        }
+
+        Ok(res)
+    }
+
+    pub fn release_executor(&self, id: &ExecutorID) -> Result<(), FlameError> {
+        let mut exe_map = lock_ptr!(self.executors)?;
+        exe_map.remove(id);
+
+        Ok(())
+    }
+
+    /// Mark the Ready nodes which did not sync within the grace period as NotReady,
+    /// and return their names.
+    pub fn expire_nodes(&self) -> Result<Vec<String>, FlameError> {
+        let now = Utc::now();
+        let mut res = vec![];
+
+        let node_map = lock_ptr!(self.nodes)?;
+        let heartbeats = lock_ptr!(self.heartbeats)?;
+        for (name, node) in node_map.iter() {
+            let mut node = lock_ptr!(node)?;
+            if node.state != NodeState::Ready {
+                continue;
+            }
+
+            let expired = match heartbeats.get(name) {
+                Some(last) => now - *last > self.context.node_grace_period,
+                None => true,
+            };
+
+            if expired {
+                node.state = NodeState::NotReady;
+                res.push(name.clone());
+            }
+        }
+
+        Ok(res)
+    }
+
     pub async fn create_session(
         &self,
         app: String,
@@ -187,11 +464,15 @@ impl Storage {
     pub async fn close_session(&self, id: SessionID) -> Result<Session, FlameError> {
         let ssn = self.engine.close_session(id).await?;
 
-        let ssn_ptr = self.get_session_ptr(ssn.id)?;
-        let mut ssn = lock_ptr!(ssn_ptr)?;
-        ssn.status.state = SessionState::Closed;
+        let ssn = {
+            let ssn_ptr = self.get_session_ptr(ssn.id)?;
+            let mut ssn = lock_ptr!(ssn_ptr)?;
+            ssn.status.state = SessionState::Closed;
+            ssn.clone()
+        };
+        self.session_events.send_replace(());
 
-        Ok(ssn.clone())
+        Ok(ssn)
     }
 
     pub fn get_session(&self, id: SessionID) -> Result<Session, FlameError> {
@@ -227,8 +508,11 @@ impl Storage {
     pub async fn delete_session(&self, id: SessionID) -> Result<Session, FlameError> {
         let ssn = self.engine.delete_session(id).await?;
 
-        let mut ssn_map = lock_ptr!(self.sessions)?;
-        ssn_map.remove(&ssn.id);
+        {
+            let mut ssn_map = lock_ptr!(self.sessions)?;
+            ssn_map.remove(&ssn.id);
+        }
+        self.session_events.send_replace(());
 
         Ok(ssn)
     }
@@ -252,13 +536,81 @@ impl Storage {
     ) -> Result<Task, FlameError> {
         let task = self.engine.create_task(ssn_id, task_input).await?;
 
-        let ssn = self.get_session_ptr(ssn_id)?;
-        let mut ssn = lock_ptr!(ssn)?;
-        ssn.update_task(&task);
+        {
+            let ssn = self.get_session_ptr(ssn_id)?;
+            let mut ssn = lock_ptr!(ssn)?;
+            ssn.update_task(&task);
+        }
+        self.publish(TaskEvent::StateChanged(task.clone()));
 
         Ok(task)
     }
 
+    pub fn list_task(&self, ssn_id: SessionID) -> Result<Vec<Task>, FlameError> {
+        let ssn_ptr = self.get_session_ptr(ssn_id)?;
+        let ssn = lock_ptr!(ssn_ptr)?;
+
+        let mut tasks = vec![];
+        for task in ssn.tasks.values() {
+            let task = lock_ptr!(task)?;
+            tasks.push(task.clone());
+        }
+
+        Ok(tasks)
+    }
+
+    /// Record the progress of the running task, and publish it with the partial output.
+    pub fn report_task(
+        &self,
+        gid: TaskGID,
+        progress: Option<TaskProgress>,
+        partial_output: Option<TaskOutput>,
+    ) -> Result<(), FlameError> {
+        let task_ptr = self.get_task_ptr(gid)?;
+        let task = {
+            let mut task = lock_ptr!(task_ptr)?;
+            if task.state != TaskState::Running {
+                return Err(FlameError::InvalidState(format!(
+                    "task <{gid}> is not running"
+                )));
+            }
+            if progress.is_some() {
+                task.progress = progress;
+            }
+            task.clone()
+        };
+
+        self.publish(TaskEvent::Reported(task, partial_output));
+
+        Ok(())
+    }
+
+    /// Subscribe the events of all tasks.
+    pub fn watch_tasks(&self) -> broadcast::Receiver<TaskEvent> {
+        self.task_events.subscribe()
+    }
+
+    fn publish(&self, event: TaskEvent) {
+        // It's ok that no one is watching the tasks.
+        let _ = self.task_events.send(event);
+    }
+
+    /// Subscribe the changes of the executors, e.g. bound to a session or released; it
+    /// only notifies the change, the watchers read the executors from the storage.
+    pub fn watch_executors(&self) -> watch::Receiver<()> {
+        self.executor_events.subscribe()
+    }
+
+    pub fn notify_executors(&self) {
+        self.executor_events.send_replace(());
+    }
+
+    /// Subscribe the changes of the sessions, e.g. closed or deleted; it only notifies the
+    /// change, the watchers read the sessions from the storage.
+    pub fn watch_sessions(&self) -> watch::Receiver<()> {
+        self.session_events.subscribe()
+    }
+
     pub fn get_task(&self, ssn_id: SessionID, id: TaskID) -> Result<Task, FlameError> {
         let ssn_map = lock_ptr!(self.sessions)?;
 
@@ -316,12 +668,45 @@ impl Storage {
 
         let task = self.engine.update_task(gid, state, output).await?;
 
-        let mut ssn_ptr = lock_ptr!(ssn)?;
-        ssn_ptr.update_task(&task);
+        {
+            let mut ssn_ptr = lock_ptr!(ssn)?;
+            ssn_ptr.update_task(&task);
+        }
+        self.publish(TaskEvent::StateChanged(task));
 
         Ok(())
     }
 
+    /// Re-queue the task if it does not reach the retry limit; otherwise, mark it as Failed.
+    pub async fn retry_task(&self, gid: TaskGID) -> Result<Task, FlameError> {
+        let ssn_ptr = self.get_session_ptr(gid.ssn_id)?;
+        let retry_count = {
+            let task_ptr = self.get_task_ptr(gid)?;
+            let task = lock_ptr!(task_ptr)?;
+            task.retry_count
+        };
+
+        let task = if retry_count < self.context.max_task_retries {
+            self.engine.retry_task(gid).await?
+        } else {
+            log::warn!(
+                "Task <{gid}> reached the retry limit <{}>, mark it as failed.",
+                self.context.max_task_retries
+            );
+            self.engine
+                .update_task(gid, TaskState::Failed, None)
+                .await?
+        };
+
+        {
+            let mut ssn = lock_ptr!(ssn_ptr)?;
+            ssn.update_task(&task);
+        }
+        self.publish(TaskEvent::StateChanged(task.clone()));
+
+        Ok(task)
+    }
+
     pub async fn create_executor(
         &self,
         node_name: String,
@@ -337,9 +722,10 @@ impl Storage {
             id: Uuid::new_v4().to_string(),
             node: node_name.clone(),
             resreq,
-            task_id: None,
+            task_ids: vec![],
             ssn_id: None,
             creation_time: Utc::now(),
+            idle_since: None,
             state: ExecutorState::Void,
         };
 
@@ -362,3 +748,209 @@ impl Storage {
         Ok(exe.clone())
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn new_storage(name: &str) -> Result<StoragePtr, FlameError> {
+        let ctx = FlameContext {
+            storage: format!(
+                "sqlite:///tmp/flame_test_{name}_{}.db",
+                Utc::now().timestamp()
+            ),
+            ..FlameContext::default()
+        };
+
+        let storage = tokio_test::block_on(new_ptr(&ctx))?;
+        for (name, attr) in common::default_applications() {
+            tokio_test::block_on(storage.register_application(name, attr))?;
+        }
+
+        Ok(storage)
+    }
+
+    #[test]
+    fn test_load_data_requeue_running_tasks() -> Result<(), FlameError> {
+        let storage = new_storage("load_data")?;
+        let ssn = tokio_test::block_on(storage.create_session("flmexec".to_string(), 1, None))?;
+        let task = tokio_test::block_on(storage.create_task(ssn.id, None))?;
+        tokio_test::block_on(
+            storage
+                .engine
+                .update_task(task.gid(), TaskState::Running, None),
+        )?;
+
+        // Restart the server with the same engine.
+        let restarted = Storage {
+            sessions: ptr::new_ptr(HashMap::new()),
+            ..(*storage).clone()
+        };
+        tokio_test::block_on(restarted.load_data())?;
+
+        let task = restarted.get_task(ssn.id, task.id)?;
+        assert_eq!(task.state, TaskState::Pending);
+        assert_eq!(task.retry_count, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_lost_executors() -> Result<(), FlameError> {
+        let storage = new_storage("lost_executors")?;
+        let ssn = tokio_test::block_on(storage.create_session("flmexec".to_string(), 1, None))?;
+
+        let mut ids = vec![];
+        for _ in 0..3 {
+            let exe = tokio_test::block_on(storage.create_executor("node-1".to_string(), ssn.id))?;
+            let exe_ptr = storage.get_executor_ptr(exe.id.clone())?;
+            lock_ptr!(exe_ptr)?.state = ExecutorState::Bound;
+            ids.push(exe.id);
+        }
+
+        // The node reports the first executor alive, the second one exited, and the
+        // third one is missing.
+        let reported: Vec<Executor> = storage
+            .list_executors("node-1")?
+            .into_iter()
+            .filter(|e| e.id != ids[2])
+            .map(|e| Executor {
+                state: if e.id == ids[0] {
+                    ExecutorState::Bound
+                } else {
+                    ExecutorState::Released
+                },
+                ..e
+            })
+            .collect();
+
+        let mut lost: Vec<String> = storage
+            .release_lost_executors("node-1", &reported)?
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        lost.sort();
+        let mut expected = vec![ids[1].clone(), ids[2].clone()];
+        expected.sort();
+        assert_eq!(lost, expected);
+
+        let remaining = storage.list_executors("node-1")?;
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, ids[0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_adopt_executors_after_restart() -> Result<(), FlameError> {
+        let storage = new_storage("adopt_executors")?;
+        let ssn = tokio_test::block_on(storage.create_session("flmexec".to_string(), 1, None))?;
+        let task = tokio_test::block_on(storage.create_task(ssn.id, None))?;
+        tokio_test::block_on(
+            storage
+                .engine
+                .update_task(task.gid(), TaskState::Running, None),
+        )?;
+
+        // Restart the server with the same engine, and re-register the node.
+        let restarted = Storage {
+            sessions: ptr::new_ptr(HashMap::new()),
+            executors: ptr::new_ptr(HashMap::new()),
+            nodes: ptr::new_ptr(HashMap::new()),
+            heartbeats: ptr::new_ptr(HashMap::new()),
+            ..(*storage).clone()
+        };
+        tokio_test::block_on(restarted.load_data())?;
+        let node = Node {
+            name: "node-1".to_string(),
+            ..Node::default()
+        };
+        tokio_test::block_on(restarted.register_node(&node))?;
+
+        // The node reports the executor running the task, an idle one, a registering
+        // one and one bound to an unknown session.
+        let new_executor = |id: &str, state, ssn_id, task_ids| Executor {
+            id: id.to_string(),
+            node: node.name.clone(),
+            resreq: ResourceRequirement::default(),
+            task_ids,
+            ssn_id,
+            creation_time: Utc::now(),
+            idle_since: None,
+            state,
+        };
+        let reported = vec![
+            new_executor("exe-1", ExecutorState::Bound, Some(ssn.id), vec![task.id]),
+            new_executor("exe-2", ExecutorState::Idle, None, vec![]),
+            new_executor("exe-3", ExecutorState::Void, None, vec![]),
+            new_executor("exe-4", ExecutorState::Bound, Some(ssn.id + 1), vec![1]),
+        ];
+
+        let mut adopted: Vec<String> =
+            tokio_test::block_on(restarted.adopt_executors(&node.name, &reported))?
+                .into_iter()
+                .map(|e| e.id)
+                .collect();
+        adopted.sort();
+        assert_eq!(adopted, vec!["exe-1".to_string(), "exe-2".to_string()]);
+
+        // The task is launched to the adopted executor again.
+        let task = restarted.get_task(ssn.id, task.id)?;
+        assert_eq!(task.state, TaskState::Running);
+        let exe_ptr = restarted.get_executor_ptr("exe-1".to_string())?;
+        assert_eq!(lock_ptr!(exe_ptr)?.task_ids, vec![task.id]);
+
+        // The executors which were not adopted are released.
+        let (_, executors) = tokio_test::block_on(restarted.sync_node(&node, &reported))?;
+        assert_eq!(executors.len(), reported.len());
+        for exe in executors {
+            let expected = match exe.id.as_str() {
+                "exe-1" => ExecutorState::Bound,
+                "exe-2" => ExecutorState::Idle,
+                _ => ExecutorState::Released,
+            };
+            assert_eq!(exe.state, expected, "executor <{}>", exe.id);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cordon_and_drain_node() -> Result<(), FlameError> {
+        let storage = new_storage("drain_node")?;
+        let node = Node {
+            name: "node-1".to_string(),
+            ..Node::default()
+        };
+        tokio_test::block_on(storage.register_node(&node))?;
+
+        // The node is cordoned by the server; it's kept when the node syncs itself.
+        storage.cordon_node(&node.name, true)?;
+        tokio_test::block_on(storage.sync_node(&node, &[]))?;
+        let nodes = storage.list_nodes()?;
+        assert!(nodes[0].unschedulable);
+        assert!(!nodes[0].draining);
+
+        // The draining node is unschedulable until it's uncordoned; and the deadline
+        // is not extended by draining it again.
+        storage.drain_node(&node.name, Duration::seconds(60))?;
+        let deadline = storage.draining_nodes()?[0].1;
+        storage.drain_node(&node.name, Duration::seconds(120))?;
+        assert_eq!(
+            storage.draining_nodes()?,
+            vec![(node.name.clone(), deadline)]
+        );
+        tokio_test::block_on(storage.register_node(&node))?;
+        let nodes = storage.list_nodes()?;
+        assert!(nodes[0].unschedulable);
+        assert!(nodes[0].draining);
+
+        storage.cordon_node(&node.name, false)?;
+        let nodes = storage.list_nodes()?;
+        assert!(!nodes[0].unschedulable);
+        assert!(!nodes[0].draining);
+        assert!(storage.draining_nodes()?.is_empty());
+
+        Ok(())
+    }
+}